@@ -0,0 +1,49 @@
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::common::errors::FieldError;
+
+/// Answers plain `OPTIONS` requests with the methods routed for the path. The request is
+/// replayed with a method no route accepts, so axum reports the routed methods in `Allow`.
+pub async fn allow_methods(request: Request, next: Next) -> Response {
+    if request.method() != Method::OPTIONS
+        || request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+    {
+        let mut response = next.run(request).await;
+        if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            let allow = with_options(response.headers().get(header::ALLOW));
+            response.headers_mut().insert(header::ALLOW, allow);
+        }
+        return response;
+    }
+
+    let (mut parts, _) = request.into_parts();
+    parts.method = Method::from_bytes(b"DISCOVER").unwrap();
+    let response = next.run(Request::from_parts(parts, Body::empty())).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let allow = with_options(response.headers().get(header::ALLOW));
+    (StatusCode::NO_CONTENT, [(header::ALLOW, allow)]).into_response()
+}
+
+fn with_options(allow: Option<&HeaderValue>) -> HeaderValue {
+    let allow = match allow.and_then(|allow| allow.to_str().ok()) {
+        Some(allow) if !allow.is_empty() => format!("{},OPTIONS", allow),
+        _ => "OPTIONS".to_string(),
+    };
+    HeaderValue::from_str(&allow).unwrap()
+}
+
+/// Method fallback for every routed path, so a path that exists but doesn't accept the
+/// method answers 405 with the error body instead of falling through to the router
+/// fallback. axum fills in `Allow` with the methods routed for the path.
+pub async fn method_not_allowed() -> FieldError {
+    FieldError::MethodNotAllowed
+}
@@ -1,7 +1,11 @@
 use sea_orm::*;
 
+use super::de::from_str;
+use super::models::AttachmentText;
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
+use crate::common::forms::TimeRange;
+use crate::common::utils::{get_cursor_ascending, get_order_by};
 use crate::entity::{content, content::Entity as Content};
 use crate::AppState;
 
@@ -11,7 +15,7 @@ pub async fn create_attachment_with_params(
     now: u32,
     text: &str,
     uid: u32,
-) -> Result<content::ActiveModel, FieldError> {
+) -> Result<content::Model, FieldError> {
     content::ActiveModel {
         r#type: Set("attachment".to_string()),
         title: Set(Some(name.to_owned())),
@@ -22,7 +26,7 @@ pub async fn create_attachment_with_params(
         author_id: Set(uid),
         ..Default::default()
     }
-    .save(&state.conn)
+    .insert(&state.conn)
     .await
     .map_err(|_| FieldError::DatabaseFailed("create attachment failed".to_string()))
 }
@@ -52,33 +56,178 @@ pub async fn modify_attachment_by_cid_with_params(
         .map_err(|_| FieldError::DatabaseFailed("modify attachment failed".to_string()))
 }
 
+pub async fn modify_attachment_meta_by_cid(
+    state: &AppState,
+    cid: u32,
+    title: Option<&str>,
+    slug: Option<&str>,
+    text: Option<&str>,
+    now: u32,
+) -> Result<content::Model, FieldError> {
+    let exist_attachment = common_db::get_content_by_cid(state, cid).await?;
+    if exist_attachment.is_none() {
+        return Err(FieldError::InvalidParams("cid".to_string()));
+    }
+    let exist_attachment = exist_attachment.unwrap();
+    let mut c = content::ActiveModel::from(exist_attachment);
+    if let Some(title) = title {
+        c.title = Set(Some(title.to_owned()));
+    }
+    if let Some(slug) = slug {
+        c.slug = Set(Some(slug.to_owned()));
+    }
+    if let Some(text) = text {
+        c.text = Set(Some(text.to_owned()));
+    }
+    c.modified = Set(now);
+    c.update(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("modify attachment failed".to_string()))
+}
+
+/// Stored paths of every attachment, trashed ones included. A row that fails to decode is an
+/// error rather than skipped, otherwise pruning would take its file for an orphan.
+pub async fn get_attachment_paths(state: &AppState) -> Result<Vec<String>, FieldError> {
+    let texts: Vec<Option<String>> = Content::find()
+        .select_only()
+        .column(content::Column::Text)
+        .filter(content::Column::Type.eq("attachment"))
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch attachments failed".to_string()))?;
+    texts
+        .into_iter()
+        .flatten()
+        .map(|t| {
+            from_str::<AttachmentText>(&t)
+                .map(|at| at.path)
+                .map_err(|_| FieldError::DatabaseFailed("attachment decode error".to_string()))
+        })
+        .collect()
+}
+
+const DOCUMENT_MIME_PREFIXES: [&str; 5] = [
+    "text/",
+    "application/pdf",
+    "application/msword",
+    "application/rtf",
+    "application/vnd.",
+];
+
+fn get_kind_mime_prefixes(kind: &str) -> Vec<&'static str> {
+    match kind {
+        "image" => vec!["image/"],
+        "audio" => vec!["audio/"],
+        "video" => vec!["video/"],
+        "document" => DOCUMENT_MIME_PREFIXES.to_vec(),
+        _ => vec![],
+    }
+}
+
+fn get_mime_like(mime_prefix: &str) -> String {
+    format!("%s:4:\"mime\";s:%:\"{mime_prefix}%")
+}
+
+/// The mime only lives in the serialized text column, so matching is a LIKE over it.
+/// This keeps the Typecho schema untouched and counts exact, but cannot use an index.
+fn filter_attachments_by_mime(
+    stmt: Select<Content>,
+    mime_prefix: Option<&str>,
+    kind: Option<&str>,
+) -> Select<Content> {
+    let stmt = match mime_prefix {
+        Some(mime_prefix) => stmt.filter(content::Column::Text.like(get_mime_like(mime_prefix))),
+        None => stmt,
+    };
+    match kind {
+        Some("other") => {
+            let mut condition = Condition::all();
+            for k in ["image", "audio", "video", "document"] {
+                for prefix in get_kind_mime_prefixes(k) {
+                    condition =
+                        condition.add(content::Column::Text.not_like(get_mime_like(prefix)));
+                }
+            }
+            stmt.filter(condition)
+        }
+        Some(kind) => {
+            let mut condition = Condition::any();
+            for prefix in get_kind_mime_prefixes(kind) {
+                condition = condition.add(content::Column::Text.like(get_mime_like(prefix)));
+            }
+            stmt.filter(condition)
+        }
+        None => stmt,
+    }
+}
+
+pub async fn get_attachments_count_by_list_query(
+    state: &AppState,
+    private: bool,
+    status: Option<&str>,
+    mime_prefix: Option<&str>,
+    kind: Option<&str>,
+    range: &TimeRange,
+    author: Option<u32>,
+) -> Result<u64, FieldError> {
+    let stmt = Content::find().filter(content::Column::Type.eq("attachment"));
+    let stmt = stmt.apply_if(author, |stmt, uid| {
+        stmt.filter(content::Column::AuthorId.eq(uid))
+    });
+    let stmt = common_db::filter_contents_by_status(stmt, private, status);
+    let stmt = filter_attachments_by_mime(stmt, mime_prefix, kind);
+    let stmt = common_db::filter_contents_by_time_range(stmt, range);
+    stmt.count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count attachments failed".to_string()))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn get_attachments_by_list_query(
     state: &AppState,
     private: bool,
+    status: Option<&str>,
+    mime_prefix: Option<&str>,
+    kind: Option<&str>,
+    range: &TimeRange,
+    author: Option<u32>,
     page_size: u64,
     page: u64,
     order_by: &str,
+    after: Option<u32>,
 ) -> Result<Vec<content::Model>, FieldError> {
     let stmt = Content::find().filter(content::Column::Type.eq("attachment"));
-    let stmt = if private {
-        stmt
-    } else {
-        stmt.filter(content::Column::Status.eq("publish"))
+    let stmt = stmt.apply_if(author, |stmt, uid| {
+        stmt.filter(content::Column::AuthorId.eq(uid))
+    });
+    let stmt = match after {
+        None | Some(0) => stmt,
+        Some(after) if get_cursor_ascending(order_by, "cid") == Some(true) => {
+            stmt.filter(content::Column::Cid.gt(after))
+        }
+        Some(after) => stmt.filter(content::Column::Cid.lt(after)),
     };
-    let stmt = match order_by {
-        "-cid" => stmt.order_by_desc(content::Column::Cid),
-        "cid" => stmt.order_by_asc(content::Column::Cid),
-        "-slug" => stmt.order_by_desc(content::Column::Slug),
-        "slug" => stmt.order_by_asc(content::Column::Slug),
-        _ => stmt.order_by_desc(content::Column::Cid),
-    };
-
-    let paginator = stmt.paginate(&state.conn, page_size);
+    let stmt = common_db::filter_contents_by_status(stmt, private, status);
+    let stmt = filter_attachments_by_mime(stmt, mime_prefix, kind);
+    let stmt = common_db::filter_contents_by_time_range(stmt, range);
+    let (column, order) = get_order_by(
+        order_by,
+        &[
+            ("cid", content::Column::Cid),
+            ("slug", content::Column::Slug),
+        ],
+    )?;
+    // ties on the chosen column would shuffle rows between pages
+    let stmt = stmt
+        .order_by(column, order.clone())
+        .order_by(content::Column::Cid, order);
 
-    let contents = paginator
-        .fetch_page(page - 1)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))?;
+    let contents = match after {
+        Some(_) => stmt.limit(page_size).all(&state.conn).await,
+        None => stmt.paginate(&state.conn, page_size).fetch_page(page - 1).await,
+    }
+    .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))?;
     Ok(contents)
 }
 
@@ -93,6 +242,36 @@ pub async fn get_attachments_by_parent(
         .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))
 }
 
+pub async fn get_attachments_after_cid(
+    state: &AppState,
+    after: u32,
+    cid: Option<u32>,
+    limit: u64,
+) -> Result<Vec<content::Model>, FieldError> {
+    Content::find()
+        .filter(content::Column::Type.eq("attachment"))
+        .filter(content::Column::Cid.gt(after))
+        .apply_if(cid, |stmt, cid| stmt.filter(content::Column::Cid.eq(cid)))
+        .order_by_asc(content::Column::Cid)
+        .limit(limit)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch attachments failed".to_string()))
+}
+
+pub async fn get_attachments_by_author(
+    state: &AppState,
+    author_id: u32,
+) -> Result<Vec<content::Model>, FieldError> {
+    Content::find()
+        .filter(content::Column::Type.eq("attachment"))
+        .filter(content::Column::AuthorId.eq(author_id))
+        .filter(content::Column::Status.ne("trash"))
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch attachments failed".to_string()))
+}
+
 pub async fn modify_attachment_parent_by_cid(
     state: &AppState,
     cid: u32,
@@ -109,3 +288,42 @@ pub async fn modify_attachment_parent_by_cid(
         .await
         .map_err(|_| FieldError::DatabaseFailed("modify attachment failed".to_string()))
 }
+
+pub async fn modify_attachment_author_by_cid(
+    state: &AppState,
+    cid: u32,
+    uid: u32,
+) -> Result<content::Model, FieldError> {
+    let exist_attachment = common_db::get_content_by_cid(state, cid).await?;
+    if exist_attachment.is_none() {
+        return Err(FieldError::InvalidParams("cid".to_string()));
+    }
+    let exist_attachment = exist_attachment.unwrap();
+    let mut c = content::ActiveModel::from(exist_attachment);
+    c.author_id = Set(uid);
+    c.update(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("modify attachment failed".to_string()))
+}
+
+pub async fn modify_attachments_author_by_author(
+    state: &AppState,
+    from_uid: u32,
+    uid: u32,
+) -> Result<u64, FieldError> {
+    let res = Content::update_many()
+        .col_expr(content::Column::AuthorId, sea_query::Expr::value(uid))
+        .filter(content::Column::Type.eq("attachment"))
+        .filter(content::Column::AuthorId.eq(from_uid))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("reassign attachments failed".to_string()))?;
+    Ok(res.rows_affected)
+}
+
+#[test]
+fn test_get_mime_like() {
+    assert_eq!(get_mime_like("image/"), r#"%s:4:"mime";s:%:"image/%"#);
+    assert_eq!(get_kind_mime_prefixes("audio"), vec!["audio/"]);
+    assert!(get_kind_mime_prefixes("other").is_empty());
+}
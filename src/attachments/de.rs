@@ -214,11 +214,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         unimplemented!()
     }
 
-    fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_some(self)
     }
 
     fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
@@ -362,6 +362,7 @@ fn test_from_string() {
         size: 14660,
         r#type: "png".to_string(),
         mime: "image/png".to_string(),
+        sha256: None,
     };
     assert_eq!(expected, from_str(j).unwrap());
 
@@ -372,6 +373,18 @@ fn test_from_string() {
         size: 25523,
         r#type: "png".to_string(),
         mime: "image/png".to_string(),
+        sha256: None,
+    };
+    assert_eq!(expected, from_str(j).unwrap());
+
+    let j = r#"a:6:{s:4:"name";s:5:"a.png";s:4:"path";s:26:"/usr/uploads/2022/12/1.png";s:4:"size";i:1;s:4:"type";s:3:"png";s:4:"mime";s:9:"image/png";s:6:"sha256";s:2:"ab";}"#;
+    let expected = AttachmentText {
+        name: "a.png".to_string(),
+        path: "/usr/uploads/2022/12/1.png".to_string(),
+        size: 1,
+        r#type: "png".to_string(),
+        mime: "image/png".to_string(),
+        sha256: Some("ab".to_string()),
     };
     assert_eq!(expected, from_str(j).unwrap());
 }
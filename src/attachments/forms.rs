@@ -1,5 +1,40 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::common::forms::TimeRange;
+
+pub const ATTACHMENT_ORDER_BY: [&str; 4] = ["cid", "-cid", "slug", "-slug"];
+
+pub const ATTACHMENT_KINDS: [&str; 5] = ["image", "audio", "video", "document", "other"];
+
+fn validate_kind(kind: &str) -> Result<(), ValidationError> {
+    if ATTACHMENT_KINDS.contains(&kind) {
+        return Ok(());
+    }
+    Err(ValidationError::new("kind").with_message(Cow::from("kind is unknown")))
+}
+
+fn validate_mime_prefix(mime_prefix: &str) -> Result<(), ValidationError> {
+    if mime_prefix
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "/+.-".contains(c))
+    {
+        return Ok(());
+    }
+    Err(ValidationError::new("mime_prefix").with_message(Cow::from(
+        "mime_prefix must only contain a-z, 0-9, /, +, . and -",
+    )))
+}
+
+fn validate_order_by(order_by: &str) -> Result<(), ValidationError> {
+    if ATTACHMENT_ORDER_BY.contains(&order_by) {
+        return Ok(());
+    }
+    Err(ValidationError::new("order_by")
+        .with_message(Cow::from("order_by must be one of cid, -cid, slug, -slug")))
+}
 
 #[derive(Serialize, Deserialize, Validate)]
 pub struct AttachmentsQuery {
@@ -7,13 +42,111 @@ pub struct AttachmentsQuery {
     pub page: Option<u64>,
     #[validate(range(min = 1, message = "page_size must greater than 1"))]
     pub page_size: Option<u64>,
-    #[validate(length(min = 1, max = 13, message = "order_by length must greater than 1"))]
+    #[validate(custom(function = "validate_order_by"))]
     pub order_by: Option<String>,
     pub private: Option<bool>,
+    pub after: Option<u32>,
+    #[validate(length(min = 1, max = 16, message = "status length must greater than 1"))]
+    pub status: Option<String>,
+    #[validate(
+        length(
+            min = 1,
+            max = 64,
+            message = "mime_prefix length must between 1 and 64"
+        ),
+        custom(function = "validate_mime_prefix")
+    )]
+    pub mime_prefix: Option<String>,
+    #[validate(custom(function = "validate_kind"))]
+    pub kind: Option<String>,
+    pub created_after: Option<u32>,
+    pub created_before: Option<u32>,
+    pub modified_after: Option<u32>,
+    pub modified_before: Option<u32>,
+    pub author: Option<u32>,
+}
+
+impl AttachmentsQuery {
+    pub fn time_range(&self) -> TimeRange {
+        TimeRange {
+            created_after: self.created_after,
+            created_before: self.created_before,
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Validate)]
 pub struct AttachmentCreate {
     #[validate(range(min = 1, message = "cid must greater than 1"))]
     pub cid: u32,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct AttachmentModify {
+    #[validate(length(min = 1, max = 150, message = "name length must between 1 and 150"))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 150, message = "title length must between 1 and 150"))]
+    pub title: Option<String>,
+    #[validate(length(min = 1, max = 150, message = "slug length must between 1 and 150"))]
+    pub slug: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct AttachmentsPrune {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct AttachmentsVerify {
+    #[validate(range(min = 1, message = "cid must greater than 1"))]
+    pub cid: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct AttachmentAuthor {
+    #[validate(range(min = 1, message = "uid must greater than 1"))]
+    pub uid: u32,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct AttachmentsReassign {
+    #[validate(range(min = 1, message = "from_uid must greater than 1"))]
+    pub from_uid: u32,
+    #[validate(range(min = 1, message = "uid must greater than 1"))]
+    pub uid: u32,
+}
+#[test]
+fn test_validate_attachments_query() {
+    let query = |order_by: &str| AttachmentsQuery {
+        page: None,
+        page_size: None,
+        order_by: Some(order_by.to_string()),
+        private: None,
+        after: None,
+        status: None,
+        mime_prefix: None,
+        kind: None,
+        created_after: None,
+        created_before: None,
+        modified_after: None,
+        modified_before: None,
+    };
+
+    assert!(query("cid").validate().is_ok());
+    assert!(query("-slug").validate().is_ok());
+    assert!(query("created").validate().is_err());
+    assert!(query("invalidkey").validate().is_err());
+    assert!(query("").validate().is_err());
+
+    let mut q = query("cid");
+    q.mime_prefix = Some("image/".to_string());
+    q.kind = Some("document".to_string());
+    assert!(q.validate().is_ok());
+    q.mime_prefix = Some("image/%".to_string());
+    assert!(q.validate().is_err());
+    q.mime_prefix = None;
+    q.kind = Some("picture".to_string());
+    assert!(q.validate().is_err());
+}
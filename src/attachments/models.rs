@@ -10,6 +10,9 @@ pub struct AttachmentText {
     pub size: u64,
     pub r#type: String,
     pub mime: String,
+    /// Hex SHA-256 recorded on upload, missing for files stored before it was kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -52,3 +55,11 @@ impl From<content::Model> for AttachmentInfo {
         }
     }
 }
+
+/// Total decoded `size` of the given attachments, unreadable ones count as empty.
+pub fn get_attachments_size(attachments: Vec<content::Model>) -> u64 {
+    attachments
+        .into_iter()
+        .map(|a| AttachmentInfo::from(a).size)
+        .sum()
+}
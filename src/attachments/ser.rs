@@ -93,11 +93,11 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         unimplemented!()
     }
 
-    fn serialize_some<T>(self, _v: &T) -> Result<()>
+    fn serialize_some<T>(self, v: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        unimplemented!()
+        v.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<()> {
@@ -318,6 +318,7 @@ fn test_to_string() {
         size: 14660,
         r#type: "png".to_string(),
         mime: "image/png".to_string(),
+        sha256: None,
     };
     let expected = r#"a:5:{s:4:"name";s:17:"mate10-emui-1.png";s:4:"path";s:35:"/usr/uploads/2022/12/3448342241.png";s:4:"size";i:14660;s:4:"type";s:3:"png";s:4:"mime";s:9:"image/png";}"#;
     assert_eq!(to_string(&s).unwrap(), expected);
@@ -328,7 +329,19 @@ fn test_to_string() {
         size: 25523,
         r#type: "png".to_string(),
         mime: "image/png".to_string(),
+        sha256: None,
     };
     let expected = r#"a:5:{s:4:"name";s:22:"https-get-status-1.png";s:4:"path";s:35:"/usr/uploads/2022/12/2415673155.png";s:4:"size";i:25523;s:4:"type";s:3:"png";s:4:"mime";s:9:"image/png";}"#;
     assert_eq!(to_string(&s).unwrap(), expected);
+
+    let s = AttachmentText {
+        name: "a.png".to_string(),
+        path: "/usr/uploads/2022/12/1.png".to_string(),
+        size: 1,
+        r#type: "png".to_string(),
+        mime: "image/png".to_string(),
+        sha256: Some("ab".to_string()),
+    };
+    let expected = r#"a:6:{s:4:"name";s:5:"a.png";s:4:"path";s:26:"/usr/uploads/2022/12/1.png";s:4:"size";i:1;s:4:"type";s:3:"png";s:4:"mime";s:9:"image/png";s:6:"sha256";s:2:"ab";}"#;
+    assert_eq!(to_string(&s).unwrap(), expected);
 }
@@ -5,23 +5,42 @@ use axum::{
 use std::sync::Arc;
 
 use super::views;
+use crate::allow::method_not_allowed;
 use crate::AppState;
 
 pub fn attachments_routers(ro: bool) -> Router<Arc<AppState>> {
     let attachments_route = Router::new()
-        .route("/api/attachments/", get(views::list_attachments))
-        .route("/api/attachments/:cid", get(views::get_attachment_by_cid))
+        .route(
+            "/api/attachments/",
+            get(views::list_attachments).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/attachments/verify",
+            get(views::verify_attachment_integrity).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/attachments/:cid",
+            get(views::get_attachment_by_cid).fallback(method_not_allowed),
+        )
         .route(
             "/api/pages/:slug/attachments/",
-            get(views::list_content_attachments_by_slug),
+            get(views::list_content_attachments_by_slug).fallback(method_not_allowed),
         )
         .route(
             "/api/posts/:slug/attachments/",
-            get(views::list_content_attachments_by_slug),
+            get(views::list_content_attachments_by_slug).fallback(method_not_allowed),
         );
     if !ro {
         attachments_route
             .route("/api/attachments/", post(views::create_attachment))
+            .route(
+                "/api/attachments/reassign",
+                post(views::reassign_attachments_author).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/attachments/prune",
+                post(views::prune_attachments).fallback(method_not_allowed),
+            )
             .route(
                 "/api/attachments/:cid",
                 patch(views::modify_attachment_by_cid),
@@ -30,6 +49,14 @@ pub fn attachments_routers(ro: bool) -> Router<Arc<AppState>> {
                 "/api/attachments/:cid",
                 delete(views::delete_attachment_by_cid),
             )
+            .route(
+                "/api/attachments/:cid/meta",
+                patch(views::patch_attachment_by_cid).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/attachments/:cid/author",
+                patch(views::reassign_attachment_author).fallback(method_not_allowed),
+            )
             .route(
                 "/api/pages/:slug/attachments/",
                 post(views::add_attachment_to_content_by_cid),
@@ -40,11 +67,11 @@ pub fn attachments_routers(ro: bool) -> Router<Arc<AppState>> {
             )
             .route(
                 "/api/pages/:slug/attachments/:cid",
-                delete(views::delete_attachment_from_content_by_cid),
+                delete(views::delete_attachment_from_content_by_cid).fallback(method_not_allowed),
             )
             .route(
                 "/api/posts/:slug/attachments/:cid",
-                delete(views::delete_attachment_from_content_by_cid),
+                delete(views::delete_attachment_from_content_by_cid).fallback(method_not_allowed),
             )
     } else {
         attachments_route
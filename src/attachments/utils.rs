@@ -1,13 +1,47 @@
 use axum::{body::Bytes, BoxError};
 use futures::{Stream, TryStreamExt};
-use std::{io, path::PathBuf};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 use tokio::{
-    fs::{create_dir_all, remove_file, File},
-    io::BufWriter,
+    fs::{canonicalize, create_dir_all, read_dir, remove_file, File, OpenOptions},
+    io::{AsyncReadExt, BufWriter},
 };
 use tokio_util::io::StreamReader;
 
+use super::db;
 use crate::common::errors::FieldError;
+use crate::AppState;
+
+pub const UPLOAD_DIR: &str = "usr/uploads";
+pub const QUOTA_EXCEEDED: &str = "quota exceeded";
+/// Files younger than this are never pruned, covering uploads whose row is not written yet.
+pub const PRUNE_GRACE_SECONDS: u64 = 3600;
+
+const IMAGE_MIME_TYPES: [(&str, &str); 4] = [
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+];
+
+pub fn get_image_ext(mime: &str) -> Option<&'static str> {
+    IMAGE_MIME_TYPES
+        .iter()
+        .find(|(m, _)| *m == mime)
+        .map(|(_, ext)| *ext)
+}
+
+pub fn get_image_mime(ext: &str) -> Option<&'static str> {
+    IMAGE_MIME_TYPES
+        .iter()
+        .find(|(_, e)| *e == ext)
+        .map(|(mime, _)| *mime)
+}
 
 pub fn filename_is_valid(filename: &str) -> bool {
     let path = std::path::Path::new(filename);
@@ -22,11 +56,61 @@ pub fn filename_is_valid(filename: &str) -> bool {
     components.count() == 1
 }
 
+/// Keeps only the last path component of an uploaded filename, dropping control characters
+/// and leading dots so the result can not escape the upload directory or be hidden.
+pub fn sanitize_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or("");
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    name.trim().trim_start_matches('.').to_string()
+}
+
+/// Creates `filename` in `base_dir`, appending `-1`, `-2`, ... before the extension while
+/// the name is taken. `create_new` checks and creates in one step, so concurrent uploads of
+/// the same name never share a file.
+async fn create_available_file(base_dir: &Path, filename: &str) -> io::Result<(String, File)> {
+    let (stem, ext) = match filename.rfind('.') {
+        Some(pos) => (&filename[..pos], &filename[pos..]),
+        None => (filename, ""),
+    };
+    let mut name = filename.to_string();
+    let mut i = 0;
+    loop {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(base_dir.join(&name))
+            .await
+        {
+            Ok(file) => return Ok((name, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                i += 1;
+                name = format!("{stem}-{i}{ext}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 pub async fn stream_to_file<S, E>(
     base_dir: PathBuf,
     filename: &str,
     stream: S,
 ) -> Result<u64, FieldError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<BoxError>,
+{
+    stream_to_file_with_limit(base_dir, filename, stream, u64::MAX).await
+}
+
+/// Like `stream_to_file`, but a stream longer than `limit` bytes is cut off, its partial
+/// file removed and `QUOTA_EXCEEDED` reported.
+pub async fn stream_to_file_with_limit<S, E>(
+    base_dir: PathBuf,
+    filename: &str,
+    stream: S,
+    limit: u64,
+) -> Result<u64, FieldError>
 where
     S: Stream<Item = Result<Bytes, E>>,
     E: Into<BoxError>,
@@ -35,32 +119,334 @@ where
         return Err(FieldError::InvalidParams(filename.to_string()));
     }
 
-    async {
-        let body_with_io_error = stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err));
-        let body_reader = StreamReader::new(body_with_io_error);
-        futures::pin_mut!(body_reader);
+    let path = base_dir.join(filename);
+    let file = async {
+        if !base_dir.exists() {
+            create_dir_all(&base_dir).await?;
+        }
+        File::create(&path).await
+    }
+    .await
+    .map_err(|_| FieldError::InvalidParams("files".to_string()))?;
+    write_to_file_with_limit(file, &path, stream, limit).await
+}
+
+/// Like `stream_to_file_with_limit`, but a taken `filename` gets `-1`, `-2`, ... appended
+/// before the extension instead of being overwritten, returns the name written and its size.
+pub async fn stream_to_available_file_with_limit<S, E>(
+    base_dir: PathBuf,
+    filename: &str,
+    stream: S,
+    limit: u64,
+) -> Result<(String, u64), FieldError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<BoxError>,
+{
+    if !filename_is_valid(filename) {
+        return Err(FieldError::InvalidParams(filename.to_string()));
+    }
 
+    let (name, file) = async {
         if !base_dir.exists() {
             create_dir_all(&base_dir).await?;
         }
-        let path = base_dir.join(filename);
-        let mut file = BufWriter::new(File::create(path).await?);
+        create_available_file(&base_dir, filename).await
+    }
+    .await
+    .map_err(|_| FieldError::InvalidParams("files".to_string()))?;
+    let size = write_to_file_with_limit(file, &base_dir.join(&name), stream, limit).await?;
+    Ok((name, size))
+}
+
+async fn write_to_file_with_limit<S, E>(
+    file: File,
+    path: &Path,
+    stream: S,
+    limit: u64,
+) -> Result<u64, FieldError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<BoxError>,
+{
+    let size = async {
+        let body_with_io_error = stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err));
+        let body_reader = StreamReader::new(body_with_io_error).take(limit.saturating_add(1));
+        futures::pin_mut!(body_reader);
 
+        let mut file = BufWriter::new(file);
         let size = tokio::io::copy(&mut body_reader, &mut file).await?;
 
         Ok::<u64, io::Error>(size)
     }
-    .await
-    .map_err(|_| FieldError::InvalidParams("files".to_string()))
+    .await;
+
+    match size {
+        Ok(size) if size <= limit => Ok(size),
+        Ok(_) => {
+            let _ = remove_file(path).await;
+            Err(FieldError::InvalidParams(QUOTA_EXCEEDED.to_string()))
+        }
+        Err(_) => {
+            let _ = remove_file(path).await;
+            Err(FieldError::InvalidParams("files".to_string()))
+        }
+    }
+}
+
+/// Resolves a stored `/usr/...` path below `base_dir`. Paths with `..` or resolving outside
+/// `base_dir` after following symlinks are rejected, a missing file resolves to `None`.
+async fn resolve_stored_path(
+    base_dir: PathBuf,
+    filepath: &str,
+) -> Result<Option<PathBuf>, FieldError> {
+    let filepath = match filepath.strip_prefix("/") {
+        Some(f) => Path::new(f),
+        None => return Err(FieldError::InvalidParams("path".to_string())),
+    };
+    if filepath
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(FieldError::InvalidParams("path".to_string()));
+    }
+
+    let root = match canonicalize(&base_dir).await {
+        Ok(r) => r,
+        Err(_) => return Ok(None),
+    };
+    let path = match canonicalize(base_dir.join(filepath)).await {
+        Ok(p) => p,
+        Err(_) => return Ok(None),
+    };
+    if !path.starts_with(&root) {
+        return Err(FieldError::InvalidParams("path".to_string()));
+    }
+    Ok(Some(path))
+}
+
+/// Removes a stored `/usr/...` path below `base_dir`, a missing file is not an error.
+pub async fn delete_file(base_dir: PathBuf, filepath: &str) -> Result<(), FieldError> {
+    if let Some(path) = resolve_stored_path(base_dir, filepath).await? {
+        let _ = remove_file(path).await;
+    }
+    Ok(())
+}
+
+/// Size and hex SHA-256 of a stored `/usr/...` path below `base_dir`, `None` when missing.
+pub async fn get_file_digest(
+    base_dir: PathBuf,
+    filepath: &str,
+) -> Result<Option<(u64, String)>, FieldError> {
+    let path = match resolve_stored_path(base_dir, filepath).await? {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+    let digest = async {
+        let mut file = File::open(&path).await?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 64 * 1024];
+        let mut size = 0;
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            size += n as u64;
+        }
+        Ok::<(u64, String), io::Error>((size, format!("{:x}", hasher.finalize())))
+    }
+    .await;
+    match digest {
+        Ok(d) => Ok(Some(d)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(_) => Err(FieldError::InvalidParams("path".to_string())),
+    }
+}
+
+/// Lists stored paths (`/usr/uploads/...`) of regular files under the upload directory,
+/// skipping files modified within `grace` seconds so in-flight uploads are never listed.
+pub async fn list_upload_files(upload_root: &Path, grace: u64) -> Vec<String> {
+    let now = SystemTime::now();
+    let mut files = vec![];
+    let mut dirs = vec![upload_root.join(UPLOAD_DIR)];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = match read_dir(&dir).await {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let metadata = match entry.metadata().await {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+            if !metadata.is_file() {
+                continue;
+            }
+            let recent = metadata
+                .modified()
+                .ok()
+                .and_then(|m| now.duration_since(m).ok())
+                .map_or(true, |d| d.as_secs() < grace);
+            if recent {
+                continue;
+            }
+            let path = entry.path();
+            if let Some(rel) = path.strip_prefix(upload_root).ok().and_then(|p| p.to_str()) {
+                files.push(format!("/{rel}"));
+            }
+        }
+    }
+    files.sort();
+    files
 }
 
-pub async fn delete_file(base_dir: PathBuf, filepath: &str) -> bool {
-    let filepath = filepath.strip_prefix("/");
-    if filepath.is_none() {
+/// Deletes upload files no attachment row points at and returns their paths. The directory
+/// is walked before attachment paths are fetched, so rows created meanwhile are still kept.
+pub async fn prune_orphaned_attachments(
+    state: &AppState,
+    dry_run: bool,
+) -> Result<Vec<String>, FieldError> {
+    let upload_root = Path::new(&state.upload_root);
+    let files = list_upload_files(upload_root, PRUNE_GRACE_SECONDS).await;
+    let paths: HashSet<String> = db::get_attachment_paths(state).await?.into_iter().collect();
+
+    let orphans: Vec<String> = files.into_iter().filter(|f| !paths.contains(f)).collect();
+    if !dry_run {
+        for orphan in orphans.iter() {
+            delete_file(upload_root.to_path_buf(), orphan).await?;
+        }
+    }
+    Ok(orphans)
+}
+
+/// Every suffix after a dot is checked against `blocked`, so `shell.php.png` is refused as
+/// well, the last one against `allowed` unless it is empty. Both lists are lowercase.
+pub fn is_extension_allowed(file_name: &str, allowed: &[String], blocked: &[String]) -> bool {
+    let file_name = file_name.to_lowercase();
+    let ext = match file_name.rfind('.') {
+        Some(pos) => &file_name[pos + 1..],
+        None => return false,
+    };
+    if !allowed.is_empty() && !allowed.iter().any(|a| a == ext) {
         return false;
     }
-    let filepath = filepath.unwrap();
-    let path = base_dir.join(filepath);
-    let _ = remove_file(path).await;
-    true
+    !file_name
+        .split('.')
+        .skip(1)
+        .any(|part| blocked.iter().any(|b| b == part))
+}
+
+#[test]
+fn test_image_mime() {
+    assert_eq!(get_image_ext("image/png"), Some("png"));
+    assert_eq!(get_image_ext("text/html"), None);
+    assert_eq!(get_image_mime("jpg"), Some("image/jpeg"));
+    assert_eq!(get_image_mime("svg"), None);
+}
+
+#[test]
+fn test_sanitize_filename() {
+    assert_eq!(sanitize_filename("brochure.pdf"), "brochure.pdf");
+    assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+    assert_eq!(sanitize_filename("..\\..\\boot.ini"), "boot.ini");
+    assert_eq!(sanitize_filename("a\nb\u{0}.txt"), "ab.txt");
+    assert_eq!(sanitize_filename(".hidden.png"), "hidden.png");
+    assert_eq!(sanitize_filename("dir/"), "");
+}
+
+#[tokio::test]
+async fn test_create_available_file() {
+    let base_dir = std::env::temp_dir().join(format!("rumo-upload-{}", std::process::id()));
+    std::fs::create_dir_all(&base_dir).unwrap();
+    for expected in ["a.pdf", "a-1.pdf", "a-2.pdf"] {
+        let (name, _) = create_available_file(&base_dir, "a.pdf").await.unwrap();
+        assert_eq!(name, expected);
+    }
+
+    let (first, second) = tokio::join!(
+        create_available_file(&base_dir, "b.pdf"),
+        create_available_file(&base_dir, "b.pdf"),
+    );
+    assert_ne!(first.unwrap().0, second.unwrap().0);
+    std::fs::remove_dir_all(&base_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_delete_file() {
+    let root = std::env::temp_dir().join(format!("rumo-delete-{}", std::process::id()));
+    let base_dir = root.join("site");
+    std::fs::create_dir_all(base_dir.join("usr/uploads")).unwrap();
+    std::fs::write(root.join("secret.txt"), b"").unwrap();
+    std::fs::write(base_dir.join("usr/uploads/a.png"), b"").unwrap();
+
+    assert!(delete_file(base_dir.clone(), "/../secret.txt")
+        .await
+        .is_err());
+    assert!(delete_file(base_dir.clone(), "/usr/../../secret.txt")
+        .await
+        .is_err());
+    assert!(delete_file(base_dir.clone(), "/../../etc/passwd")
+        .await
+        .is_err());
+    assert!(delete_file(base_dir.clone(), "usr/uploads/a.png")
+        .await
+        .is_err());
+    assert!(root.join("secret.txt").exists());
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(root.join("secret.txt"), base_dir.join("usr/link.txt")).unwrap();
+        assert!(delete_file(base_dir.clone(), "/usr/link.txt")
+            .await
+            .is_err());
+        assert!(root.join("secret.txt").exists());
+    }
+
+    assert!(delete_file(base_dir.clone(), "/usr/uploads/a.png")
+        .await
+        .is_ok());
+    assert!(!base_dir.join("usr/uploads/a.png").exists());
+    assert!(delete_file(base_dir.clone(), "/usr/uploads/missing.png")
+        .await
+        .is_ok());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn test_stream_to_file_with_limit() {
+    let base_dir = std::env::temp_dir().join(format!("rumo-quota-{}", std::process::id()));
+    let chunks = || {
+        futures::stream::iter(vec![
+            Ok::<_, io::Error>(Bytes::from_static(b"12345")),
+            Ok(Bytes::from_static(b"67890")),
+        ])
+    };
+
+    let size = stream_to_file_with_limit(base_dir.clone(), "fits.txt", chunks(), 10).await;
+    assert_eq!(size.unwrap(), 10);
+    assert!(base_dir.join("fits.txt").exists());
+
+    let res = stream_to_file_with_limit(base_dir.clone(), "over.txt", chunks(), 9).await;
+    assert!(matches!(res, Err(FieldError::InvalidParams(s)) if s == QUOTA_EXCEEDED));
+    assert!(!base_dir.join("over.txt").exists());
+}
+
+#[test]
+fn test_is_extension_allowed() {
+    let blocked = vec!["php".to_string(), "exe".to_string()];
+    assert!(is_extension_allowed("photo.PNG", &[], &blocked));
+    assert!(!is_extension_allowed("shell.php", &[], &blocked));
+    assert!(!is_extension_allowed("shell.PHP", &[], &blocked));
+    assert!(!is_extension_allowed("shell.php.png", &[], &blocked));
+    assert!(!is_extension_allowed("noext", &[], &blocked));
+
+    let allowed = vec!["png".to_string(), "jpg".to_string()];
+    assert!(is_extension_allowed("photo.jpg", &allowed, &blocked));
+    assert!(!is_extension_allowed("notes.txt", &allowed, &blocked));
 }
@@ -1,21 +1,34 @@
 use std::sync::Arc;
 
-use axum::extract::{Multipart, Path, State};
-use axum::http::StatusCode;
-use axum::response::Json;
+use axum::body::Body;
+use axum::extract::{multipart::Field, Multipart, Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
 use chrono::prelude::*;
 use rand::Rng;
 use serde_json::{json, Value};
 
 use super::db;
 use super::de::from_str;
-use super::forms::{AttachmentCreate, AttachmentsQuery};
-use super::models::{AttachmentInfo, AttachmentText};
+use super::forms::{
+    AttachmentAuthor, AttachmentCreate, AttachmentModify, AttachmentsPrune, AttachmentsQuery,
+    AttachmentsReassign, AttachmentsVerify,
+};
+use super::models::{get_attachments_size, AttachmentInfo, AttachmentText};
 use super::ser::to_string;
-use super::utils::{delete_file, stream_to_file};
+use super::utils::{
+    delete_file, get_file_digest, is_extension_allowed, prune_orphaned_attachments,
+    sanitize_filename, stream_to_available_file_with_limit, stream_to_file_with_limit,
+    QUOTA_EXCEEDED, UPLOAD_DIR,
+};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::common::extractors::{PMContributor, ValidatedJson, ValidatedQuery};
+use crate::common::extractors::{PMAdministrator, PMContributor, ValidatedJson, ValidatedQuery};
+use crate::common::utils::{
+    get_conditional_response, get_cursor_ascending, get_page_links, get_page_size,
+};
+use crate::entity::{content, user::Model as User};
+use crate::users::db as user_db;
 use crate::AppState;
 
 pub async fn list_attachments(
@@ -23,19 +36,57 @@ pub async fn list_attachments(
     PMContributor(user): PMContributor,
     ValidatedQuery(q): ValidatedQuery<AttachmentsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let private =
-        q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
+    let admin = user.group == "editor" || user.group == "administrator";
+    if q.author.is_some() && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+    // another user's media view covers every status, like private
+    let private = (q.private.unwrap_or(false) || q.author.is_some()) && admin;
+    let status = q.status.as_deref().filter(|_| admin);
+
+    let mime_prefix = q.mime_prefix.as_deref();
+    let kind = q.kind.as_deref();
+    let range = q.time_range();
+    if let Some(field) = range.invalid_field() {
+        return Err(FieldError::InvalidParams(field.to_string()));
+    }
 
-    let all_count =
-        common_db::get_contents_count_with_private(&state, private, false, &user, "attachment")
-            .await;
+    let all_count = db::get_attachments_count_by_list_query(
+        &state,
+        private,
+        status,
+        mime_prefix,
+        kind,
+        &range,
+        q.author,
+    )
+    .await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-cid".to_string());
+    if q.after.is_some() && get_cursor_ascending(&order_by, "cid").is_none() {
+        return Err(FieldError::InvalidParams("order_by".to_string()));
+    }
 
-    let attachments =
-        db::get_attachments_by_list_query(&state, private, page_size, page, &order_by).await?;
+    let attachments = db::get_attachments_by_list_query(
+        &state,
+        private,
+        status,
+        mime_prefix,
+        kind,
+        &range,
+        q.author,
+        page_size,
+        page,
+        &order_by,
+        q.after,
+    )
+    .await?;
+    let next_cursor = match q.after {
+        Some(_) if attachments.len() as u64 == page_size => attachments.last().map(|a| a.cid),
+        _ => None,
+    };
 
     let mut results = vec![];
     for at in attachments {
@@ -47,21 +98,71 @@ pub async fn list_attachments(
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
+        "links": get_page_links(page, page_size, all_count),
         "count": results.len(),
+        "next_cursor": next_cursor,
         "results": results
     })))
 }
 
-pub async fn create_attachment(
-    State(state): State<Arc<AppState>>,
-    PMContributor(user): PMContributor,
-    mut multipart: Multipart,
-) -> Result<(StatusCode, Json<Value>), FieldError> {
+/// Writes an uploaded file below `base_dir` under its sanitized original name when
+/// `keep_upload_filename` is set, a random one otherwise, returns the name and size.
+async fn stream_upload(
+    state: &AppState,
+    base_dir: std::path::PathBuf,
+    file_name: &str,
+    ext: &str,
+    field: Field<'_>,
+    limit: u64,
+) -> Result<(String, u64), FieldError> {
+    if state.keep_upload_filename {
+        let name = sanitize_filename(file_name);
+        if !name.is_empty() {
+            return stream_to_available_file_with_limit(base_dir, &name, field, limit).await;
+        }
+    }
+    let rand_name: u64 = rand::thread_rng().gen_range(1_000_000_000..9_999_999_999);
+    let name = format!("{rand_name}.{ext}");
+    let size = stream_to_file_with_limit(base_dir, &name, field, limit).await?;
+    Ok((name, size))
+}
+
+fn check_extension(state: &AppState, file_name: &str) -> Result<(), FieldError> {
+    match is_extension_allowed(
+        file_name,
+        &state.allowed_extensions,
+        &state.blocked_extensions,
+    ) {
+        true => Ok(()),
+        false => Err(FieldError::InvalidParams("extension".to_string())),
+    }
+}
+
+/// Bytes `user` may still store, `freed` being the size of a file about to be replaced.
+/// Editors and administrators are exempt, as is everyone when no quota is configured.
+async fn get_upload_limit(state: &AppState, user: &User, freed: u64) -> Result<u64, FieldError> {
+    let admin = user.group == "editor" || user.group == "administrator";
+    if admin || state.max_storage_bytes_per_user == 0 {
+        return Ok(u64::MAX);
+    }
+
+    let used = get_attachments_size(db::get_attachments_by_author(state, user.uid).await?);
+    let limit = state
+        .max_storage_bytes_per_user
+        .saturating_add(freed)
+        .saturating_sub(used);
+    if limit == 0 {
+        return Err(FieldError::InvalidParams(QUOTA_EXCEEDED.to_string()));
+    }
+    Ok(limit)
+}
+
+async fn save_attachment_field(
+    state: &AppState,
+    user: &User,
+    field: Field<'_>,
+) -> Result<content::Model, FieldError> {
     let now = Local::now();
-    let field = match multipart.next_field().await {
-        Ok(Some(f)) => f,
-        _ => return Err(FieldError::InvalidParams("file".to_string())),
-    };
     let file_name = match field.file_name() {
         Some(f) => f.to_string(),
         None => return Err(FieldError::InvalidParams("file".to_string())),
@@ -75,21 +176,23 @@ pub async fn create_attachment(
         None => return Err(FieldError::InvalidParams("file".to_string())),
     };
     let ext = (&file_name[dot_pos + 1..]).to_string();
+    check_extension(state, &file_name)?;
+    let limit = get_upload_limit(state, user, 0).await?;
 
-    let rand_name: u64 = rand::thread_rng().gen_range(1_000_000_000..9_999_999_999);
-    let name = format!("{rand_name}.{ext}");
-
-    let filedir = format!("usr/uploads/{}/{}", now.year(), now.month());
+    let filedir = format!("{UPLOAD_DIR}/{}/{}", now.year(), now.month());
     let base_dir = std::path::Path::new(&state.upload_root).join(&filedir);
-    let size = stream_to_file(base_dir, &name, field).await?;
+    let (name, size) = stream_upload(state, base_dir, &file_name, &ext, field, limit).await?;
 
     let path = format!("/{filedir}/{name}");
+    let root = std::path::Path::new(&state.upload_root).to_path_buf();
+    let sha256 = get_file_digest(root, &path).await?.map(|(_, h)| h);
     let text = AttachmentText {
         name: file_name,
         path,
         size,
         r#type: ext,
         mime: content_type,
+        sha256,
     };
     let attachment_text = match to_string(&text) {
         Ok(t) => t,
@@ -97,24 +200,76 @@ pub async fn create_attachment(
     };
     let now_timestamp = now.timestamp() as u32;
 
-    let _ = db::create_attachment_with_params(
-        &state,
-        &text.name,
-        now_timestamp,
-        &attachment_text,
-        user.uid,
+    db::create_attachment_with_params(state, &text.name, now_timestamp, &attachment_text, user.uid)
+        .await
+}
+
+pub async fn create_attachment(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    mut multipart: Multipart,
+) -> Result<Response, FieldError> {
+    let mut results = vec![];
+    while let Ok(Some(field)) = multipart.next_field().await {
+        let name = field.file_name().map(|f| f.to_string());
+        let result = save_attachment_field(&state, &user, field).await;
+        results.push((name, result));
+    }
+
+    // a single file keeps the plain response, several files report each one
+    if results.len() <= 1 {
+        let attachment = match results.pop() {
+            Some((_, result)) => result?,
+            None => return Err(FieldError::InvalidParams("file".to_string())),
+        };
+        let location = format!("/api/attachments/{}", attachment.cid);
+        let at = AttachmentInfo::from(attachment);
+        return Ok((
+            StatusCode::CREATED,
+            [(header::LOCATION, location)],
+            Json(json!(at)),
+        )
+            .into_response());
+    }
+
+    let mut created = vec![];
+    let mut errors = vec![];
+    for (index, (name, result)) in results.into_iter().enumerate() {
+        match result {
+            Ok(attachment) => created.push(AttachmentInfo::from(attachment)),
+            Err(e) => {
+                let msg = match e {
+                    FieldError::DatabaseFailed(s) => s,
+                    FieldError::InvalidParams(s) if s == QUOTA_EXCEEDED => s,
+                    FieldError::InvalidParams(s) if s == "extension" => format!("Invalid {s}"),
+                    _ => "Invalid file".to_string(),
+                };
+                errors.push(json!({ "index": index, "name": name, "msg": msg }));
+            }
+        }
+    }
+    if created.is_empty() {
+        return Err(FieldError::InvalidParams("file".to_string()));
+    }
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "count": created.len(),
+            "results": created,
+            "errors": errors,
+        })),
     )
-    .await?;
-    Ok((StatusCode::CREATED, Json(json!({"msg":"ok"}))))
+        .into_response())
 }
 
 pub async fn get_attachment_by_cid(
     State(state): State<Arc<AppState>>,
     PMContributor(user): PMContributor,
     Path(cid): Path<u32>,
-) -> Result<Json<Value>, FieldError> {
-    let attachment = match common_db::get_content_by_cid(&state, cid).await {
-        Ok(Some(a)) => a,
+    headers: HeaderMap,
+) -> Result<Response, FieldError> {
+    let attachment = match common_db::get_content_by_cid(&state, cid).await? {
+        Some(a) => a,
         _ => return Err(FieldError::NotFound("cid".to_string())),
     };
 
@@ -123,8 +278,9 @@ pub async fn get_attachment_by_cid(
         return Err(FieldError::PermissionDeny);
     }
 
+    let modified = attachment.modified;
     let at = AttachmentInfo::from(attachment);
-    Ok(Json(json!(at)))
+    Ok(get_conditional_response(&headers, json!(at), modified))
 }
 
 pub async fn modify_attachment_by_cid(
@@ -133,8 +289,8 @@ pub async fn modify_attachment_by_cid(
     Path(cid): Path<u32>,
     mut multipart: Multipart,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_attachment = match common_db::get_content_by_cid(&state, cid).await {
-        Ok(Some(a)) => a,
+    let exist_attachment = match common_db::get_content_by_cid(&state, cid).await? {
+        Some(a) => a,
         _ => return Err(FieldError::NotFound("cid".to_string())),
     };
 
@@ -143,11 +299,10 @@ pub async fn modify_attachment_by_cid(
         return Err(FieldError::PermissionDeny);
     }
 
-    let base_dir = std::path::Path::new(&state.upload_root);
     let text = exist_attachment.text.unwrap_or("".to_string());
     let exist_at = from_str::<AttachmentText>(&text)
         .map_err(|_| FieldError::DatabaseFailed("attachment decode error".to_string()))?;
-    let _ = delete_file(base_dir.to_path_buf(), &exist_at.path).await;
+    let limit = get_upload_limit(&state, &user, exist_at.size).await?;
 
     let now = Local::now();
     let field = match multipart.next_field().await {
@@ -167,37 +322,104 @@ pub async fn modify_attachment_by_cid(
         None => return Err(FieldError::InvalidParams("file".to_string())),
     };
     let ext = (&file_name[dot_pos + 1..]).to_string();
+    check_extension(&state, &file_name)?;
 
-    let rand_name: u64 = rand::thread_rng().gen_range(1_000_000_000..9_999_999_999);
-    let name = format!("{rand_name}.{ext}");
-
-    let filedir = format!("usr/uploads/{}/{}", now.year(), now.month());
+    let filedir = format!("{UPLOAD_DIR}/{}/{}", now.year(), now.month());
     let base_dir = std::path::Path::new(&state.upload_root).join(&filedir);
-    let size = stream_to_file(base_dir, &name, field).await?;
+    let (name, size) = stream_upload(&state, base_dir, &file_name, &ext, field, limit).await?;
 
     let path = format!("/{filedir}/{name}");
-    let text = AttachmentText {
-        name: file_name,
-        path,
-        size,
-        r#type: ext,
-        mime: content_type,
+    let root = std::path::Path::new(&state.upload_root).to_path_buf();
+    let saved = async {
+        let sha256 = get_file_digest(root.clone(), &path).await?.map(|(_, h)| h);
+        let text = AttachmentText {
+            name: file_name,
+            path: path.clone(),
+            size,
+            r#type: ext,
+            mime: content_type,
+            sha256,
+        };
+        let attachment_text = match to_string(&text) {
+            Ok(t) => t,
+            Err(_) => return Err(FieldError::InvalidParams("file".to_string())),
+        };
+        let now_timestamp = now.timestamp() as u32;
+
+        db::modify_attachment_by_cid_with_params(
+            &state,
+            cid,
+            &text.name,
+            now_timestamp,
+            &attachment_text,
+        )
+        .await
+    }
+    .await;
+    // the old file is only removed once the row points at the replacement
+    if let Err(e) = saved {
+        let _ = delete_file(root, &path).await;
+        return Err(e);
+    }
+    delete_file(root, &exist_at.path).await?;
+    Ok(Json(json!({"msg":"ok"})))
+}
+
+pub async fn patch_attachment_by_cid(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path(cid): Path<u32>,
+    ValidatedJson(attachment_modify): ValidatedJson<AttachmentModify>,
+) -> Result<Json<Value>, FieldError> {
+    let exist_attachment = match common_db::get_content_by_cid(&state, cid).await? {
+        Some(a) if a.r#type == "attachment" => a,
+        _ => return Err(FieldError::NotFound("cid".to_string())),
     };
-    let attachment_text = match to_string(&text) {
-        Ok(t) => t,
-        Err(_) => return Err(FieldError::InvalidParams("file".to_string())),
+
+    let admin = user.group == "editor" || user.group == "administrator";
+    if user.uid != exist_attachment.author_id && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+
+    if attachment_modify.name.is_none()
+        && attachment_modify.title.is_none()
+        && attachment_modify.slug.is_none()
+    {
+        return Err(FieldError::InvalidParams("name, title or slug".to_string()));
+    }
+
+    if let Some(ref slug) = attachment_modify.slug {
+        if let Ok(Some(c)) = common_db::get_content_by_slug(&state, slug).await {
+            if c.cid != cid {
+                return Err(FieldError::AlreadyExist("slug".to_string()));
+            }
+        }
+    }
+
+    let attachment_text = match attachment_modify.name {
+        Some(name) => {
+            let text = exist_attachment.text.clone().unwrap_or("".to_string());
+            let mut at = from_str::<AttachmentText>(&text)
+                .map_err(|_| FieldError::DatabaseFailed("attachment decode error".to_string()))?;
+            at.name = name;
+            let text = to_string(&at)
+                .map_err(|_| FieldError::DatabaseFailed("attachment encode error".to_string()))?;
+            Some(text)
+        }
+        None => None,
     };
-    let now_timestamp = now.timestamp() as u32;
 
-    let _ = db::modify_attachment_by_cid_with_params(
+    let now = Local::now().timestamp() as u32;
+    let attachment = db::modify_attachment_meta_by_cid(
         &state,
-        exist_attachment.cid,
-        &text.name,
-        now_timestamp,
-        &attachment_text,
+        cid,
+        attachment_modify.title.as_deref(),
+        attachment_modify.slug.as_deref(),
+        attachment_text.as_deref(),
+        now,
     )
     .await?;
-    Ok(Json(json!({"msg":"ok"})))
+    Ok(Json(json!(AttachmentInfo::from(attachment))))
 }
 
 pub async fn delete_attachment_by_cid(
@@ -205,8 +427,8 @@ pub async fn delete_attachment_by_cid(
     PMContributor(user): PMContributor,
     Path(cid): Path<u32>,
 ) -> Result<Json<Value>, FieldError> {
-    let attachment = match common_db::get_content_by_cid(&state, cid).await {
-        Ok(Some(a)) => a,
+    let attachment = match common_db::get_content_by_cid(&state, cid).await? {
+        Some(a) => a,
         _ => return Err(FieldError::NotFound("cid".to_string())),
     };
 
@@ -221,7 +443,7 @@ pub async fn delete_attachment_by_cid(
 
     let base_dir = std::path::Path::new(&state.upload_root);
     let filepath = text.path;
-    let _ = delete_file(base_dir.to_path_buf(), &filepath).await;
+    delete_file(base_dir.to_path_buf(), &filepath).await?;
 
     let _ = common_db::delete_content_by_cid(&state, cid).await?;
     Ok(Json(json!({ "msg": "ok" })))
@@ -231,9 +453,9 @@ pub async fn list_content_attachments_by_slug(
     State(state): State<Arc<AppState>>,
     Path(slug): Path<String>,
 ) -> Result<Json<Value>, FieldError> {
-    let content = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(c)) => c,
-        _ => return Err(FieldError::InvalidParams("slug".to_string())),
+    let content = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(c) => c,
+        None => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
     let attachments = db::get_attachments_by_parent(&state, content.cid).await?;
@@ -259,8 +481,8 @@ pub async fn add_attachment_to_content_by_cid(
     Path(slug): Path<String>,
     ValidatedJson(attachement_create): ValidatedJson<AttachmentCreate>,
 ) -> Result<Json<Value>, FieldError> {
-    let attachment = match common_db::get_content_by_cid(&state, attachement_create.cid).await {
-        Ok(Some(a)) => a,
+    let attachment = match common_db::get_content_by_cid(&state, attachement_create.cid).await? {
+        Some(a) => a,
         _ => return Err(FieldError::NotFound("cid".to_string())),
     };
 
@@ -284,8 +506,8 @@ pub async fn delete_attachment_from_content_by_cid(
     PMContributor(user): PMContributor,
     Path((slug, cid)): Path<(String, u32)>,
 ) -> Result<Json<Value>, FieldError> {
-    let attachment = match common_db::get_content_by_cid(&state, cid).await {
-        Ok(Some(a)) => a,
+    let attachment = match common_db::get_content_by_cid(&state, cid).await? {
+        Some(a) => a,
         _ => return Err(FieldError::NotFound("cid".to_string())),
     };
 
@@ -306,3 +528,186 @@ pub async fn delete_attachment_from_content_by_cid(
     let _ = db::modify_attachment_parent_by_cid(&state, attachment.cid, 0).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
+
+pub async fn reassign_attachment_author(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    Path(cid): Path<u32>,
+    ValidatedJson(attachment_author): ValidatedJson<AttachmentAuthor>,
+) -> Result<Json<Value>, FieldError> {
+    let attachment = match common_db::get_content_by_cid(&state, cid).await? {
+        Some(a) if a.r#type == "attachment" => a,
+        _ => return Err(FieldError::NotFound("cid".to_string())),
+    };
+
+    match user_db::get_user_by_uid(&state, attachment_author.uid).await {
+        Ok(Some(_)) => (),
+        _ => return Err(FieldError::InvalidParams("uid".to_string())),
+    };
+
+    let attachment =
+        db::modify_attachment_author_by_cid(&state, attachment.cid, attachment_author.uid).await?;
+    Ok(Json(json!(AttachmentInfo::from(attachment))))
+}
+
+pub async fn reassign_attachments_author(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    ValidatedJson(attachments_reassign): ValidatedJson<AttachmentsReassign>,
+) -> Result<Json<Value>, FieldError> {
+    match user_db::get_user_by_uid(&state, attachments_reassign.uid).await {
+        Ok(Some(_)) => (),
+        _ => return Err(FieldError::InvalidParams("uid".to_string())),
+    };
+
+    let count = db::modify_attachments_author_by_author(
+        &state,
+        attachments_reassign.from_uid,
+        attachments_reassign.uid,
+    )
+    .await?;
+    Ok(Json(json!({ "count": count })))
+}
+
+pub async fn prune_attachments(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    ValidatedQuery(q): ValidatedQuery<AttachmentsPrune>,
+) -> Result<Json<Value>, FieldError> {
+    let dry_run = q.dry_run.unwrap_or(false);
+    let files = prune_orphaned_attachments(&state, dry_run).await?;
+    Ok(Json(json!({
+        "dry_run": dry_run,
+        "count": files.len(),
+        "files": files
+    })))
+}
+
+const VERIFY_PAGE_SIZE: u64 = 100;
+
+#[derive(Clone, Copy, Default)]
+struct VerifySummary {
+    checked: u64,
+    ok: u64,
+    missing: u64,
+    mismatched: u64,
+    invalid: u64,
+}
+
+enum VerifyStage {
+    Page(u32, VerifySummary),
+    Done,
+}
+
+async fn verify_attachment(state: &AppState, attachment: content::Model) -> (&'static str, Value) {
+    let text = attachment.text.unwrap_or("".to_string());
+    let at = match from_str::<AttachmentText>(&text) {
+        Ok(at) => at,
+        Err(_) => {
+            let line = json!({
+                "cid": attachment.cid,
+                "path": null,
+                "status": "invalid",
+                "hashed": false,
+            });
+            return ("invalid", line);
+        }
+    };
+    let base_dir = std::path::Path::new(&state.upload_root).to_path_buf();
+    let status = match get_file_digest(base_dir, &at.path).await {
+        Ok(Some((size, hash))) => {
+            let hash_matched = at.sha256.as_ref().map_or(true, |h| *h == hash);
+            if size == at.size && hash_matched {
+                "ok"
+            } else {
+                "mismatch"
+            }
+        }
+        Ok(None) => "missing",
+        Err(_) => "invalid",
+    };
+    let line = json!({
+        "cid": attachment.cid,
+        "path": at.path,
+        "status": status,
+        "hashed": at.sha256.is_some(),
+    });
+    (status, line)
+}
+
+async fn get_verify_chunk(
+    state: &AppState,
+    cid: Option<u32>,
+    after: u32,
+    summary: &mut VerifySummary,
+) -> Result<(String, Option<u32>), FieldError> {
+    let attachments = db::get_attachments_after_cid(state, after, cid, VERIFY_PAGE_SIZE).await?;
+    let last = attachments.last().map(|a| a.cid);
+
+    let mut chunk = String::new();
+    for attachment in attachments {
+        let (status, line) = verify_attachment(state, attachment).await;
+        summary.checked += 1;
+        match status {
+            "ok" => summary.ok += 1,
+            "missing" => summary.missing += 1,
+            "mismatch" => summary.mismatched += 1,
+            _ => summary.invalid += 1,
+        }
+        chunk.push_str(&line.to_string());
+        chunk.push('\n');
+    }
+    Ok((chunk, last))
+}
+
+/// Streams one NDJSON line per checked attachment followed by a `summary` line.
+pub async fn verify_attachment_integrity(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    ValidatedQuery(q): ValidatedQuery<AttachmentsVerify>,
+) -> Result<Response, FieldError> {
+    if let Some(cid) = q.cid {
+        match common_db::get_content_by_cid(&state, cid).await? {
+            Some(a) if a.r#type == "attachment" => (),
+            _ => return Err(FieldError::NotFound("cid".to_string())),
+        }
+    }
+
+    let cid = q.cid;
+    let start = VerifyStage::Page(0, VerifySummary::default());
+    let stream = futures::stream::unfold(start, move |stage| {
+        let state = state.clone();
+        async move {
+            match stage {
+                VerifyStage::Page(after, mut summary) => {
+                    match get_verify_chunk(&state, cid, after, &mut summary).await {
+                        Ok((chunk, Some(last))) => {
+                            Some((Ok(chunk), VerifyStage::Page(last, summary)))
+                        }
+                        Ok((_, None)) => {
+                            let line = json!({"summary": {
+                                "checked": summary.checked,
+                                "ok": summary.ok,
+                                "missing": summary.missing,
+                                "mismatched": summary.mismatched,
+                                "invalid": summary.invalid,
+                            }});
+                            Some((Ok(format!("{line}\n")), VerifyStage::Done))
+                        }
+                        Err(_) => Some((
+                            Err(std::io::Error::other("verify attachments failed")),
+                            VerifyStage::Done,
+                        )),
+                    }
+                }
+                VerifyStage::Done => None,
+            }
+        }
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
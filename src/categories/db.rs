@@ -3,7 +3,7 @@ use sea_orm::*;
 use super::forms::CategoryCreate;
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::entity::meta;
+use crate::entity::{meta, meta::Entity as Meta};
 use crate::AppState;
 
 pub async fn create_category_by_category_create(
@@ -58,3 +58,11 @@ pub async fn modify_category_by_mid_and_category_modify(
         .await
         .map_err(|_| FieldError::DatabaseFailed("modify category failed".to_string()))
 }
+
+pub async fn get_categories(state: &AppState) -> Result<Vec<meta::Model>, FieldError> {
+    Meta::find()
+        .filter(meta::Column::Type.eq("category"))
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch categories failed".to_string()))
+}
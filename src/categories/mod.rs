@@ -1,5 +1,6 @@
 pub mod db;
 pub mod forms;
+mod models;
 mod urls;
 mod views;
 
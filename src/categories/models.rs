@@ -0,0 +1,113 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::errors::FieldError;
+use crate::entity::meta;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct CategoryNode {
+    pub mid: u32,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+    pub count: u32,
+    pub order: u32,
+    pub parent: u32,
+    pub depth: u32,
+    pub children: Vec<CategoryNode>,
+}
+
+impl CategoryNode {
+    fn from_meta(category: meta::Model, depth: u32) -> Self {
+        Self {
+            mid: category.mid,
+            name: category.name,
+            slug: category.slug,
+            description: category.description,
+            count: category.count,
+            order: category.order,
+            parent: category.parent,
+            depth,
+            children: vec![],
+        }
+    }
+}
+
+fn build_nodes(
+    parent: u32,
+    depth: u32,
+    children_map: &mut HashMap<u32, Vec<meta::Model>>,
+) -> Vec<CategoryNode> {
+    let children = children_map.remove(&parent).unwrap_or_default();
+
+    let mut nodes = vec![];
+    for child in children {
+        let mid = child.mid;
+        let mut node = CategoryNode::from_meta(child, depth);
+        node.children = build_nodes(mid, depth + 1, children_map);
+        nodes.push(node);
+    }
+    nodes
+}
+
+pub fn build_category_tree(categories: Vec<meta::Model>) -> Result<Vec<CategoryNode>, FieldError> {
+    let parents: HashMap<u32, u32> = categories.iter().map(|c| (c.mid, c.parent)).collect();
+
+    for category in &categories {
+        let mut visited = HashSet::new();
+        let mut mid = category.mid;
+        while let Some(&parent) = parents.get(&mid) {
+            if !visited.insert(mid) {
+                return Err(FieldError::InvalidParams(format!(
+                    "category {} parent cycle",
+                    category.mid
+                )));
+            }
+            mid = parent;
+        }
+    }
+
+    let mut children_map: HashMap<u32, Vec<meta::Model>> = HashMap::new();
+    for category in categories {
+        let parent = if parents.contains_key(&category.parent) {
+            category.parent
+        } else {
+            0
+        };
+        children_map.entry(parent).or_default().push(category);
+    }
+    for children in children_map.values_mut() {
+        children.sort_by_key(|c| (c.order, c.mid));
+    }
+
+    Ok(build_nodes(0, 0, &mut children_map))
+}
+
+#[test]
+fn test_build_category_tree() {
+    let category = |mid: u32, parent: u32| meta::Model {
+        mid,
+        name: Some(format!("category-{mid}")),
+        slug: Some(format!("category-{mid}")),
+        r#type: "category".to_string(),
+        description: None,
+        count: 0,
+        order: 0,
+        parent,
+    };
+
+    let tree = build_category_tree(vec![category(1, 0), category(2, 1), category(3, 2)]).unwrap();
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree[0].depth, 0);
+    assert_eq!(tree[0].children[0].mid, 2);
+    assert_eq!(tree[0].children[0].depth, 1);
+    assert_eq!(tree[0].children[0].children[0].mid, 3);
+    assert_eq!(tree[0].children[0].children[0].depth, 2);
+
+    let tree = build_category_tree(vec![category(1, 0), category(2, 99)]).unwrap();
+    assert_eq!(tree.len(), 2);
+
+    assert!(build_category_tree(vec![category(1, 2), category(2, 1)]).is_err());
+    assert!(build_category_tree(vec![category(1, 1)]).is_err());
+}
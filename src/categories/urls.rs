@@ -5,15 +5,26 @@ use axum::{
 use std::sync::Arc;
 
 use super::views;
+use crate::allow::method_not_allowed;
 use crate::AppState;
 
 pub fn categories_routers(ro: bool) -> Router<Arc<AppState>> {
     let categories_route = Router::new()
-        .route("/api/categories/", get(views::list_categories))
-        .route("/api/categories/:slug", get(views::get_category_by_slug))
+        .route(
+            "/api/categories/",
+            get(views::list_categories).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/categories/tree",
+            get(views::list_categories_tree).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/categories/:slug",
+            get(views::get_category_by_slug).fallback(method_not_allowed),
+        )
         .route(
             "/api/categories/:slug/posts/",
-            get(views::list_category_posts_by_slug),
+            get(views::list_category_posts_by_slug).fallback(method_not_allowed),
         );
 
     if !ro {
@@ -33,7 +44,7 @@ pub fn categories_routers(ro: bool) -> Router<Arc<AppState>> {
             )
             .route(
                 "/api/categories/:slug/posts/:post_slug",
-                delete(views::delete_post_from_category),
+                delete(views::delete_post_from_category).fallback(method_not_allowed),
             )
     } else {
         categories_route
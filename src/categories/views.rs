@@ -7,10 +7,12 @@ use serde_json::{json, Value};
 
 use super::db;
 use super::forms::{CategoryCreate, CategoryPostAdd};
+use super::models::build_category_tree;
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
 use crate::common::forms::ListQuery;
+use crate::common::utils::get_page_size;
 use crate::posts::forms::PostsQuery;
 use crate::AppState;
 
@@ -32,10 +34,10 @@ pub async fn list_categories(
     State(state): State<Arc<AppState>>,
     ValidatedQuery(q): ValidatedQuery<ListQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let all_count = common_db::get_metas_count(&state, false).await;
+    let all_count = common_db::get_metas_count(&state, false).await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-mid".to_string());
 
     let categories =
@@ -49,12 +51,26 @@ pub async fn list_categories(
     })))
 }
 
+pub async fn list_categories_tree(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Value>, FieldError> {
+    let categories = db::get_categories(&state).await?;
+    let all_count = categories.len();
+
+    let tree = build_category_tree(categories)?;
+    Ok(Json(json!({
+        "all_count": all_count,
+        "count": tree.len(),
+        "results": tree
+    })))
+}
+
 pub async fn get_category_by_slug(
     State(state): State<Arc<AppState>>,
     Path(slug): Path<String>,
 ) -> Result<Json<Value>, FieldError> {
-    match common_db::get_meta_by_slug(&state, &slug, false).await {
-        Ok(Some(category)) => Ok(Json(json!(category))),
+    match common_db::get_meta_by_slug(&state, &slug, false).await? {
+        Some(category) => Ok(Json(json!(category))),
         _ => Err(FieldError::NotFound("slug".to_string())),
     }
 }
@@ -157,19 +173,19 @@ pub async fn list_category_posts_by_slug(
     Path(slug): Path<String>,
     ValidatedQuery(q): ValidatedQuery<PostsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let mid = match common_db::get_meta_by_slug(&state, &slug, false).await {
-        Ok(Some(category)) => category.mid,
-        _ => return Err(FieldError::InvalidParams("slug".to_string())),
+    let mid = match common_db::get_meta_by_slug(&state, &slug, false).await? {
+        Some(category) => category.mid,
+        None => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
     let private =
         q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
 
     let all_count =
-        common_db::get_meta_posts_count_by_mid_with_private(&state, mid, private).await;
+        common_db::get_meta_posts_count_by_mid_with_private(&state, mid, private).await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-cid".to_string());
 
     let posts = common_db::get_contents_with_metas_user_and_fields_by_mid_list_query_and_private(
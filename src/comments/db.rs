@@ -1,10 +1,14 @@
-use std::time::SystemTime;
-
 use sea_orm::*;
 
+use super::forms::CommentListEntry;
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::entity::{comment, comment::Entity as Comment, content};
+use crate::common::utils::get_order_by;
+use crate::entity::{
+    comment, comment::Entity as Comment, content, content::Entity as Content, user,
+    user::Entity as User,
+};
+use crate::users::{db as user_db, forms::OptionModify, utils::current_timestamp};
 use crate::AppState;
 
 pub async fn get_comment_by_coid(
@@ -15,7 +19,7 @@ pub async fn get_comment_by_coid(
         .filter(comment::Column::Coid.eq(coid))
         .one(&state.conn)
         .await
-        .map_err(|_| FieldError::InvalidParams("coid".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("fetch comment failed".to_string()))
 }
 
 pub async fn create_comment_with_params(
@@ -32,12 +36,16 @@ pub async fn create_comment_with_params(
     status: &str,
     parent: u32,
 ) -> Result<comment::ActiveModel, FieldError> {
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?;
+
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
 
-    comment::ActiveModel {
+    let c = comment::ActiveModel {
         cid: Set(cid),
         created: Set(now as u32),
         author: Set(Some(author.to_owned())),
@@ -52,43 +60,122 @@ pub async fn create_comment_with_params(
         parent: Set(parent),
         ..Default::default()
     }
-    .save(&state.conn)
+    .save(&txn)
     .await
-    .map_err(|_| FieldError::DatabaseFailed("insert comment failed".to_string()))
+    .map_err(|_| FieldError::DatabaseFailed("insert comment failed".to_string()))?;
+
+    if status == "approved" {
+        update_content_comments_num_by_cid(&txn, cid, true).await?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(c)
 }
 
-pub async fn update_content_count_increase_by_cid(
-    state: &AppState,
+/// Only approved comments are counted in commentsNum, as Typecho does.
+pub async fn update_content_comments_num_by_cid<C: ConnectionTrait>(
+    conn: &C,
     cid: u32,
-) -> Result<content::Model, FieldError> {
-    let exist_content = match common_db::get_content_by_cid(state, cid).await? {
-        Some(c) => c,
-        None => return Err(FieldError::InvalidParams("cid".to_string())),
+    increase: bool,
+) -> Result<u64, FieldError> {
+    let stmt = Content::update_many().filter(content::Column::Cid.eq(cid));
+    let stmt = if increase {
+        stmt.col_expr(
+            content::Column::CommentsNum,
+            sea_query::Expr::col(content::Column::CommentsNum).add(1),
+        )
+    } else {
+        stmt.filter(content::Column::CommentsNum.gt(0)).col_expr(
+            content::Column::CommentsNum,
+            sea_query::Expr::col(content::Column::CommentsNum).sub(1),
+        )
     };
-
-    let count = exist_content.comments_num;
-    let mut c = content::ActiveModel::from(exist_content);
-    c.comments_num = Set(count + 1);
-    c.update(&state.conn)
+    stmt.exec(conn)
         .await
+        .map(|r| r.rows_affected)
         .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))
 }
 
-pub async fn update_content_count_decrease_by_cid(
-    state: &AppState,
-    cid: u32,
-) -> Result<content::Model, FieldError> {
-    let exist_content = match common_db::get_content_by_cid(state, cid).await? {
-        Some(c) => c,
-        None => return Err(FieldError::InvalidParams("cid".to_string())),
-    };
+/// Attempts before giving up on a comment whose status keeps changing underneath a write.
+const COUNT_UPDATE_RETRIES: usize = 3;
 
-    let count = exist_content.comments_num;
-    let mut c = content::ActiveModel::from(exist_content);
-    c.comments_num = Set(count - 1);
-    c.update(&state.conn)
+fn approved_condition(approved: bool) -> sea_query::SimpleExpr {
+    match approved {
+        true => comment::Column::Status.eq("approved"),
+        false => comment::Column::Status.ne("approved"),
+    }
+}
+
+async fn get_comment_in_txn(
+    txn: &DatabaseTransaction,
+    coid: u32,
+) -> Result<Option<comment::Model>, FieldError> {
+    Comment::find()
+        .filter(comment::Column::Coid.eq(coid))
+        .one(txn)
         .await
-        .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("fetch comment failed".to_string()))
+}
+
+/// Writes only while the comment is still on the side of approved it was read on, so
+/// concurrent moderation can not count the same transition twice.
+async fn update_comment_with_count<F>(
+    state: &AppState,
+    coid: u32,
+    modify: F,
+) -> Result<comment::Model, FieldError>
+where
+    F: Fn(&mut comment::ActiveModel),
+{
+    for _ in 0..COUNT_UPDATE_RETRIES {
+        let txn = state
+            .conn
+            .begin()
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+        let exist_comment = match get_comment_in_txn(&txn, coid).await? {
+            Some(c) => c,
+            None => return Err(FieldError::InvalidParams("coid".to_string())),
+        };
+        let was_approved = exist_comment.status == "approved";
+
+        let mut c = comment::ActiveModel::from(exist_comment.clone());
+        modify(&mut c);
+        let comment = c
+            .clone()
+            .try_into_model()
+            .map_err(|_| FieldError::DatabaseFailed("update comment failed".to_string()))?;
+        // MySQL reports unchanged rows as unaffected, so a no-op must not look like a race
+        if comment == exist_comment {
+            return Ok(comment);
+        }
+
+        let result = Comment::update_many()
+            .set(c)
+            .filter(comment::Column::Coid.eq(coid))
+            .filter(approved_condition(was_approved))
+            .exec(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update comment failed".to_string()))?;
+        if result.rows_affected == 0 {
+            let _ = txn.rollback().await;
+            continue;
+        }
+
+        let is_approved = comment.status == "approved";
+        if was_approved != is_approved {
+            update_content_comments_num_by_cid(&txn, comment.cid, is_approved).await?;
+        }
+
+        txn.commit()
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+        return Ok(comment);
+    }
+    Err(FieldError::Conflict("status".to_string()))
 }
 
 pub async fn modify_comment_with_params(
@@ -97,36 +184,100 @@ pub async fn modify_comment_with_params(
     text: &str,
     status: &str,
 ) -> Result<comment::Model, FieldError> {
-    let exist_comment = match get_comment_by_coid(state, coid).await? {
-        Some(c) => c,
-        None => return Err(FieldError::InvalidParams("coid".to_string())),
-    };
+    update_comment_with_count(state, coid, |c| {
+        c.text = Set(Some(text.to_owned()));
+        c.status = Set(status.to_owned());
+    })
+    .await
+}
 
-    let mut c = comment::ActiveModel::from(exist_comment);
-    c.text = Set(Some(text.to_owned()));
-    c.status = Set(status.to_owned());
-    c.update(&state.conn)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("update comment failed".to_string()))
+pub async fn update_comment_status_by_coid(
+    state: &AppState,
+    coid: u32,
+    status: &str,
+) -> Result<comment::Model, FieldError> {
+    update_comment_with_count(state, coid, |c| {
+        c.status = Set(status.to_owned());
+    })
+    .await
 }
 
 pub async fn delete_comment_by_coid(
     state: &AppState,
     coid: u32,
 ) -> Result<DeleteResult, FieldError> {
-    let exist_comment = match get_comment_by_coid(state, coid).await? {
+    for _ in 0..COUNT_UPDATE_RETRIES {
+        let txn = state
+            .conn
+            .begin()
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+        let exist_comment = match get_comment_in_txn(&txn, coid).await? {
+            Some(c) => c,
+            None => return Err(FieldError::InvalidParams("coid".to_string())),
+        };
+        let was_approved = exist_comment.status == "approved";
+
+        let result = Comment::delete_many()
+            .filter(comment::Column::Coid.eq(coid))
+            .filter(approved_condition(was_approved))
+            .exec(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("delete comment failed".to_string()))?;
+        if result.rows_affected == 0 {
+            let _ = txn.rollback().await;
+            continue;
+        }
+
+        if was_approved {
+            update_content_comments_num_by_cid(&txn, exist_comment.cid, false).await?;
+        }
+
+        txn.commit()
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+        return Ok(result);
+    }
+    Err(FieldError::Conflict("status".to_string()))
+}
+
+pub async fn recount_comments_num_by_cid(state: &AppState, cid: u32) -> Result<u32, FieldError> {
+    let exist_content = match common_db::get_content_by_cid(state, cid).await? {
         Some(c) => c,
-        None => return Err(FieldError::InvalidParams("coid".to_string())),
+        None => return Err(FieldError::InvalidParams("cid".to_string())),
     };
 
-    exist_comment
-        .delete(&state.conn)
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    let count = Comment::find()
+        .filter(comment::Column::Cid.eq(cid))
+        .filter(comment::Column::Status.eq("approved"))
+        .count(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count comments failed".to_string()))?;
+
+    let mut c = content::ActiveModel::from(exist_content);
+    c.comments_num = Set(count as u32);
+    c.update(&txn)
         .await
-        .map_err(|_| FieldError::DatabaseFailed("delete comment failed".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(count as u32)
 }
 
-pub async fn get_comments_count(state: &AppState) -> u64 {
-    Comment::find().count(&state.conn).await.unwrap_or(0)
+pub async fn get_comments_count(state: &AppState) -> Result<u64, FieldError> {
+    Comment::find()
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count comments failed".to_string()))
 }
 
 pub async fn get_comments_by_list_query(
@@ -137,62 +288,150 @@ pub async fn get_comments_by_list_query(
 ) -> Result<Vec<comment::Model>, FieldError> {
     let stmt = Comment::find();
 
-    let stmt = match order_by {
-        "-coid" => stmt.order_by_desc(comment::Column::Coid),
-        "coid" => stmt.order_by_asc(comment::Column::Coid),
-        "-created" => stmt.order_by_desc(comment::Column::Created),
-        "created" => stmt.order_by_asc(comment::Column::Created),
-        _ => stmt.order_by_desc(comment::Column::Coid),
-    };
+    let (column, order) = get_order_by(
+        order_by,
+        &[
+            ("coid", comment::Column::Coid),
+            ("created", comment::Column::Created),
+        ],
+    )?;
+    let stmt = stmt
+        .order_by(column, order.clone())
+        .order_by(comment::Column::Coid, order);
     let paginator = stmt.paginate(&state.conn, page_size);
     paginator
-        .fetch_page(page)
+        .fetch_page(page - 1)
         .await
         .map_err(|_| FieldError::DatabaseFailed("get comments by list query failed".to_string()))
 }
 
-pub async fn get_content_comments_count_by_cid_with_private(
+pub async fn get_comments_count_by_statuses(
+    state: &AppState,
+    statuses: &[&str],
+) -> Result<u64, FieldError> {
+    Comment::find()
+        .filter(comment::Column::Status.is_in(statuses.iter().copied()))
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count comments failed".to_string()))
+}
+
+/// Oldest first, so the queue is worked through in arrival order.
+pub async fn get_comments_with_content_by_statuses(
+    state: &AppState,
+    statuses: &[&str],
+    page_size: u64,
+    page: u64,
+) -> Result<Vec<(comment::Model, Option<content::Model>)>, FieldError> {
+    Comment::find()
+        .filter(comment::Column::Status.is_in(statuses.iter().copied()))
+        .find_also_related(Content)
+        .order_by_asc(comment::Column::Created)
+        .order_by_asc(comment::Column::Coid)
+        .paginate(&state.conn, page_size)
+        .fetch_page(page - 1)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch comments failed".to_string()))
+}
+
+pub async fn get_content_comments_count_by_cid_with_status(
     state: &AppState,
     cid: u32,
-    private: bool,
-) -> u64 {
+    status: Option<&str>,
+) -> Result<u64, FieldError> {
     let stmt = Comment::find().filter(comment::Column::Cid.eq(cid));
 
-    let stmt = if private {
-        stmt
-    } else {
-        stmt.filter(comment::Column::Status.eq("approved"))
+    let stmt = match status {
+        Some(status) => stmt.filter(comment::Column::Status.eq(status)),
+        None => stmt,
     };
 
-    stmt.count(&state.conn).await.unwrap_or(0)
+    stmt.count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count comments failed".to_string()))
 }
 
-pub async fn get_comments_by_cid_and_list_query_with_private(
+pub async fn get_comments_by_cid_and_list_query_with_status(
     state: &AppState,
     cid: u32,
-    private: bool,
+    status: Option<&str>,
     page_size: u64,
     page: u64,
     order_by: &str,
 ) -> Result<Vec<comment::Model>, FieldError> {
     let stmt = Comment::find().filter(comment::Column::Cid.eq(cid));
 
-    let stmt = if private {
-        stmt
-    } else {
-        stmt.filter(comment::Column::Status.eq("approved"))
+    let stmt = match status {
+        Some(status) => stmt.filter(comment::Column::Status.eq(status)),
+        None => stmt,
     };
 
-    let stmt = match order_by {
-        "-coid" => stmt.order_by_desc(comment::Column::Coid),
-        "coid" => stmt.order_by_asc(comment::Column::Coid),
-        "-created" => stmt.order_by_desc(comment::Column::Created),
-        "created" => stmt.order_by_asc(comment::Column::Created),
-        _ => stmt.order_by_desc(comment::Column::Coid),
-    };
+    let (column, order) = get_order_by(
+        order_by,
+        &[
+            ("coid", comment::Column::Coid),
+            ("created", comment::Column::Created),
+        ],
+    )?;
+    let stmt = stmt
+        .order_by(column, order.clone())
+        .order_by(comment::Column::Coid, order);
     let paginator = stmt.paginate(&state.conn, page_size);
     paginator
         .fetch_page(page - 1)
         .await
         .map_err(|_| FieldError::DatabaseFailed("get comments by list query failed".to_string()))
 }
+
+pub async fn get_exist_uids_by_uids(
+    state: &AppState,
+    uids: Vec<u32>,
+) -> Result<Vec<u32>, FieldError> {
+    if uids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    User::find()
+        .select_only()
+        .column(user::Column::Uid)
+        .filter(user::Column::Uid.is_in(uids))
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("get users failed".to_string()))
+}
+
+pub async fn get_comment_list(
+    state: &AppState,
+    name: &str,
+) -> Result<Vec<CommentListEntry>, FieldError> {
+    let option = user_db::get_option_by_uid_and_name(state, 0, name).await?;
+    Ok(option
+        .and_then(|o| serde_json::from_str(&o.value.unwrap_or_default()).ok())
+        .unwrap_or_default())
+}
+
+pub async fn save_comment_list(
+    state: &AppState,
+    name: &str,
+    entries: &[CommentListEntry],
+) -> Result<(), FieldError> {
+    let value = serde_json::to_string(entries)
+        .map_err(|_| FieldError::InvalidParams("value".to_string()))?;
+    match user_db::get_option_by_uid_and_name(state, 0, name).await? {
+        Some(_) => {
+            let option_modify = OptionModify { value };
+            user_db::modify_option_by_uid_and_name_with_option_modify(
+                state,
+                0,
+                name,
+                &option_modify,
+            )
+            .await?;
+        }
+        None => {
+            user_db::create_option_with_params(state, 0, name, &value).await?;
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,7 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Serialize, Deserialize, Validate)]
 pub struct CommentCreate {
@@ -30,4 +32,80 @@ pub struct CommentsQuery {
     #[validate(length(min = 1, max = 13, message = "order_by length must greater than 1"))]
     pub order_by: Option<String>,
     pub private: Option<bool>,
+    #[validate(length(min = 1, max = 16, message = "status can not be longer than 16"))]
+    pub status: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PendingCommentsQuery {
+    #[validate(range(min = 1, message = "page must greater than 1"))]
+    pub page: Option<u64>,
+    #[validate(range(min = 1, message = "page_size must greater than 1"))]
+    pub page_size: Option<u64>,
+    pub include_spam: Option<bool>,
+}
+
+pub const MODERATE_ACTIONS: [&str; 2] = ["approve", "reject"];
+
+fn validate_moderate_action(action: &str) -> Result<(), ValidationError> {
+    if MODERATE_ACTIONS.contains(&action) {
+        return Ok(());
+    }
+    Err(ValidationError::new("action").with_message(Cow::from("action must be approve or reject")))
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct CommentsModerate {
+    #[validate(length(min = 1, max = 100, message = "coids length must between 1 and 100"))]
+    pub coids: Vec<u32>,
+    #[validate(custom(function = "validate_moderate_action"))]
+    pub action: String,
+}
+
+pub const COMMENT_LIST_KINDS: [&str; 2] = ["mail", "ip"];
+
+fn validate_comment_list_kind(kind: &str) -> Result<(), ValidationError> {
+    if COMMENT_LIST_KINDS.contains(&kind) {
+        return Ok(());
+    }
+    Err(ValidationError::new("kind").with_message(Cow::from("kind must be mail or ip")))
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct CommentListEntryCreate {
+    #[validate(custom(function = "validate_comment_list_kind"))]
+    pub kind: String,
+    #[validate(length(min = 1, max = 200, message = "value length must between 1 and 200"))]
+    pub value: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CommentListEntry {
+    pub kind: String,
+    pub value: String,
+    pub created: u64,
+}
+
+impl CommentListEntry {
+    /// Mail addresses are compared case-insensitively, IPs exactly.
+    pub fn matches(&self, mail: &str, ip: &str) -> bool {
+        match self.kind.as_str() {
+            "mail" => self.value.eq_ignore_ascii_case(mail.trim()),
+            "ip" => self.value == ip,
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn test_comment_list_entry_matches() {
+    let entry = |kind: &str, value: &str| CommentListEntry {
+        kind: kind.to_string(),
+        value: value.to_string(),
+        created: 0,
+    };
+    assert!(entry("mail", "Trusted@Local.Host").matches("trusted@local.host", "127.0.0.1"));
+    assert!(!entry("mail", "trusted@local.host").matches("other@local.host", "127.0.0.1"));
+    assert!(entry("ip", "10.0.0.1").matches("any@local.host", "10.0.0.1"));
+    assert!(!entry("ip", "10.0.0.1").matches("10.0.0.1", "10.0.0.2"));
 }
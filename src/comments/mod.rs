@@ -1,6 +1,9 @@
+mod db;
 mod forms;
+mod models;
 mod urls;
 mod views;
-mod db;
 
-pub use urls::comments_routers;
\ No newline at end of file
+pub use models::{CommentEvent, COMMENT_EVENTS_CAPACITY};
+pub use urls::comments_routers;
+pub use views::prune_comment_attempts;
\ No newline at end of file
@@ -0,0 +1,100 @@
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{comment, content};
+
+#[derive(Serialize, Deserialize)]
+pub struct CommentWithAuthor {
+    pub coid: u32,
+    pub cid: u32,
+    pub created: u32,
+    pub author: Option<String>,
+    pub author_id: u32,
+    pub owner_id: u32,
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    pub text: Option<String>,
+    pub r#type: String,
+    pub status: String,
+    pub parent: u32,
+    pub gravatar: Option<String>,
+    pub registered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<CommentContent>,
+}
+
+/// The post or page a comment belongs to, attached in the moderation queue.
+#[derive(Serialize, Deserialize)]
+pub struct CommentContent {
+    pub cid: u32,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub r#type: String,
+}
+
+impl From<content::Model> for CommentContent {
+    fn from(content: content::Model) -> Self {
+        Self {
+            cid: content.cid,
+            title: content.title,
+            slug: content.slug,
+            r#type: content.r#type,
+        }
+    }
+}
+
+/// Buffered events per `/api/comments/stream` subscriber, slower readers skip the oldest.
+pub const COMMENT_EVENTS_CAPACITY: usize = 64;
+
+/// Pushed to `/api/comments/stream` subscribers for every created comment.
+#[derive(Clone, Serialize)]
+pub struct CommentEvent {
+    pub coid: u32,
+    pub slug: String,
+}
+
+pub fn get_gravatar_url(mail: &str, default: &str) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(mail.trim().to_lowercase().as_bytes());
+    format!(
+        "https://gravatar.com/avatar/{:x}?d={}",
+        hasher.finalize(),
+        default
+    )
+}
+
+impl CommentWithAuthor {
+    pub fn from_comment(comment: comment::Model, registered: bool, gravatar_default: &str) -> Self {
+        Self {
+            coid: comment.coid,
+            cid: comment.cid,
+            created: comment.created,
+            author: comment.author,
+            author_id: comment.author_id,
+            owner_id: comment.owner_id,
+            url: comment.url,
+            ip: comment.ip,
+            agent: comment.agent,
+            text: comment.text,
+            r#type: comment.r#type,
+            status: comment.status,
+            parent: comment.parent,
+            gravatar: comment
+                .mail
+                .map(|m| get_gravatar_url(&m, gravatar_default)),
+            registered,
+            content: None,
+        }
+    }
+}
+
+#[test]
+fn test_get_gravatar_url() {
+    assert_eq!(
+        get_gravatar_url(" MyEmailAddress@example.com ", "mp"),
+        "https://gravatar.com/avatar/0bc83cb571cd1c50ba6f3e8a78ef1346?d=mp"
+    );
+}
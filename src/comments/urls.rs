@@ -5,25 +5,68 @@ use axum::{
 use std::sync::Arc;
 
 use super::views;
+use crate::allow::method_not_allowed;
 use crate::AppState;
 
 pub fn comments_routers(ro: bool) -> Router<Arc<AppState>> {
     let comments_route = Router::new()
-        .route("/api/comments/", get(views::list_comments))
-        .route("/api/comments/:coid", get(views::get_comment_by_coid))
+        .route(
+            "/api/comments/",
+            get(views::list_comments).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/comments/pending",
+            get(views::list_pending_comments).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/comments/stream",
+            get(views::stream_comments).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/comments/:coid",
+            get(views::get_comment_by_coid).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/comments/lists/:list",
+            get(views::list_comment_list_entries).fallback(method_not_allowed),
+        )
         .route(
             "/api/pages/:slug/comments/",
-            get(views::list_page_comments_by_slug),
+            get(views::list_page_comments_by_slug).fallback(method_not_allowed),
         )
         .route(
             "/api/posts/:slug/comments/",
-            get(views::list_post_comments_by_slug),
+            get(views::list_post_comments_by_slug).fallback(method_not_allowed),
         );
 
     if !ro {
         comments_route
             .route("/api/comments/:coid", patch(views::modify_comment_by_coid))
             .route("/api/comments/:coid", delete(views::delete_comment_by_coid))
+            .route(
+                "/api/comments/:coid/approve",
+                post(views::approve_comment).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/comments/:coid/spam",
+                post(views::mark_comment_spam).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/comments/moderate",
+                post(views::moderate_comments).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/comments/lists/:list",
+                post(views::create_comment_list_entry),
+            )
+            .route(
+                "/api/comments/lists/:list/:kind/:value",
+                delete(views::delete_comment_list_entry).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/admin/contents/:cid/comments/recount",
+                post(views::recount_comments_for_content).fallback(method_not_allowed),
+            )
             .route(
                 "/api/pages/:slug/comments/",
                 post(views::create_page_comment),
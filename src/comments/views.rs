@@ -1,28 +1,46 @@
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::Json;
-use axum_client_ip::InsecureClientIp;
-use axum_extra::{headers::UserAgent, TypedHeader};
-use md5::{Digest, Md5};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization, UserAgent},
+    TypedHeader,
+};
+use futures::stream::{self, Stream};
 use serde_json::{json, Value};
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::broadcast::error::RecvError;
 
 use super::db;
-use super::forms::{CommentCreate, CommentModify, CommentsQuery};
+use super::forms::{
+    CommentCreate, CommentListEntry, CommentListEntryCreate, CommentModify, CommentsModerate,
+    CommentsQuery, PendingCommentsQuery,
+};
+use super::models::{CommentContent, CommentEvent, CommentWithAuthor};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
+use crate::common::extractors::{
+    ClientIp, PMAdministrator, PMEditor, PMVisitor, ValidatedJson, ValidatedQuery,
+};
+use crate::common::utils::{get_page_links, get_page_size};
 use crate::entity::comment;
-use crate::AppState;
+use crate::entity::user::Model as User;
+use crate::users::utils::get_user_by_token;
+use crate::{AppState, SpamVerdict};
 
 pub async fn create_page_comment(
     State(state): State<Arc<AppState>>,
     PMVisitor(user): PMVisitor,
-    TypedHeader(user_agent): TypedHeader<UserAgent>,
-    InsecureClientIp(ip): InsecureClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    ClientIp(ip): ClientIp,
     Path(slug): Path<String>,
     ValidatedJson(comment_create): ValidatedJson<CommentCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
+    let ip = ip.map(|ip| ip.to_string()).unwrap_or_default();
+    check_comment_rate_limit(&state, &user, &ip)?;
+
     let page = match common_db::get_content_by_slug(&state, &slug).await {
         Ok(Some(p)) => {
             if p.allow_comment == "0" {
@@ -67,11 +85,11 @@ pub async fn create_page_comment(
             url = user.url;
         }
     };
-    let ip = ip.to_string();
-    let ua = user_agent.to_string();
-    let status = "approved";
+    let ua = get_comment_agent(user_agent);
+    let status =
+        get_comment_status(&state, &user, &author, &mail, &comment_create.text, &ip).await?;
 
-    let _ = db::create_comment_with_params(
+    let comment = db::create_comment_with_params(
         &state,
         page.cid,
         &author,
@@ -86,18 +104,25 @@ pub async fn create_page_comment(
         parent,
     )
     .await?;
-    let _ = db::update_content_count_increase_by_cid(&state, page.cid).await?;
+    // sending only fails when no dashboard is listening
+    let _ = state.comment_events.send(CommentEvent {
+        coid: comment.coid.unwrap(),
+        slug,
+    });
     Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
 }
 
 pub async fn create_post_comment(
     State(state): State<Arc<AppState>>,
     PMVisitor(user): PMVisitor,
-    TypedHeader(user_agent): TypedHeader<UserAgent>,
-    InsecureClientIp(ip): InsecureClientIp,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    ClientIp(ip): ClientIp,
     Path(slug): Path<String>,
     ValidatedJson(comment_create): ValidatedJson<CommentCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
+    let ip = ip.map(|ip| ip.to_string()).unwrap_or_default();
+    check_comment_rate_limit(&state, &user, &ip)?;
+
     let post = match common_db::get_content_by_slug(&state, &slug).await {
         Ok(Some(p)) => {
             if p.allow_comment == "0" {
@@ -142,11 +167,11 @@ pub async fn create_post_comment(
             url = user.url;
         }
     };
-    let ip = ip.to_string();
-    let ua = user_agent.to_string();
-    let status = "approved";
+    let ua = get_comment_agent(user_agent);
+    let status =
+        get_comment_status(&state, &user, &author, &mail, &comment_create.text, &ip).await?;
 
-    let _ = db::create_comment_with_params(
+    let comment = db::create_comment_with_params(
         &state,
         post.cid,
         &author,
@@ -161,7 +186,11 @@ pub async fn create_post_comment(
         parent,
     )
     .await?;
-    let _ = db::update_content_count_increase_by_cid(&state, post.cid).await?;
+    // sending only fails when no dashboard is listening
+    let _ = state.comment_events.send(CommentEvent {
+        coid: comment.coid.unwrap(),
+        slug,
+    });
     Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
 }
 
@@ -170,10 +199,10 @@ pub async fn list_comments(
     PMEditor(_): PMEditor,
     ValidatedQuery(q): ValidatedQuery<CommentsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let all_count = db::get_comments_count(&state).await;
+    let all_count = db::get_comments_count(&state).await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-coid".to_string());
 
     let comments = db::get_comments_by_list_query(&state, page_size, page, &order_by).await?;
@@ -181,60 +210,189 @@ pub async fn list_comments(
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
+        "links": get_page_links(page, page_size, all_count),
         "count": comments.len(),
         "results": comments
     })))
 }
 
+fn get_status_filter(user: &User, q: &CommentsQuery) -> Result<Option<String>, FieldError> {
+    let admin = user.group == "editor" || user.group == "administrator";
+    if !admin {
+        return Ok(Some("approved".to_string()));
+    }
+
+    match q.status.as_deref() {
+        Some("approved") | Some("waiting") | Some("spam") => Ok(q.status.clone()),
+        Some(_) => Err(FieldError::InvalidParams("status".to_string())),
+        None => {
+            if q.private.unwrap_or(false) {
+                Ok(None)
+            } else {
+                Ok(Some("approved".to_string()))
+            }
+        }
+    }
+}
+
+/// Seconds a comment rate limit window lasts.
+const COMMENT_RATE_WINDOW: u64 = 60;
+
+/// Drops counters whose window has passed, the keys are client addresses and would pile up.
+pub fn prune_comment_attempts(state: &AppState, now: u64) {
+    state
+        .comment_attempts
+        .retain(|_, (_, start)| now < *start + COMMENT_RATE_WINDOW);
+}
+
+fn check_comment_rate_limit(state: &AppState, user: &User, ip: &str) -> Result<(), FieldError> {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let limit = if user.group == "visitor" {
+        state.comment_rate_limit
+    } else {
+        state.comment_rate_limit_user
+    };
+
+    let mut attempts = state
+        .comment_attempts
+        .entry(ip.to_string())
+        .or_insert((0, now));
+    if now >= attempts.1 + COMMENT_RATE_WINDOW {
+        *attempts = (0, now);
+    }
+    if attempts.0 >= limit {
+        return Err(FieldError::TooManyRequests(
+            attempts.1 + COMMENT_RATE_WINDOW - now,
+        ));
+    }
+    attempts.0 += 1;
+    Ok(())
+}
+
+/// Longest stored user agent, the width of the `agent` column.
+const COMMENT_AGENT_MAX_LENGTH: usize = 511;
+
+fn get_comment_agent(user_agent: Option<TypedHeader<UserAgent>>) -> String {
+    user_agent
+        .map(|TypedHeader(ua)| ua.as_str().chars().take(COMMENT_AGENT_MAX_LENGTH).collect())
+        .unwrap_or_default()
+}
+
+const COMMENT_ALLOWLIST: &str = "commentAllowlist";
+const COMMENT_DENYLIST: &str = "commentDenylist";
+
+fn get_comment_list_name(list: &str) -> Result<&'static str, FieldError> {
+    match list {
+        "allow" => Ok(COMMENT_ALLOWLIST),
+        "deny" => Ok(COMMENT_DENYLIST),
+        _ => Err(FieldError::NotFound("list".to_string())),
+    }
+}
+
+/// Denylisted authors are stored as spam without telling them, allowlisted ones skip
+/// both the spam checker and moderation.
+async fn get_comment_status(
+    state: &AppState,
+    user: &User,
+    author: &str,
+    mail: &str,
+    text: &str,
+    ip: &str,
+) -> Result<&'static str, FieldError> {
+    let denylist = db::get_comment_list(state, COMMENT_DENYLIST).await?;
+    if denylist.iter().any(|e| e.matches(mail, ip)) {
+        return Ok("spam");
+    }
+    let allowlist = db::get_comment_list(state, COMMENT_ALLOWLIST).await?;
+    if allowlist.iter().any(|e| e.matches(mail, ip)) {
+        return Ok("approved");
+    }
+
+    let verdict = state.spam_checker.check(author, mail, text, ip).await;
+    let status = if verdict == SpamVerdict::Spam {
+        "spam"
+    } else if user.group == "visitor" {
+        "waiting"
+    } else {
+        "approved"
+    };
+    Ok(status)
+}
+
+/// The client `ip` and `agent` are only kept for editors moderating comments.
+async fn get_comments_with_author(
+    state: &AppState,
+    user: &User,
+    comments: Vec<comment::Model>,
+) -> Result<Vec<CommentWithAuthor>, FieldError> {
+    let admin = user.group == "editor" || user.group == "administrator";
+    let author_ids = comments
+        .iter()
+        .filter(|c| c.author_id > 0)
+        .map(|c| c.author_id)
+        .collect::<Vec<u32>>();
+    let registered_uids = db::get_exist_uids_by_uids(state, author_ids).await?;
+
+    Ok(comments
+        .into_iter()
+        .map(|c| {
+            let registered = registered_uids.contains(&c.author_id);
+            let mut comment =
+                CommentWithAuthor::from_comment(c, registered, &state.gravatar_default);
+            if !admin {
+                comment.ip = None;
+                comment.agent = None;
+            }
+            comment
+        })
+        .collect())
+}
+
 pub async fn list_page_comments_by_slug(
     State(state): State<Arc<AppState>>,
     PMVisitor(user): PMVisitor,
     Path(slug): Path<String>,
     ValidatedQuery(q): ValidatedQuery<CommentsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let private =
-        q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
+    let status = get_status_filter(&user, &q)?;
 
-    let target_page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
-        _ => return Err(FieldError::InvalidParams("slug".to_string())),
+    let target_page = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) => p,
+        None => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
-    let all_count =
-        db::get_content_comments_count_by_cid_with_private(&state, target_page.cid, private).await;
+    let all_count = db::get_content_comments_count_by_cid_with_status(
+        &state,
+        target_page.cid,
+        status.as_deref(),
+    )
+    .await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-coid".to_string());
 
-    let comments = db::get_comments_by_cid_and_list_query_with_private(
+    let comments = db::get_comments_by_cid_and_list_query_with_status(
         &state,
         target_page.cid,
-        private,
+        status.as_deref(),
         page_size,
         page,
         &order_by,
     )
     .await?;
 
-    let mut hasher = Md5::new();
-    let mut hashed_comments = vec![];
-    for cm in comments {
-        let mail = match cm.mail {
-            Some(m) => {
-                hasher.update(m.as_bytes());
-                Some(format!("{:x}", hasher.finalize_reset()))
-            }
-            None => None,
-        };
-        hashed_comments.push(comment::Model { mail: mail, ..cm })
-    }
+    let comments = get_comments_with_author(&state, &user, comments).await?;
     return Ok(Json(json!({
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
-        "count": hashed_comments.len(),
-        "results": hashed_comments
+        "links": get_page_links(page, page_size, all_count),
+        "count": comments.len(),
+        "results": comments
     })));
 }
 
@@ -244,52 +402,42 @@ pub async fn list_post_comments_by_slug(
     Path(slug): Path<String>,
     ValidatedQuery(q): ValidatedQuery<CommentsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let private =
-        q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
+    let status = get_status_filter(&user, &q)?;
 
-    let target_post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
-        _ => return Err(FieldError::InvalidParams("slug".to_string())),
+    let target_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) => p,
+        None => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
-    let all_count =
-        db::get_content_comments_count_by_cid_with_private(&state, target_post.cid, private).await;
+    let all_count = db::get_content_comments_count_by_cid_with_status(
+        &state,
+        target_post.cid,
+        status.as_deref(),
+    )
+    .await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-coid".to_string());
 
-    let comments = db::get_comments_by_cid_and_list_query_with_private(
+    let comments = db::get_comments_by_cid_and_list_query_with_status(
         &state,
         target_post.cid,
-        private,
+        status.as_deref(),
         page_size,
         page,
         &order_by,
     )
     .await?;
 
-    let mut hasher = Md5::new();
-    let mut hashed_comments = vec![];
-    for comment in comments {
-        let mail = match comment.mail {
-            Some(m) => {
-                hasher.update(m.as_bytes());
-                Some(format!("{:x}", hasher.finalize_reset()))
-            }
-            None => None,
-        };
-        hashed_comments.push(comment::Model {
-            mail: mail,
-            ..comment
-        })
-    }
+    let comments = get_comments_with_author(&state, &user, comments).await?;
     return Ok(Json(json!({
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
-        "count": hashed_comments.len(),
-        "results": hashed_comments
+        "links": get_page_links(page, page_size, all_count),
+        "count": comments.len(),
+        "results": comments
     })));
 }
 
@@ -298,8 +446,8 @@ pub async fn get_comment_by_coid(
     PMEditor(_): PMEditor,
     Path(coid): Path<u32>,
 ) -> Result<Json<Value>, FieldError> {
-    match db::get_comment_by_coid(&state, coid).await {
-        Ok(Some(comment)) => Ok(Json(json!(comment))),
+    match db::get_comment_by_coid(&state, coid).await? {
+        Some(comment) => Ok(Json(json!(comment))),
         _ => Err(FieldError::NotFound("coid".to_string())),
     }
 }
@@ -310,8 +458,8 @@ pub async fn modify_comment_by_coid(
     Path(coid): Path<u32>,
     ValidatedJson(comment_modify): ValidatedJson<CommentModify>,
 ) -> Result<Json<Value>, FieldError> {
-    match db::get_comment_by_coid(&state, coid).await {
-        Ok(Some(comment)) => Some(comment),
+    match db::get_comment_by_coid(&state, coid).await? {
+        Some(comment) => Some(comment),
         _ => return Err(FieldError::NotFound("coid".to_string())),
     };
 
@@ -326,18 +474,208 @@ pub async fn modify_comment_by_coid(
     Ok(Json(json!({ "msg": "ok" })))
 }
 
-pub async fn delete_comment_by_coid(
+pub async fn approve_comment(
     State(state): State<Arc<AppState>>,
     PMEditor(_): PMEditor,
     Path(coid): Path<u32>,
 ) -> Result<Json<Value>, FieldError> {
-    let comment = match db::get_comment_by_coid(&state, coid).await {
-        Ok(Some(comment)) => comment,
+    match db::get_comment_by_coid(&state, coid).await? {
+        Some(comment) => comment,
         _ => return Err(FieldError::NotFound("coid".to_string())),
     };
-    let cid = comment.cid;
-    let _ = db::update_content_count_decrease_by_cid(&state, cid).await?;
 
+    let _ = db::update_comment_status_by_coid(&state, coid, "approved").await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn mark_comment_spam(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(coid): Path<u32>,
+) -> Result<Json<Value>, FieldError> {
+    match db::get_comment_by_coid(&state, coid).await? {
+        Some(comment) => comment,
+        _ => return Err(FieldError::NotFound("coid".to_string())),
+    };
+
+    let _ = db::update_comment_status_by_coid(&state, coid, "spam").await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn list_pending_comments(
+    State(state): State<Arc<AppState>>,
+    PMEditor(user): PMEditor,
+    ValidatedQuery(q): ValidatedQuery<PendingCommentsQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let statuses: &[&str] = match q.include_spam.unwrap_or(false) {
+        true => &["waiting", "spam"],
+        false => &["waiting"],
+    };
+    let all_count = db::get_comments_count_by_statuses(&state, statuses).await?;
+
+    let page = q.page.unwrap_or(1);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
+    let rows = db::get_comments_with_content_by_statuses(&state, statuses, page_size, page).await?;
+
+    let (comments, contents): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+    let mut comments = get_comments_with_author(&state, &user, comments).await?;
+    for (comment, content) in comments.iter_mut().zip(contents) {
+        comment.content = content.map(CommentContent::from);
+    }
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": all_count,
+        "links": get_page_links(page, page_size, all_count),
+        "count": comments.len(),
+        "results": comments
+    })))
+}
+
+/// Comments are moderated one by one so each keeps its content's comment count right,
+/// unknown ids are reported back instead of failing the whole batch.
+pub async fn moderate_comments(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    ValidatedJson(comments_moderate): ValidatedJson<CommentsModerate>,
+) -> Result<Json<Value>, FieldError> {
+    let status = match comments_moderate.action.as_str() {
+        "approve" => "approved",
+        _ => "spam",
+    };
+
+    let mut updated = vec![];
+    let mut missing = vec![];
+    for coid in comments_moderate.coids {
+        match db::get_comment_by_coid(&state, coid).await? {
+            Some(_) => {
+                db::update_comment_status_by_coid(&state, coid, status).await?;
+                updated.push(coid);
+            }
+            None => missing.push(coid),
+        }
+    }
+    Ok(Json(json!({ "updated": updated, "missing": missing })))
+}
+
+/// Streams a `comment` event for every new comment. Dropping the stream on disconnect
+/// drops the receiver, a reader lagging behind the buffer skips the missed events.
+/// The token is checked again before each event, the stream ends once it expires, is
+/// revoked or its user is no longer an editor.
+pub async fn stream_comments(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.comment_events.subscribe();
+    let token = bearer.token().to_string();
+    let events = stream::unfold(receiver, move |mut receiver| {
+        let state = state.clone();
+        let token = token.clone();
+        async move {
+            loop {
+                let comment_event = match receiver.recv().await {
+                    Ok(comment_event) => comment_event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                };
+                match get_user_by_token(&state, &token, true).await {
+                    Ok(user) if user.group == "editor" || user.group == "administrator" => (),
+                    _ => return None,
+                }
+                let event = Event::default()
+                    .event("comment")
+                    .data(json!(comment_event).to_string());
+                return Some((Ok(event), receiver));
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+pub async fn delete_comment_by_coid(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(coid): Path<u32>,
+) -> Result<Json<Value>, FieldError> {
+    match db::get_comment_by_coid(&state, coid).await? {
+        Some(comment) => comment,
+        _ => return Err(FieldError::NotFound("coid".to_string())),
+    };
     let _ = db::delete_comment_by_coid(&state, coid).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
+
+pub async fn recount_comments_for_content(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    Path(cid): Path<u32>,
+) -> Result<Json<Value>, FieldError> {
+    match common_db::get_content_by_cid(&state, cid).await? {
+        Some(content) => content,
+        _ => return Err(FieldError::NotFound("cid".to_string())),
+    };
+
+    let comments_num = db::recount_comments_num_by_cid(&state, cid).await?;
+    Ok(Json(json!({ "cid": cid, "comments_num": comments_num })))
+}
+
+pub async fn list_comment_list_entries(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(list): Path<String>,
+) -> Result<Json<Value>, FieldError> {
+    let name = get_comment_list_name(&list)?;
+    let entries = db::get_comment_list(&state, name).await?;
+    Ok(Json(json!({
+        "list": list,
+        "count": entries.len(),
+        "results": entries,
+    })))
+}
+
+pub async fn create_comment_list_entry(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(list): Path<String>,
+    ValidatedJson(entry_create): ValidatedJson<CommentListEntryCreate>,
+) -> Result<(StatusCode, Json<Value>), FieldError> {
+    let name = get_comment_list_name(&list)?;
+    let value = entry_create.value.trim().to_string();
+    let mut entries = db::get_comment_list(&state, name).await?;
+    if entries
+        .iter()
+        .any(|e| e.kind == entry_create.kind && e.value.eq_ignore_ascii_case(&value))
+    {
+        return Err(FieldError::AlreadyExist("value".to_string()));
+    }
+
+    let entry = CommentListEntry {
+        kind: entry_create.kind,
+        value,
+        created: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+    entries.push(entry.clone());
+    db::save_comment_list(&state, name, &entries).await?;
+    Ok((StatusCode::CREATED, Json(json!(entry))))
+}
+
+pub async fn delete_comment_list_entry(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path((list, kind, value)): Path<(String, String, String)>,
+) -> Result<Json<Value>, FieldError> {
+    let name = get_comment_list_name(&list)?;
+    let mut entries = db::get_comment_list(&state, name).await?;
+    let count = entries.len();
+    entries.retain(|e| !(e.kind == kind && e.value.eq_ignore_ascii_case(&value)));
+    if entries.len() == count {
+        return Err(FieldError::NotFound("value".to_string()));
+    }
+
+    db::save_comment_list(&state, name, &entries).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
@@ -1,12 +1,16 @@
+use std::time::SystemTime;
+
 use sea_orm::*;
 
-use super::forms::FieldCreate;
-use super::utils::get_field_params;
+use super::forms::{FieldCreate, TimeRange};
+use super::utils::{get_field_params, get_order_by};
 use crate::common::errors::FieldError;
 use crate::common::models::ContentWithMetasUsersFields;
+use crate::users::db as user_db;
 use crate::entity::{
-    content, content::Entity as Content, field, field::Entity as ContentField, meta,
-    meta::Entity as Meta, relationship, relationship::Entity as Relationship, user,
+    comment, comment::Entity as Comment, content, content::Entity as Content, field,
+    field::Entity as ContentField, meta, meta::Entity as Meta, relationship,
+    relationship::Entity as Relationship, user, user::Entity as User,
 };
 use crate::AppState;
 
@@ -18,7 +22,7 @@ pub async fn get_content_by_cid(
         .filter(content::Column::Cid.eq(cid))
         .one(&state.conn)
         .await
-        .map_err(|_| FieldError::InvalidParams("cid".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))
 }
 
 pub async fn get_content_by_slug(
@@ -29,7 +33,7 @@ pub async fn get_content_by_slug(
         .filter(content::Column::Slug.eq(slug))
         .one(&state.conn)
         .await
-        .map_err(|_| FieldError::InvalidParams("slug".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))
 }
 
 pub async fn get_contents_count_with_private(
@@ -37,20 +41,57 @@ pub async fn get_contents_count_with_private(
     private: bool,
     own: bool,
     author: &user::Model,
+    status: Option<&str>,
+    range: &TimeRange,
     content_type: &str,
-) -> u64 {
+) -> Result<u64, FieldError> {
     let stmt = Content::find().filter(content::Column::Type.eq(content_type));
     let stmt = if own {
         stmt.filter(content::Column::AuthorId.eq(author.uid))
     } else {
         stmt
     };
-    let stmt = if private {
-        stmt
-    } else {
-        stmt.filter(content::Column::Status.eq("publish"))
-    };
-    stmt.count(&state.conn).await.unwrap_or(0)
+    let stmt = filter_contents_by_status(stmt, private, status);
+    let stmt = filter_contents_by_time_range(stmt, range);
+    stmt.count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count contents failed".to_string()))
+}
+
+/// Keeps counts and listings in step: an explicit status wins, private hides the trash,
+/// otherwise only published contents that are already due are visible.
+pub fn filter_contents_by_status(
+    stmt: Select<Content>,
+    private: bool,
+    status: Option<&str>,
+) -> Select<Content> {
+    match status {
+        Some(status) => stmt.filter(content::Column::Status.eq(status)),
+        None if private => stmt.filter(content::Column::Status.ne("trash")),
+        None => {
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as u32;
+            stmt.filter(content::Column::Status.eq("publish"))
+                .filter(content::Column::Created.lte(now))
+        }
+    }
+}
+
+pub fn filter_contents_by_time_range(stmt: Select<Content>, range: &TimeRange) -> Select<Content> {
+    stmt.apply_if(range.created_after, |s, v| {
+        s.filter(content::Column::Created.gte(v))
+    })
+    .apply_if(range.created_before, |s, v| {
+        s.filter(content::Column::Created.lte(v))
+    })
+    .apply_if(range.modified_after, |s, v| {
+        s.filter(content::Column::Modified.gte(v))
+    })
+    .apply_if(range.modified_before, |s, v| {
+        s.filter(content::Column::Modified.lte(v))
+    })
 }
 
 pub async fn get_contents_with_metas_user_and_fields_by_mid_list_query_and_private(
@@ -77,13 +118,16 @@ pub async fn get_contents_with_metas_user_and_fields_by_mid_list_query_and_priva
         stmt.filter(content::Column::Status.eq("publish"))
     };
 
-    let stmt = match order_by {
-        "-cid" => stmt.order_by_desc(content::Column::Cid),
-        "cid" => stmt.order_by_asc(content::Column::Cid),
-        "-slug" => stmt.order_by_desc(content::Column::Slug),
-        "slug" => stmt.order_by_asc(content::Column::Slug),
-        _ => stmt.order_by_desc(content::Column::Cid),
-    };
+    let (column, order) = get_order_by(
+        order_by,
+        &[
+            ("cid", content::Column::Cid),
+            ("slug", content::Column::Slug),
+            ("created", content::Column::Created),
+            ("modified", content::Column::Modified),
+        ],
+    )?;
+    let stmt = stmt.order_by(column, order);
 
     let paginator = stmt.paginate(&state.conn, page_size);
 
@@ -207,6 +251,18 @@ pub async fn delete_relationships_by_mid(
         .map_err(|_| FieldError::DatabaseFailed("delete relationships failed".to_string()))
 }
 
+pub async fn get_fields_by_cid(
+    state: &AppState,
+    cid: u32,
+) -> Result<Vec<field::Model>, FieldError> {
+    ContentField::find()
+        .filter(field::Column::Cid.eq(cid))
+        .order_by_asc(field::Column::Name)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch fields failed".to_string()))
+}
+
 pub async fn get_field_by_cid_and_name(
     state: &AppState,
     cid: u32,
@@ -329,13 +385,11 @@ pub async fn get_metas_by_list_query(
 
     let stmt = Meta::find().filter(meta::Column::Type.eq(meta_type));
 
-    let stmt = match order_by {
-        "-mid" => stmt.order_by_desc(meta::Column::Mid),
-        "mid" => stmt.order_by_asc(meta::Column::Mid),
-        "-slug" => stmt.order_by_desc(meta::Column::Slug),
-        "slug" => stmt.order_by_asc(meta::Column::Slug),
-        _ => stmt.order_by_desc(meta::Column::Mid),
-    };
+    let (column, order) = get_order_by(
+        order_by,
+        &[("mid", meta::Column::Mid), ("slug", meta::Column::Slug)],
+    )?;
+    let stmt = stmt.order_by(column, order);
     let paginator = stmt.paginate(&state.conn, page_size);
 
     paginator
@@ -344,33 +398,58 @@ pub async fn get_metas_by_list_query(
         .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))
 }
 
-pub async fn get_metas_count(state: &AppState, tag: bool) -> u64 {
+pub async fn get_metas_count(state: &AppState, tag: bool) -> Result<u64, FieldError> {
     let meta_type = if tag { "tag" } else { "category" };
 
     Meta::find()
         .filter(meta::Column::Type.eq(meta_type))
         .count(&state.conn)
         .await
-        .unwrap_or(0)
+        .map_err(|_| FieldError::DatabaseFailed("count metas failed".to_string()))
+}
+
+/// Recomputes every meta `count` from its relationships in one UPDATE, so concurrent writes
+/// are never overwritten with a stale value. Returns how many metas were corrected.
+pub async fn recount_metas(state: &AppState) -> Result<u64, FieldError> {
+    let relationships_count = sea_query::Query::select()
+        .expr(sea_query::Expr::col((Relationship, relationship::Column::Cid)).count())
+        .from(Relationship)
+        .and_where(
+            sea_query::Expr::col((Relationship, relationship::Column::Mid))
+                .equals((Meta, meta::Column::Mid)),
+        )
+        .to_owned();
+    let count = sea_query::SimpleExpr::SubQuery(
+        None,
+        Box::new(relationships_count.into_sub_query_statement()),
+    );
+
+    Meta::update_many()
+        .col_expr(meta::Column::Count, count.clone())
+        .filter(sea_query::Expr::col((Meta, meta::Column::Count)).ne(count))
+        .exec(&state.conn)
+        .await
+        .map(|r| r.rows_affected)
+        .map_err(|_| FieldError::DatabaseFailed("recount metas failed".to_string()))
 }
 
 pub async fn get_meta_posts_count_by_mid_with_private(
     state: &AppState,
     mid: u32,
     private: bool,
-) -> u64 {
+) -> Result<u64, FieldError> {
     let stmt = Content::find()
         .left_join(Meta)
         .filter(meta::Column::Mid.eq(mid));
 
-    if private {
-        stmt.count(&state.conn).await.unwrap_or(0)
+    let stmt = if private {
+        stmt
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
-            .count(&state.conn)
-            .await
-            .unwrap_or(0)
-    }
+    };
+    stmt.count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count contents failed".to_string()))
 }
 
 pub async fn update_meta_by_mid_for_increase_count(
@@ -431,3 +510,135 @@ pub async fn delete_meta_by_mid(state: &AppState, mid: u32) -> Result<DeleteResu
         .await
         .map_err(|_| FieldError::DatabaseFailed("delete meta failed".to_string()))
 }
+
+/// `SITE_URL` wins over the `siteUrl` option stored by Typecho.
+pub async fn get_site_url(state: &AppState) -> Result<String, FieldError> {
+    if !state.site_url.is_empty() {
+        return Ok(state.site_url.clone());
+    }
+    let site_url = user_db::get_option_by_uid_and_name(state, 0, "siteUrl")
+        .await?
+        .and_then(|o| o.value)
+        .unwrap_or_default();
+    Ok(site_url.trim_end_matches('/').to_string())
+}
+
+fn get_sitemap_select() -> Select<Content> {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32;
+
+    Content::find()
+        .filter(content::Column::Type.is_in(["post", "page"]))
+        .filter(content::Column::Status.eq("publish"))
+        .filter(content::Column::Created.lte(now))
+        .filter(
+            Condition::any()
+                .add(content::Column::Password.is_null())
+                .add(content::Column::Password.eq("")),
+        )
+}
+
+pub async fn get_sitemap_contents_count(state: &AppState) -> Result<u64, FieldError> {
+    get_sitemap_select()
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count contents failed".to_string()))
+}
+
+pub async fn get_sitemap_contents(
+    state: &AppState,
+    page_size: u64,
+    page: u64,
+) -> Result<Vec<(String, Option<String>, u32)>, FieldError> {
+    get_sitemap_select()
+        .select_only()
+        .columns([
+            content::Column::Type,
+            content::Column::Slug,
+            content::Column::Modified,
+        ])
+        .order_by_asc(content::Column::Cid)
+        .offset((page - 1) * page_size)
+        .limit(page_size)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch sitemap contents failed".to_string()))
+}
+
+/// Slugs and modified times of everything the sitemap lists, optionally only those changed after `since`.
+pub async fn get_public_slugs(
+    state: &AppState,
+    since: Option<u32>,
+) -> Result<Vec<(String, Option<String>, u32)>, FieldError> {
+    let stmt = get_sitemap_select();
+    let stmt = match since {
+        Some(since) => stmt.filter(content::Column::Modified.gt(since)),
+        None => stmt,
+    };
+    stmt.select_only()
+        .columns([
+            content::Column::Type,
+            content::Column::Slug,
+            content::Column::Modified,
+        ])
+        .order_by_asc(content::Column::Cid)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch slugs failed".to_string()))
+}
+
+pub async fn get_contents_count_group_by_type_and_status(
+    state: &AppState,
+) -> Result<Vec<(String, String, i64)>, FieldError> {
+    Content::find()
+        .select_only()
+        .columns([content::Column::Type, content::Column::Status])
+        .column_as(content::Column::Cid.count(), "count")
+        .group_by(content::Column::Type)
+        .group_by(content::Column::Status)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count contents failed".to_string()))
+}
+
+pub async fn get_comments_count_group_by_status(
+    state: &AppState,
+) -> Result<Vec<(String, i64)>, FieldError> {
+    Comment::find()
+        .select_only()
+        .column(comment::Column::Status)
+        .column_as(comment::Column::Coid.count(), "count")
+        .group_by(comment::Column::Status)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count comments failed".to_string()))
+}
+
+pub async fn get_users_count_group_by_group(
+    state: &AppState,
+) -> Result<Vec<(String, i64)>, FieldError> {
+    User::find()
+        .select_only()
+        .column(user::Column::Group)
+        .column_as(user::Column::Uid.count(), "count")
+        .group_by(user::Column::Group)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count users failed".to_string()))
+}
+
+pub async fn get_attachments(state: &AppState) -> Result<Vec<content::Model>, FieldError> {
+    Content::find()
+        .filter(content::Column::Type.eq("attachment"))
+        .filter(content::Column::Status.ne("trash"))
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch attachments failed".to_string()))
+}
@@ -1,10 +1,12 @@
 use axum::{
     extract::rejection::{JsonRejection, QueryRejection},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use sea_orm::{sqlx, DbErr, RuntimeErr};
+use serde::Serialize;
+use serde_json::{json, Value};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -12,6 +14,8 @@ pub enum ValidateRequestError {
     #[error(transparent)]
     ValidationError(#[from] validator::ValidationErrors),
     #[error(transparent)]
+    JsonValidationError(validator::ValidationErrors),
+    #[error(transparent)]
     AxumFormRejection(#[from] JsonRejection),
     #[error(transparent)]
     AxumQueryRejection(#[from] QueryRejection),
@@ -20,29 +24,91 @@ pub enum ValidateRequestError {
 impl IntoResponse for ValidateRequestError {
     fn into_response(self) -> Response {
         match self {
-            ValidateRequestError::ValidationError(_) => {
+            ValidateRequestError::ValidationError(ref e) => {
+                let field = e.field_errors().keys().min().map(|f| f.to_string());
+                let message = format!("Input validation error: {}", self).replace('\n', ", ");
+                (
+                    StatusCode::BAD_REQUEST,
+                    error_body(ErrorCode::ValidationError, field, message),
+                )
+            }
+            ValidateRequestError::JsonValidationError(ref e) => {
+                let field = e.field_errors().keys().min().map(|f| f.to_string());
                 let message = format!("Input validation error: {}", self).replace('\n', ", ");
-                let message = Json(json!({ "msg": message }));
-                (StatusCode::BAD_REQUEST, message)
+                let Json(mut body) = error_body(ErrorCode::ValidationError, field, message);
+                body["errors"] = validation_errors_map(e);
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response();
             }
-            ValidateRequestError::AxumFormRejection(_) => (
+            ValidateRequestError::AxumFormRejection(ref e) => (
                 StatusCode::BAD_REQUEST,
-                Json(json!({"msg": "Invalid json"})),
+                error_body(
+                    ErrorCode::InvalidJson,
+                    None,
+                    format!("Invalid json: {}", e.body_text()),
+                ),
             ),
             ValidateRequestError::AxumQueryRejection(_) => (
                 StatusCode::BAD_REQUEST,
-                Json(json!({"msg": "Invalid query params"})),
+                error_body(ErrorCode::InvalidQuery, None, "Invalid query params"),
             ),
         }
         .into_response()
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    ValidationError,
+    InvalidJson,
+    InvalidQuery,
+    WrongCredentials,
+    InvalidToken,
+    PermissionDenied,
+    TooManyAttempts,
+    TokenCreationFailed,
+    NotFound,
+    MethodNotAllowed,
+    AlreadyExists,
+    EditConflict,
+    PasswordRequired,
+    InvalidParams,
+    DatabaseFailed,
+    TooManyRequests,
+}
+
+fn validation_errors_map(errors: &validator::ValidationErrors) -> Value {
+    let mut map = serde_json::Map::new();
+    for (field, field_errors) in errors.field_errors() {
+        let messages: Vec<String> = field_errors
+            .iter()
+            .map(|e| match e.message {
+                Some(ref message) => message.to_string(),
+                None => e.code.to_string(),
+            })
+            .collect();
+        map.insert(field.to_string(), json!(messages));
+    }
+    Value::Object(map)
+}
+
+fn error_body(code: ErrorCode, field: Option<String>, message: impl Into<String>) -> Json<Value> {
+    let message = message.into();
+    Json(json!({
+        "code": code,
+        "field": field,
+        "message": message,
+        "msg": message,
+    }))
+}
+
 #[derive(Debug)]
 pub enum AuthError {
     WrongCredentials,
     InvalidToken,
     PermissionDeny,
+    TooManyAttempts,
+    TokenCreationFailed,
 }
 
 impl IntoResponse for AuthError {
@@ -50,15 +116,23 @@ impl IntoResponse for AuthError {
         match self {
             AuthError::WrongCredentials => (
                 StatusCode::UNAUTHORIZED,
-                Json(json!({"msg": "Wrong credentials"})),
+                error_body(ErrorCode::WrongCredentials, None, "Wrong credentials"),
             ),
             AuthError::InvalidToken => (
                 StatusCode::BAD_REQUEST,
-                Json(json!({"msg": "Invalid token"})),
+                error_body(ErrorCode::InvalidToken, None, "Invalid token"),
             ),
             AuthError::PermissionDeny => (
                 StatusCode::FORBIDDEN,
-                Json(json!({"msg": "Permission deny"})),
+                error_body(ErrorCode::PermissionDenied, None, "Permission deny"),
+            ),
+            AuthError::TooManyAttempts => (
+                StatusCode::TOO_MANY_REQUESTS,
+                error_body(ErrorCode::TooManyAttempts, None, "Too many attempts"),
+            ),
+            AuthError::TokenCreationFailed => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_body(ErrorCode::TokenCreationFailed, None, "Create token failed"),
             ),
         }
         .into_response()
@@ -68,11 +142,15 @@ impl IntoResponse for AuthError {
 #[derive(Debug)]
 pub enum FieldError {
     NotFound(String),
+    MethodNotAllowed,
     AlreadyExist(String),
+    Conflict(String),
+    Modified(String),
     PermissionDeny,
     PasswordRequired,
     InvalidParams(String),
     DatabaseFailed(String),
+    TooManyRequests(u64),
 }
 
 impl IntoResponse for FieldError {
@@ -80,29 +158,291 @@ impl IntoResponse for FieldError {
         match self {
             FieldError::NotFound(field) => (
                 StatusCode::NOT_FOUND,
-                Json(json!({ "msg": format!("{} not found", field) })),
+                error_body(
+                    ErrorCode::NotFound,
+                    Some(field.clone()),
+                    format!("{} not found", field),
+                ),
+            ),
+            FieldError::MethodNotAllowed => (
+                StatusCode::METHOD_NOT_ALLOWED,
+                error_body(ErrorCode::MethodNotAllowed, None, "Method not allowed"),
             ),
             FieldError::AlreadyExist(field) => (
                 StatusCode::BAD_REQUEST,
-                Json(json!({ "msg": format!("{} already exist", field) })),
+                error_body(
+                    ErrorCode::AlreadyExists,
+                    Some(field.clone()),
+                    format!("{} already exist", field),
+                ),
+            ),
+            FieldError::Conflict(field) => (
+                StatusCode::CONFLICT,
+                error_body(
+                    ErrorCode::AlreadyExists,
+                    Some(field.clone()),
+                    format!("{} already exist", field),
+                ),
+            ),
+            FieldError::Modified(field) => (
+                StatusCode::CONFLICT,
+                error_body(
+                    ErrorCode::EditConflict,
+                    Some(field.clone()),
+                    format!("{} modified since", field),
+                ),
             ),
             FieldError::PermissionDeny => (
                 StatusCode::FORBIDDEN,
-                Json(json!({"msg": "Permission deny"})),
+                error_body(ErrorCode::PermissionDenied, None, "Permission deny"),
             ),
             FieldError::PasswordRequired => (
                 StatusCode::UNAUTHORIZED,
-                Json(json!({"msg": "Password required"})),
+                error_body(ErrorCode::PasswordRequired, None, "Password required"),
             ),
             FieldError::InvalidParams(field) => (
                 StatusCode::BAD_REQUEST,
-                Json(json!({ "msg": format!("Invalid {}", field) })),
+                error_body(
+                    ErrorCode::InvalidParams,
+                    Some(field.clone()),
+                    format!("Invalid {}", field),
+                ),
             ),
             FieldError::DatabaseFailed(s) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "msg": format!("{}", s) })),
+                error_body(ErrorCode::DatabaseFailed, None, s),
             ),
+            FieldError::TooManyRequests(retry_after) => {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    error_body(ErrorCode::TooManyRequests, None, "Too many requests"),
+                )
+                    .into_response()
+            }
         }
         .into_response()
     }
 }
+
+/// Whether the database rejected a write because of a unique constraint.
+pub fn is_unique_violation(err: &DbErr) -> bool {
+    match err {
+        DbErr::Exec(RuntimeErr::SqlxError(sqlx::Error::Database(e)))
+        | DbErr::Query(RuntimeErr::SqlxError(sqlx::Error::Database(e))) => {
+            e.kind() == sqlx::error::ErrorKind::UniqueViolation
+        }
+        _ => false,
+    }
+}
+
+/// Maps a failed insert to `Conflict(field)` on unique violations, `DatabaseFailed` otherwise.
+pub fn insert_error(err: DbErr, field: &str, message: &str) -> FieldError {
+    match is_unique_violation(&err) {
+        true => FieldError::Conflict(field.to_string()),
+        false => FieldError::DatabaseFailed(message.to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_error_body() {
+    async fn check(response: Response, status: StatusCode, code: &str, field: Option<&str>) {
+        assert_eq!(response.status(), status);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], code);
+        assert_eq!(body["field"].as_str(), field);
+        assert!(body["message"].is_string());
+    }
+
+    let cases = [
+        (
+            AuthError::WrongCredentials.into_response(),
+            StatusCode::UNAUTHORIZED,
+            "WRONG_CREDENTIALS",
+            None,
+        ),
+        (
+            AuthError::InvalidToken.into_response(),
+            StatusCode::BAD_REQUEST,
+            "INVALID_TOKEN",
+            None,
+        ),
+        (
+            AuthError::PermissionDeny.into_response(),
+            StatusCode::FORBIDDEN,
+            "PERMISSION_DENIED",
+            None,
+        ),
+        (
+            AuthError::TooManyAttempts.into_response(),
+            StatusCode::TOO_MANY_REQUESTS,
+            "TOO_MANY_ATTEMPTS",
+            None,
+        ),
+        (
+            AuthError::TokenCreationFailed.into_response(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "TOKEN_CREATION_FAILED",
+            None,
+        ),
+        (
+            FieldError::NotFound("slug".to_string()).into_response(),
+            StatusCode::NOT_FOUND,
+            "NOT_FOUND",
+            Some("slug"),
+        ),
+        (
+            FieldError::MethodNotAllowed.into_response(),
+            StatusCode::METHOD_NOT_ALLOWED,
+            "METHOD_NOT_ALLOWED",
+            None,
+        ),
+        (
+            FieldError::AlreadyExist("slug".to_string()).into_response(),
+            StatusCode::BAD_REQUEST,
+            "ALREADY_EXISTS",
+            Some("slug"),
+        ),
+        (
+            FieldError::Conflict("slug".to_string()).into_response(),
+            StatusCode::CONFLICT,
+            "ALREADY_EXISTS",
+            Some("slug"),
+        ),
+        (
+            FieldError::Modified("post".to_string()).into_response(),
+            StatusCode::CONFLICT,
+            "EDIT_CONFLICT",
+            Some("post"),
+        ),
+        (
+            FieldError::PermissionDeny.into_response(),
+            StatusCode::FORBIDDEN,
+            "PERMISSION_DENIED",
+            None,
+        ),
+        (
+            FieldError::PasswordRequired.into_response(),
+            StatusCode::UNAUTHORIZED,
+            "PASSWORD_REQUIRED",
+            None,
+        ),
+        (
+            FieldError::InvalidParams("slug".to_string()).into_response(),
+            StatusCode::BAD_REQUEST,
+            "INVALID_PARAMS",
+            Some("slug"),
+        ),
+        (
+            FieldError::DatabaseFailed("failed".to_string()).into_response(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DATABASE_FAILED",
+            None,
+        ),
+        (
+            FieldError::TooManyRequests(60).into_response(),
+            StatusCode::TOO_MANY_REQUESTS,
+            "TOO_MANY_REQUESTS",
+            None,
+        ),
+    ];
+    for (response, status, code, field) in cases {
+        check(response, status, code, field).await;
+    }
+
+    let mut errors = validator::ValidationErrors::new();
+    errors.add("page", validator::ValidationError::new("range"));
+    let response = ValidateRequestError::ValidationError(errors).into_response();
+    check(
+        response,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        Some("page"),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_json_validation_errors() {
+    let mut errors = validator::ValidationErrors::new();
+    errors.add(
+        "slug",
+        validator::ValidationError::new("slug").with_message("must not be empty".into()),
+    );
+    errors.add("mail", validator::ValidationError::new("email"));
+    let response = ValidateRequestError::JsonValidationError(errors).into_response();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], "VALIDATION_ERROR");
+    assert_eq!(body["field"], "mail");
+    assert_eq!(body["errors"]["slug"], json!(["must not be empty"]));
+    assert_eq!(body["errors"]["mail"], json!(["email"]));
+}
+
+#[test]
+fn test_insert_error() {
+    #[derive(Debug)]
+    struct FakeDatabaseError(sqlx::error::ErrorKind);
+
+    impl std::fmt::Display for FakeDatabaseError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "fake database error")
+        }
+    }
+
+    impl std::error::Error for FakeDatabaseError {}
+
+    impl sqlx::error::DatabaseError for FakeDatabaseError {
+        fn message(&self) -> &str {
+            "fake database error"
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> sqlx::error::ErrorKind {
+            self.0
+        }
+    }
+
+    let db_err = |kind| {
+        DbErr::Exec(RuntimeErr::SqlxError(sqlx::Error::Database(Box::new(
+            FakeDatabaseError(kind),
+        ))))
+    };
+
+    let unique = db_err(sqlx::error::ErrorKind::UniqueViolation);
+    assert!(is_unique_violation(&unique));
+    assert!(matches!(
+        insert_error(unique, "slug", "create post failed"),
+        FieldError::Conflict(field) if field == "slug"
+    ));
+
+    let not_null = db_err(sqlx::error::ErrorKind::NotNullViolation);
+    assert!(!is_unique_violation(&not_null));
+    assert!(matches!(
+        insert_error(not_null, "slug", "create post failed"),
+        FieldError::DatabaseFailed(_)
+    ));
+    let closed = DbErr::Custom("connection closed".to_string());
+    assert!(matches!(
+        insert_error(closed, "slug", "create post failed"),
+        FieldError::DatabaseFailed(_)
+    ));
+}
@@ -1,15 +1,20 @@
 use axum::{
     async_trait,
     body::Body,
-    extract::{rejection::JsonRejection, FromRef, FromRequest, FromRequestParts, Query},
+    extract::{
+        rejection::JsonRejection, ConnectInfo, FromRef, FromRequest, FromRequestParts, Query,
+    },
     http::{request::Parts, Request},
     Json,
 };
 use serde::de::DeserializeOwned;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use validator::Validate;
 
 use super::errors::{AuthError, ValidateRequestError};
+use super::utils::get_client_ip;
 use crate::entity::user::Model as User;
 use crate::users::utils::get_user;
 use crate::AppState;
@@ -28,7 +33,9 @@ where
 
     async fn from_request(req: Request<Body>, state: &S) -> Result<Self, Self::Rejection> {
         let Json(value) = Json::<T>::from_request(req, state).await?;
-        value.validate()?;
+        value
+            .validate()
+            .map_err(ValidateRequestError::JsonValidationError)?;
         Ok(ValidatedJson(value))
     }
 }
@@ -185,3 +192,31 @@ where
         }
     }
 }
+
+/// Client address resolved with `TRUSTED_PROXIES`, None when the server has no connect info.
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl<S> FromRequestParts<Arc<S>> for ClientIp
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<S>,
+    ) -> Result<Self, Self::Rejection> {
+        let state = AppState::from_ref(state);
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        Ok(ClientIp(get_client_ip(
+            &parts.headers,
+            peer,
+            &state.trusted_proxies,
+        )))
+    }
+}
@@ -34,3 +34,49 @@ pub struct FieldCreate {
     pub int_value: Option<i32>,
     pub float_value: Option<f32>,
 }
+
+fn is_inverted(after: Option<u32>, before: Option<u32>) -> bool {
+    matches!((after, before), (Some(after), Some(before)) if after > before)
+}
+
+/// Inclusive bounds on the `created` and `modified` columns of a content listing.
+#[derive(Default, Clone, Copy)]
+pub struct TimeRange {
+    pub created_after: Option<u32>,
+    pub created_before: Option<u32>,
+    pub modified_after: Option<u32>,
+    pub modified_before: Option<u32>,
+}
+
+impl TimeRange {
+    /// The first lower bound that lies past its upper bound, if any.
+    pub fn invalid_field(&self) -> Option<&'static str> {
+        if is_inverted(self.created_after, self.created_before) {
+            return Some("created_after");
+        }
+        if is_inverted(self.modified_after, self.modified_before) {
+            return Some("modified_after");
+        }
+        None
+    }
+}
+
+#[test]
+fn test_time_range_invalid_field() {
+    let range = |created_after, created_before, modified_after, modified_before| TimeRange {
+        created_after,
+        created_before,
+        modified_after,
+        modified_before,
+    };
+    assert_eq!(range(None, None, None, None).invalid_field(), None);
+    assert_eq!(range(Some(1), Some(1), None, Some(5)).invalid_field(), None);
+    assert_eq!(
+        range(Some(2), Some(1), None, None).invalid_field(),
+        Some("created_after")
+    );
+    assert_eq!(
+        range(None, None, Some(9), Some(5)).invalid_field(),
+        Some("modified_after")
+    );
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-use crate::entity::{content, field::Model as Field, meta::Model as Meta};
+use super::utils::{decode_text, get_excerpt, EXCERPT_LENGTH};
+use crate::entity::{content, field::Model as Field, meta::Model as Meta, user};
 
 #[derive(Serialize, Deserialize)]
 pub struct ContentWithFields {
@@ -32,7 +33,7 @@ impl From<content::Model> for ContentWithFields {
             slug: content.slug,
             created: content.created,
             modified: content.modified,
-            text: content.text,
+            text: content.text.map(decode_text),
             order: content.order,
             author_id: content.author_id,
             template: content.template,
@@ -68,23 +69,64 @@ pub struct ContentWithMetasUsersFields {
     pub allow_ping: String,
     pub allow_feed: String,
     pub parent: u32,
+    pub excerpt: Option<String>,
+    pub protected: bool,
+    pub sticky: bool,
 
     pub screen_name: Option<String>,
     pub group: String,
     pub categories: Vec<Meta>,
     pub tags: Vec<Meta>,
     pub fields: Vec<Field>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<ContentAuthor>,
+}
+
+/// The public part of a content author, embedded with `include=author`.
+#[derive(Serialize, Deserialize)]
+pub struct ContentAuthor {
+    pub uid: u32,
+    pub screen_name: Option<String>,
+    pub url: Option<String>,
+}
+
+impl From<user::Model> for ContentAuthor {
+    fn from(user: user::Model) -> Self {
+        Self {
+            uid: user.uid,
+            screen_name: user.screen_name,
+            url: user.url,
+        }
+    }
+}
+
+pub const PROTECTED_PLACEHOLDER: &str = "This post is password protected.";
+
+impl ContentWithMetasUsersFields {
+    pub fn is_password_protected(&self) -> bool {
+        self.password.as_deref().is_some_and(|p| !p.is_empty())
+    }
+
+    pub fn hide_protected_content(&mut self) {
+        self.text = Some(PROTECTED_PLACEHOLDER.to_string());
+        self.excerpt = None;
+        self.password = None;
+        self.protected = true;
+    }
 }
 
 impl From<content::Model> for ContentWithMetasUsersFields {
     fn from(content: content::Model) -> Self {
+        let text = content.text.map(decode_text);
+        let excerpt = text.as_deref().map(|t| get_excerpt(t, EXCERPT_LENGTH));
+        let sticky = content.r#type == "post" && content.order != 0;
         Self {
             cid: content.cid,
             title: content.title,
             slug: content.slug,
             created: content.created,
             modified: content.modified,
-            text: content.text,
+            text,
             order: content.order,
             author_id: content.author_id,
             template: content.template,
@@ -96,11 +138,15 @@ impl From<content::Model> for ContentWithMetasUsersFields {
             allow_ping: content.allow_ping,
             allow_feed: content.allow_feed,
             parent: content.parent,
+            excerpt,
+            protected: false,
+            sticky,
             screen_name: None,
             group: "visitor".to_string(),
             categories: vec![],
             tags: vec![],
             fields: vec![],
+            author: None,
         }
     }
 }
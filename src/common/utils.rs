@@ -1,3 +1,19 @@
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Range;
+use std::str::FromStr;
+
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, TimeZone, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sea_orm::Order;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
 use super::forms::FieldCreate;
 use crate::common::errors::FieldError;
 
@@ -43,4 +59,697 @@ pub fn get_field_params(
         _ => return Err(FieldError::InvalidParams("type".to_string())),
     };
     Ok((field_type.to_string(), str_value, int_value, float_value))
-}
\ No newline at end of file
+}
+
+pub const EXCERPT_LENGTH: usize = 200;
+
+fn strip_markup(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => {
+                for c in chars.by_ref() {
+                    if c == '>' {
+                        break;
+                    }
+                }
+            }
+            ']' if chars.peek() == Some(&'(') => {
+                for c in chars.by_ref() {
+                    if c == ')' {
+                        break;
+                    }
+                }
+            }
+            '#' | '*' | '_' | '`' | '>' | '~' | '[' | ']' | '!' => {}
+            _ => plain.push(c),
+        }
+    }
+    plain.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub fn get_excerpt(text: &str, length: usize) -> String {
+    if let Some((excerpt, _)) = text.split_once("<!--more-->") {
+        return strip_markup(excerpt);
+    }
+
+    let plain = strip_markup(text);
+    if plain.chars().count() > length {
+        format!("{}...", plain.chars().take(length).collect::<String>())
+    } else {
+        plain
+    }
+}
+
+/// Prefix Typecho puts on content written in Markdown, other content is HTML.
+pub const MARKDOWN_MARKER: &str = "<!--markdown-->";
+pub const MORE_MARKER: &str = "<!--more-->";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedHtml {
+    pub content: String,
+    /// The part before `<!--more-->`, None when the text has no marker.
+    pub excerpt: Option<String>,
+}
+
+/// Allowed markup for rendered and stored HTML, see `SANITIZE_TAGS`.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlPolicy {
+    /// Allowed tags, None keeps the ammonia defaults.
+    pub tags: Option<HashSet<String>>,
+    /// Attributes allowed on every tag, None keeps the ammonia defaults.
+    pub attributes: Option<HashSet<String>>,
+    /// Groups whose submitted text is sanitized before it is stored.
+    pub write_groups: Vec<String>,
+}
+
+impl HtmlPolicy {
+    fn builder(&self) -> ammonia::Builder<'_> {
+        let mut builder = ammonia::Builder::default();
+        if let Some(ref tags) = self.tags {
+            let tags: HashSet<&str> = tags.iter().map(String::as_str).collect();
+            // ammonia refuses tags that are both allowed and dropped with their content
+            let dropped: Vec<&str> = builder
+                .clone_clean_content_tags()
+                .into_iter()
+                .filter(|t| tags.contains(t))
+                .collect();
+            builder.rm_clean_content_tags(&dropped);
+            builder.tags(tags);
+        }
+        if let Some(ref attributes) = self.attributes {
+            if attributes.contains("rel") {
+                builder.link_rel(None);
+            }
+            builder.generic_attributes(attributes.iter().map(String::as_str).collect());
+        }
+        builder
+    }
+
+    pub fn clean(&self, html: &str) -> String {
+        self.builder().clean(html).to_string()
+    }
+
+    /// Inline tags arrive one at a time, so unpaired tags are kept unpaired.
+    fn clean_inline(&self, html: &str) -> String {
+        let builder = self.builder();
+        let closing = html.trim().strip_prefix("</");
+        if let Some(name) = closing.and_then(|s| s.strip_suffix('>')) {
+            return match builder.clone_tags().contains(name.trim()) {
+                true => html.to_string(),
+                false => String::new(),
+            };
+        }
+        let cleaned = builder.clean(html).to_string();
+        match cleaned.rfind("</") {
+            Some(i) if !html.contains("</") => cleaned[..i].to_string(),
+            _ => cleaned,
+        }
+    }
+
+    /// Sanitizes text written by `group`, Markdown keeps its syntax and only the embedded
+    /// HTML is cleaned. Groups outside `write_groups` are trusted and returned unchanged.
+    pub fn sanitize_for_group(&self, group: &str, text: &str) -> String {
+        if !self.write_groups.iter().any(|g| g == group) {
+            return text.to_string();
+        }
+        let markdown = match text.strip_prefix(MARKDOWN_MARKER) {
+            Some(markdown) => markdown,
+            None => return self.clean(text),
+        };
+
+        let mut ranges: Vec<(Range<usize>, bool)> = vec![];
+        for (event, range) in pulldown_cmark::Parser::new(markdown).into_offset_iter() {
+            let inline = match event {
+                pulldown_cmark::Event::Html(_) => false,
+                pulldown_cmark::Event::InlineHtml(_) => true,
+                _ => continue,
+            };
+            match ranges.last_mut() {
+                // the lines of one HTML block are cleaned together
+                Some((last, false)) if !inline && last.end == range.start => last.end = range.end,
+                _ => ranges.push((range, inline)),
+            }
+        }
+
+        let mut res = markdown.to_string();
+        for (range, inline) in ranges.into_iter().rev() {
+            let html = &markdown[range.clone()];
+            let cleaned = match inline {
+                true => self.clean_inline(html),
+                false => self.clean(html),
+            };
+            res.replace_range(range, &cleaned);
+        }
+        format!("{MARKDOWN_MARKER}{res}")
+    }
+}
+
+fn render_html(policy: &HtmlPolicy, text: &str) -> String {
+    let html = match text.strip_prefix(MARKDOWN_MARKER) {
+        Some(markdown) => {
+            let options = pulldown_cmark::Options::ENABLE_TABLES
+                | pulldown_cmark::Options::ENABLE_STRIKETHROUGH
+                | pulldown_cmark::Options::ENABLE_FOOTNOTES;
+            let parser = pulldown_cmark::Parser::new_ext(markdown, options);
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(&mut html, parser);
+            html
+        }
+        None => text.to_string(),
+    };
+    policy.clean(&html)
+}
+
+/// Renders Markdown content to sanitized HTML, HTML content is only sanitized.
+pub fn render_content_html(policy: &HtmlPolicy, text: &str) -> RenderedHtml {
+    let excerpt = text
+        .split_once(MORE_MARKER)
+        .map(|(excerpt, _)| render_html(policy, excerpt));
+    RenderedHtml {
+        content: render_html(policy, &text.replacen(MORE_MARKER, "", 1)),
+        excerpt,
+    }
+}
+
+/// Prefix of content text stored as base64 encoded gzip, see `COMPRESS_TEXT`.
+pub const COMPRESSED_TEXT_MARKER: &str = "<!--gzip-->";
+
+pub fn encode_text(text: &str, compress: bool) -> String {
+    if !compress {
+        return text.to_string();
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    if encoder.write_all(text.as_bytes()).is_err() {
+        return text.to_string();
+    }
+    match encoder.finish() {
+        Ok(bytes) => format!("{COMPRESSED_TEXT_MARKER}{}", STANDARD.encode(bytes)),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// Rows written before compression was enabled have no marker and are returned as is.
+pub fn decode_text(text: String) -> String {
+    let encoded = match text.strip_prefix(COMPRESSED_TEXT_MARKER) {
+        Some(e) => e,
+        None => return text,
+    };
+    let bytes = match STANDARD.decode(encoded) {
+        Ok(b) => b,
+        Err(_) => return text,
+    };
+    let mut decoded = String::new();
+    match GzDecoder::new(&bytes[..]).read_to_string(&mut decoded) {
+        Ok(_) => decoded,
+        Err(_) => text,
+    }
+}
+
+/// Cursors only work on id orderings, returns whether the cursor walks ids ascending.
+pub fn get_cursor_ascending(order_by: &str, id: &str) -> Option<bool> {
+    match order_by.strip_prefix('-') {
+        Some(o) if o == id => Some(false),
+        None if order_by == id => Some(true),
+        _ => None,
+    }
+}
+
+/// Maps an `order_by` value such as `-cid` to an allowlisted column and its direction.
+pub fn get_order_by<C: Copy>(
+    order_by: &str,
+    allowed: &[(&str, C)],
+) -> Result<(C, Order), FieldError> {
+    let (key, order) = match order_by.strip_prefix('-') {
+        Some(key) => (key, Order::Desc),
+        None => (order_by, Order::Asc),
+    };
+    allowed
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, column)| (*column, order))
+        .ok_or(FieldError::InvalidParams("order_by".to_string()))
+}
+
+/// Page size of a list request, defaults to 10 and is clamped to `MAX_PAGE_SIZE`.
+pub fn get_page_size(page_size: Option<u64>, max_page_size: u64) -> u64 {
+    page_size.unwrap_or(10).min(max_page_size)
+}
+
+/// Builds the `links` object of a list response, page numbers are null past either end.
+pub fn get_page_links(page: u64, page_size: u64, all_count: u64) -> Value {
+    let total_pages = all_count.div_ceil(page_size.max(1));
+    let last = total_pages.max(1);
+    let prev = match page > 1 {
+        true => Some((page - 1).min(last)),
+        false => None,
+    };
+    let next = match page < total_pages {
+        true => Some(page + 1),
+        false => None,
+    };
+    serde_json::json!({
+        "first": 1,
+        "last": last,
+        "prev": prev,
+        "next": next,
+        "total_pages": total_pages,
+    })
+}
+
+/// Answers 304 when `If-None-Match` or `If-Modified-Since` still match, otherwise sends `value` with validators.
+pub fn get_conditional_response(headers: &HeaderMap, value: Value, modified: u32) -> Response {
+    let mut hasher = Sha256::new();
+    hasher.update(value.to_string().as_bytes());
+    hasher.update(modified.to_be_bytes());
+    let etag = format!("\"{:x}\"", hasher.finalize());
+    let last_modified = Utc
+        .timestamp_opt(modified as i64, 0)
+        .single()
+        .map(|d| d.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+        .unwrap_or_default();
+
+    // If-Modified-Since is ignored when If-None-Match is present
+    let not_modified = match headers.get(header::IF_NONE_MATCH) {
+        Some(v) => v.to_str().unwrap_or_default().split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        }),
+        None => headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+            .is_some_and(|since| since.timestamp() >= modified as i64),
+    };
+
+    let validators = [(header::ETAG, etag), (header::LAST_MODIFIED, last_modified)];
+    if not_modified {
+        (StatusCode::NOT_MODIFIED, validators).into_response()
+    } else {
+        (validators, Json(value)).into_response()
+    }
+}
+
+/// Whether an update was based on an outdated copy, either `If-Unmodified-Since` or the
+/// expected `modified` is older than the stored one.
+pub fn is_stale_update(headers: &HeaderMap, expected: Option<u64>, modified: u32) -> bool {
+    let since = headers
+        .get(header::IF_UNMODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+        .is_some_and(|since| since.timestamp() < modified as i64);
+    since || expected.is_some_and(|expected| expected < modified as u64)
+}
+
+/// Lowercases a candidate slug and collapses every other character run into a single hyphen.
+pub fn normalize_slug(slug: &str) -> String {
+    let mut res = String::new();
+    for c in slug.trim().chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            res.push(c);
+        } else if !res.is_empty() && !res.ends_with('-') {
+            res.push('-');
+        }
+    }
+    res.trim_end_matches('-').to_string()
+}
+
+/// Longest generated slug, matching the `slug` length limit of the forms.
+pub const SLUG_MAX_LENGTH: usize = 150;
+
+/// How non-ASCII characters are handled when generating a slug, see `SLUG_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlugMode {
+    /// Transliterate to ASCII, `Café` becomes `cafe` and CJK becomes pinyin.
+    Transliterate,
+    /// Keep lowercase Unicode letters and digits, browsers percent-encode them in URLs.
+    Keep,
+    /// Use a short hash whenever the input contains non-ASCII characters.
+    Hash,
+}
+
+impl FromStr for SlugMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transliterate" => Ok(SlugMode::Transliterate),
+            "keep" => Ok(SlugMode::Keep),
+            "hash" => Ok(SlugMode::Hash),
+            _ => Err(()),
+        }
+    }
+}
+
+fn get_slug_hash(text: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(text.trim().as_bytes()));
+    digest[..8].to_string()
+}
+
+/// Builds a slug from a title, falling back to a short hash when non-ASCII input leaves nothing.
+pub fn generate_slug(text: &str, mode: SlugMode) -> String {
+    let slug = match mode {
+        SlugMode::Transliterate => normalize_slug(&deunicode::deunicode(text)),
+        SlugMode::Hash if !text.is_ascii() => String::new(),
+        SlugMode::Hash => normalize_slug(text),
+        SlugMode::Keep => {
+            let mut res = String::new();
+            for c in text.trim().chars().flat_map(|c| c.to_lowercase()) {
+                if c.is_alphanumeric() {
+                    res.push(c);
+                } else if !res.is_empty() && !res.ends_with('-') {
+                    res.push('-');
+                }
+            }
+            res.trim_end_matches('-').to_string()
+        }
+    };
+    if slug.is_empty() {
+        return match text.is_ascii() {
+            true => slug,
+            false => get_slug_hash(text),
+        };
+    }
+    let slug: String = slug.chars().take(SLUG_MAX_LENGTH).collect();
+    slug.trim_end_matches('-').to_string()
+}
+
+fn parse_forwarded_ip(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim();
+    hop.parse::<IpAddr>()
+        .ok()
+        .or_else(|| hop.parse::<SocketAddr>().ok().map(|a| a.ip()))
+}
+
+/// Resolves the client address. `X-Forwarded-For` is only read when the peer is a trusted
+/// proxy, and is walked from the right so clients can not spoof entries past the last proxy.
+/// A missing peer (no connect info) counts as trusted once any proxy is configured.
+pub fn get_client_ip(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    if trusted_proxies.is_empty() || peer.is_some_and(|p| !trusted_proxies.contains(&p)) {
+        return peer;
+    }
+
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .collect();
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        match parse_forwarded_ip(hop) {
+            Some(ip) => {
+                client = Some(ip);
+                if !trusted_proxies.contains(&ip) {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    client
+}
+
+/// Parses a comma separated `fields` value, rejecting keys outside the allowlist.
+pub fn get_fields(fields: &str, allowed: &[&str]) -> Result<Vec<String>, FieldError> {
+    fields
+        .split(',')
+        .map(|f| f.trim())
+        .map(|f| match allowed.contains(&f) {
+            true => Ok(f.to_string()),
+            false => Err(FieldError::InvalidParams("fields".to_string())),
+        })
+        .collect()
+}
+
+/// Keeps only the given keys of a JSON object, other values are returned unchanged.
+pub fn select_fields(value: Value, fields: &[String]) -> Value {
+    match value {
+        Value::Object(mut map) => {
+            map.retain(|k, _| fields.contains(k));
+            Value::Object(map)
+        }
+        _ => value,
+    }
+}
+
+#[test]
+fn test_get_excerpt() {
+    assert_eq!(
+        get_excerpt("<!--markdown-->## Title\n\nSome **bold** [link](https://a.b)", 200),
+        "Title Some bold link"
+    );
+    assert_eq!(
+        get_excerpt("<!--markdown-->intro text<!--more-->hidden text", 200),
+        "intro text"
+    );
+    assert_eq!(get_excerpt("abcdef", 3), "abc...");
+}
+
+#[test]
+fn test_encode_decode_text() {
+    let text = "<!--markdown-->## Title\n\nSome **bold** text";
+
+    let plain = encode_text(text, false);
+    assert_eq!(plain, text);
+    assert_eq!(decode_text(plain), text);
+
+    let compressed = encode_text(text, true);
+    assert!(compressed.starts_with(COMPRESSED_TEXT_MARKER));
+    assert_eq!(decode_text(compressed), text);
+
+    let broken = format!("{COMPRESSED_TEXT_MARKER}not base64");
+    assert_eq!(decode_text(broken.clone()), broken);
+}
+
+#[test]
+fn test_get_cursor_ascending() {
+    assert_eq!(get_cursor_ascending("cid", "cid"), Some(true));
+    assert_eq!(get_cursor_ascending("-cid", "cid"), Some(false));
+    assert_eq!(get_cursor_ascending("-slug", "cid"), None);
+    assert_eq!(get_cursor_ascending("uid", "cid"), None);
+}
+
+#[test]
+fn test_get_order_by() {
+    let allowed = [("cid", 1), ("slug", 2), ("created", 3)];
+
+    for (key, column) in allowed {
+        let (c, order) = get_order_by(key, &allowed).unwrap();
+        assert_eq!(c, column);
+        assert!(matches!(order, Order::Asc));
+
+        let (c, order) = get_order_by(&format!("-{key}"), &allowed).unwrap();
+        assert_eq!(c, column);
+        assert!(matches!(order, Order::Desc));
+    }
+
+    assert!(get_order_by("title", &allowed).is_err());
+    assert!(get_order_by("--cid", &allowed).is_err());
+    assert!(get_order_by("cid; DROP TABLE typecho_contents", &allowed).is_err());
+}
+
+#[test]
+fn test_get_fields_and_select_fields() {
+    let allowed = ["uid", "screen_name", "url", "mail"];
+
+    let fields = get_fields("uid, screen_name,url", &allowed).unwrap();
+    assert_eq!(fields, vec!["uid", "screen_name", "url"]);
+    assert!(get_fields("uid,password", &allowed).is_err());
+    assert!(get_fields("", &allowed).is_err());
+
+    let value = serde_json::json!({"uid": 1, "screen_name": "admin", "mail": "a@b.c"});
+    assert_eq!(
+        select_fields(value, &fields),
+        serde_json::json!({"uid": 1, "screen_name": "admin"})
+    );
+}
+
+#[test]
+fn test_normalize_slug() {
+    assert_eq!(normalize_slug("foo"), "foo");
+    assert_eq!(normalize_slug("  Hello World  "), "hello-world");
+    assert_eq!(normalize_slug("Rust_2024 -- Notes!"), "rust-2024-notes");
+    assert_eq!(normalize_slug("--a--b--"), "a-b");
+    assert_eq!(normalize_slug("!!!"), "");
+}
+
+#[test]
+fn test_generate_slug() {
+    let mode = SlugMode::Transliterate;
+    assert_eq!(generate_slug("Café déjà vu", mode), "cafe-deja-vu");
+    assert_eq!(generate_slug("你好世界", mode), "ni-hao-shi-jie");
+    assert_eq!(generate_slug("Привет мир", mode), "privet-mir");
+    assert_eq!(generate_slug("  ", mode), "");
+
+    let mode = SlugMode::Keep;
+    assert_eq!(generate_slug("Café déjà vu", mode), "café-déjà-vu");
+    assert_eq!(generate_slug("你好 世界", mode), "你好-世界");
+
+    let hash = generate_slug("你好世界", SlugMode::Hash);
+    assert_eq!(hash.len(), 8);
+    assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(hash, generate_slug(" 你好世界 ", SlugMode::Hash));
+    assert_eq!(generate_slug("Hello World", SlugMode::Hash), "hello-world");
+    assert_eq!(generate_slug("!!!", SlugMode::Hash), "");
+    assert_eq!(generate_slug("🎉", SlugMode::Keep).len(), 8);
+
+    let long = generate_slug(&"a ".repeat(200), SlugMode::Transliterate);
+    assert_eq!(long.len(), SLUG_MAX_LENGTH - 1);
+    assert!("transliterate".parse::<SlugMode>().is_ok());
+    assert!("pinyin".parse::<SlugMode>().is_err());
+}
+
+#[test]
+fn test_is_stale_update() {
+    let empty = HeaderMap::new();
+    assert!(!is_stale_update(&empty, None, 1666666666));
+    assert!(!is_stale_update(&empty, Some(1666666666), 1666666666));
+    assert!(is_stale_update(&empty, Some(1666666665), 1666666666));
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::IF_UNMODIFIED_SINCE,
+        "Tue, 25 Oct 2022 02:57:46 GMT".parse().unwrap(),
+    );
+    assert!(!is_stale_update(&headers, None, 1666666666));
+    assert!(is_stale_update(&headers, None, 1666666667));
+}
+
+#[test]
+fn test_get_conditional_response() {
+    let value = serde_json::json!({"cid": 1});
+    let response = get_conditional_response(&HeaderMap::new(), value.clone(), 1666666666);
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()[header::ETAG].clone();
+    let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+    assert_eq!(last_modified, "Tue, 25 Oct 2022 02:57:46 GMT");
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_NONE_MATCH, etag.clone());
+    let response = get_conditional_response(&headers, value.clone(), 1666666666);
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let response = get_conditional_response(&headers, value.clone(), 1666666667);
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_MODIFIED_SINCE, last_modified);
+    let response = get_conditional_response(&headers, value.clone(), 1666666666);
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let response = get_conditional_response(&headers, value, 1666666667);
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn test_get_page_size() {
+    assert_eq!(get_page_size(None, 100), 10);
+    assert_eq!(get_page_size(Some(50), 100), 50);
+    assert_eq!(get_page_size(Some(1000000), 100), 100);
+    assert_eq!(get_page_size(None, 5), 5);
+}
+
+#[test]
+fn test_get_page_links() {
+    let links = get_page_links(1, 10, 25);
+    assert_eq!(links["total_pages"], 3);
+    assert_eq!(links["last"], 3);
+    assert!(links["prev"].is_null());
+    assert_eq!(links["next"], 2);
+
+    let links = get_page_links(3, 10, 25);
+    assert_eq!(links["prev"], 2);
+    assert!(links["next"].is_null());
+
+    let links = get_page_links(1, 10, 0);
+    assert_eq!(links["total_pages"], 0);
+    assert_eq!(links["last"], 1);
+    assert!(links["next"].is_null());
+
+    let links = get_page_links(9, 10, 25);
+    assert_eq!(links["prev"], 3);
+    assert!(links["next"].is_null());
+}
+
+#[test]
+fn test_get_client_ip() {
+    let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+    let mut headers = HeaderMap::new();
+    let forwarded = "6.6.6.6, 1.1.1.1, 2.2.2.2";
+    headers.insert("x-forwarded-for", forwarded.parse().unwrap());
+    let proxy = ip("10.0.0.1");
+
+    assert_eq!(get_client_ip(&headers, Some(proxy), &[]), Some(proxy));
+    assert_eq!(get_client_ip(&headers, None, &[]), None);
+    assert_eq!(
+        get_client_ip(&headers, Some(ip("3.3.3.3")), &[proxy]),
+        Some(ip("3.3.3.3"))
+    );
+    assert_eq!(
+        get_client_ip(&headers, Some(proxy), &[proxy]),
+        Some(ip("2.2.2.2"))
+    );
+    assert_eq!(
+        get_client_ip(&headers, Some(proxy), &[proxy, ip("2.2.2.2")]),
+        Some(ip("1.1.1.1"))
+    );
+    assert_eq!(
+        get_client_ip(&headers, None, &[ip("2.2.2.2")]),
+        Some(ip("1.1.1.1"))
+    );
+
+    headers.insert("x-forwarded-for", "garbage, 1.1.1.1:8080".parse().unwrap());
+    assert_eq!(
+        get_client_ip(&headers, Some(proxy), &[proxy, ip("1.1.1.1")]),
+        Some(ip("1.1.1.1"))
+    );
+}
+
+#[test]
+fn test_render_content_html() {
+    let policy = HtmlPolicy::default();
+    let rendered = render_content_html(&policy, "<!--markdown-->## Title\n\nSome **bold** text");
+    let html = "<h2>Title</h2>\n<p>Some <strong>bold</strong> text</p>\n";
+    assert_eq!(rendered.content, html);
+    assert_eq!(rendered.excerpt, None);
+
+    let text = "<!--markdown-->intro *text*\n\n<!--more-->\n\nfull text";
+    let rendered = render_content_html(&policy, text);
+    assert_eq!(rendered.excerpt.unwrap(), "<p>intro <em>text</em></p>\n");
+    assert!(rendered.content.contains("<p>full text</p>"));
+
+    let text = "<p onclick=\"x()\">hi</p><script>alert(1)</script>";
+    let rendered = render_content_html(&policy, text);
+    assert_eq!(rendered.content, "<p>hi</p>");
+}
+
+#[test]
+fn test_sanitize_for_group() {
+    let policy = HtmlPolicy {
+        write_groups: vec!["contributor".to_string()],
+        ..Default::default()
+    };
+    let html = "<p onclick=\"x()\">hi</p><script>alert(1)</script>";
+    assert_eq!(policy.sanitize_for_group("contributor", html), "<p>hi</p>");
+    assert_eq!(policy.sanitize_for_group("administrator", html), html);
+
+    let markdown = "<!--markdown-->> quote & `a<b`\n\n<script>\nalert(1)\n</script>\n\nsome <b onclick=\"x()\">bold</b> text";
+    assert_eq!(
+        policy.sanitize_for_group("contributor", markdown),
+        "<!--markdown-->> quote & `a<b`\n\n\nsome <b>bold</b> text"
+    );
+
+    let tags = ["p", "script"].iter().map(|t| t.to_string()).collect();
+    let policy = HtmlPolicy {
+        tags: Some(tags),
+        write_groups: vec!["contributor".to_string()],
+        ..Default::default()
+    };
+    let script = "<script>alert(1)</script>";
+    assert_eq!(policy.clean(script), script);
+    assert_eq!(policy.clean("<b>bold</b>"), "bold");
+}
@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use tracing::Level;
+
+use crate::common::utils::{HtmlPolicy, SlugMode};
+
+/// Shortest accepted `SECRET_KEY`, HMAC-SHA256 keys below the output size are weak.
+pub const SECRET_KEY_MIN_LENGTH: usize = 32;
+
+/// Settings read once at startup, from the environment first and then `CONFIG_FILE`.
+pub struct Config {
+    pub database_url: String,
+    pub secret_key: String,
+    pub access_token_expire_secondes: u64,
+    pub refresh_token_expire_secondes: u64,
+    pub password_reset_expire_secondes: u64,
+    pub argon2_memory_cost: u32,
+    pub argon2_time_cost: u32,
+    pub argon2_parallelism: u32,
+    pub login_attempts_limit: u32,
+    pub login_attempts_window: u64,
+    pub comment_rate_limit: u32,
+    pub comment_rate_limit_user: u32,
+    pub preload_index: bool,
+    pub gravatar_default: String,
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allow_credentials: bool,
+    pub trusted_proxies: Vec<IpAddr>,
+    pub upload_root: String,
+    pub keep_upload_filename: bool,
+    pub allowed_extensions: Vec<String>,
+    pub blocked_extensions: Vec<String>,
+    pub compress_text: bool,
+    pub slug_mode: SlugMode,
+    pub html_policy: HtmlPolicy,
+    pub site_url: String,
+    pub site_founded: u64,
+    pub max_page_size: u64,
+    pub max_storage_bytes_per_user: u64,
+    pub read_only: bool,
+    pub trailing_slash_redirect: bool,
+    pub log_level: Level,
+    pub shutdown_timeout: u64,
+}
+
+type Vars<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+fn parse<T: FromStr>(vars: Vars, key: &str, default: &str) -> Result<T, String> {
+    vars(key)
+        .unwrap_or(default.to_string())
+        .parse::<T>()
+        .map_err(|_| format!("{key} is invalid"))
+}
+
+fn flag(vars: Vars, key: &str) -> bool {
+    vars(key).is_some_and(|s| s == "true")
+}
+
+fn list(vars: Vars, key: &str, default: &str) -> Vec<String> {
+    vars(key)
+        .unwrap_or(default.to_string())
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// File extensions are compared lowercase and without the leading dot.
+fn extensions(vars: Vars, key: &str, default: &str) -> Vec<String> {
+    list(vars, key, default)
+        .into_iter()
+        .map(|s| s.trim_start_matches('.').to_lowercase())
+        .collect()
+}
+
+/// An unset or empty list keeps the built-in defaults.
+fn optional_set(vars: Vars, key: &str) -> Option<HashSet<String>> {
+    let values = list(vars, key, "");
+    match values.is_empty() {
+        true => None,
+        false => Some(values.into_iter().map(|s| s.to_lowercase()).collect()),
+    }
+}
+
+/// Reads `KEY=VALUE` lines, blank lines and lines starting with `#` are skipped.
+fn read_config_file(path: &str) -> Result<HashMap<String, String>, String> {
+    let content = fs::read_to_string(path).map_err(|_| format!("CONFIG_FILE {path} is invalid"))?;
+    let mut vars = HashMap::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some(kv) => kv,
+            None => return Err(format!("CONFIG_FILE line {line} is invalid")),
+        };
+        let value = value.trim().trim_matches('"');
+        vars.insert(key.trim().to_string(), value.to_string());
+    }
+    Ok(vars)
+}
+
+fn is_writable_dir(path: &str) -> bool {
+    let dir = Path::new(path);
+    if !dir.is_dir() {
+        return false;
+    }
+    let probe = dir.join(format!(".rumo-write-check-{}", std::process::id()));
+    match fs::write(&probe, b"") {
+        Ok(_) => fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, String> {
+        let file_vars = match env::var("CONFIG_FILE") {
+            Ok(path) => read_config_file(&path)?,
+            Err(_) => HashMap::new(),
+        };
+        let config =
+            Self::from_vars(&|key| env::var(key).ok().or_else(|| file_vars.get(key).cloned()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_vars(vars: Vars) -> Result<Self, String> {
+        Ok(Self {
+            database_url: vars("DATABASE_URL").ok_or("DATABASE_URL is required")?,
+            secret_key: vars("SECRET_KEY").ok_or("SECRET_KEY is required")?,
+            access_token_expire_secondes: parse(vars, "TOKEN_EXPIRE", "720")?,
+            refresh_token_expire_secondes: parse(vars, "REFRESH_TOKEN_EXPIRE", "2592000")?,
+            password_reset_expire_secondes: parse(vars, "PASSWORD_RESET_EXPIRE", "1800")?,
+            argon2_memory_cost: parse(vars, "ARGON2_MEMORY_COST", "19456")?,
+            argon2_time_cost: parse(vars, "ARGON2_TIME_COST", "2")?,
+            argon2_parallelism: parse(vars, "ARGON2_PARALLELISM", "1")?,
+            login_attempts_limit: parse(vars, "LOGIN_ATTEMPTS_LIMIT", "5")?,
+            login_attempts_window: parse(vars, "LOGIN_ATTEMPTS_WINDOW", "300")?,
+            comment_rate_limit: parse(vars, "COMMENT_RATE_LIMIT", "5")?,
+            comment_rate_limit_user: parse(vars, "COMMENT_RATE_LIMIT_USER", "20")?,
+            preload_index: flag(vars, "PRELOAD_INDEX"),
+            gravatar_default: vars("GRAVATAR_DEFAULT").unwrap_or(String::from("mp")),
+            allowed_origins: list(vars, "CORS_ALLOWED_ORIGINS", ""),
+            allowed_methods: list(vars, "CORS_ALLOWED_METHODS", "GET,POST,PATCH,DELETE")
+                .into_iter()
+                .map(|s| s.to_uppercase())
+                .collect(),
+            allow_credentials: flag(vars, "CORS_ALLOW_CREDENTIALS"),
+            trusted_proxies: list(vars, "TRUSTED_PROXIES", "")
+                .iter()
+                .map(|s| s.parse::<IpAddr>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| "TRUSTED_PROXIES is invalid".to_string())?,
+            upload_root: vars("UPLOAD_ROOT").unwrap_or(String::from(".")),
+            keep_upload_filename: flag(vars, "KEEP_UPLOAD_FILENAME"),
+            allowed_extensions: extensions(vars, "ALLOWED_EXTENSIONS", ""),
+            blocked_extensions: extensions(vars, "BLOCKED_EXTENSIONS", "php,phtml,phar,exe"),
+            compress_text: flag(vars, "COMPRESS_TEXT"),
+            slug_mode: parse(vars, "SLUG_MODE", "transliterate")?,
+            html_policy: HtmlPolicy {
+                tags: optional_set(vars, "SANITIZE_TAGS"),
+                attributes: optional_set(vars, "SANITIZE_ATTRIBUTES"),
+                write_groups: list(vars, "SANITIZE_GROUPS", "contributor"),
+            },
+            site_url: vars("SITE_URL")
+                .unwrap_or_default()
+                .trim_end_matches('/')
+                .to_string(),
+            site_founded: parse(vars, "SITE_FOUNDED", "946684800")?,
+            max_page_size: parse(vars, "MAX_PAGE_SIZE", "100")?,
+            max_storage_bytes_per_user: parse(vars, "MAX_STORAGE_BYTES_PER_USER", "0")?,
+            read_only: flag(vars, "READ_ONLY"),
+            trailing_slash_redirect: flag(vars, "TRAILING_SLASH_REDIRECT"),
+            log_level: parse(vars, "LOG_LEVEL", "info")?,
+            shutdown_timeout: parse(vars, "SHUTDOWN_TIMEOUT", "30")?,
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.secret_key.len() < SECRET_KEY_MIN_LENGTH {
+            return Err(format!(
+                "SECRET_KEY must be at least {SECRET_KEY_MIN_LENGTH} bytes"
+            ));
+        }
+        for (key, value) in [
+            ("TOKEN_EXPIRE", self.access_token_expire_secondes),
+            ("REFRESH_TOKEN_EXPIRE", self.refresh_token_expire_secondes),
+            ("PASSWORD_RESET_EXPIRE", self.password_reset_expire_secondes),
+            ("MAX_PAGE_SIZE", self.max_page_size),
+        ] {
+            if value == 0 {
+                return Err(format!("{key} must be greater than 0"));
+            }
+        }
+        if let Err(e) = argon2::Params::new(
+            self.argon2_memory_cost,
+            self.argon2_time_cost,
+            self.argon2_parallelism,
+            None,
+        ) {
+            return Err(format!(
+                "ARGON2_MEMORY_COST, ARGON2_TIME_COST and ARGON2_PARALLELISM are invalid: {e}"
+            ));
+        }
+        if !self.read_only && !is_writable_dir(&self.upload_root) {
+            return Err(format!(
+                "UPLOAD_ROOT {} must be a writable directory",
+                self.upload_root
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_config_from_vars() {
+    let vars = |pairs: Vec<(&'static str, &'static str)>| {
+        let map: HashMap<&str, &str> = pairs.into_iter().collect();
+        move |key: &str| map.get(key).map(|v| v.to_string())
+    };
+    let secret = "0123456789abcdef0123456789abcdef";
+
+    let config = Config::from_vars(&vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("CORS_ALLOWED_METHODS", "get, post"),
+    ]))
+    .unwrap();
+    assert_eq!(config.access_token_expire_secondes, 720);
+    assert_eq!(config.allowed_methods, vec!["GET", "POST"]);
+    assert_eq!(config.slug_mode, SlugMode::Transliterate);
+    assert_eq!(config.max_page_size, 100);
+    assert_eq!(config.max_storage_bytes_per_user, 0);
+    assert!(config.trusted_proxies.is_empty());
+    assert!(config.allowed_extensions.is_empty());
+    assert_eq!(config.blocked_extensions, ["php", "phtml", "phar", "exe"]);
+    assert!(config.html_policy.tags.is_none());
+    assert_eq!(config.html_policy.write_groups, vec!["contributor"]);
+    assert_eq!(config.log_level, Level::INFO);
+    assert_eq!(config.shutdown_timeout, 30);
+    assert!(config.validate().is_ok());
+
+    assert!(Config::from_vars(&vars(vec![("SECRET_KEY", secret)])).is_err());
+    let invalid = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("TOKEN_EXPIRE", "soon"),
+    ]);
+    assert!(Config::from_vars(&invalid).is_err());
+    let invalid = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("LOG_LEVEL", "loud"),
+    ]);
+    assert!(Config::from_vars(&invalid).is_err());
+    let proxies = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("TRUSTED_PROXIES", "127.0.0.1, ::1"),
+    ]);
+    let config = Config::from_vars(&proxies).unwrap();
+    assert_eq!(config.trusted_proxies.len(), 2);
+    let proxies = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("TRUSTED_PROXIES", "10.0.0.0/8"),
+    ]);
+    assert!(Config::from_vars(&proxies).is_err());
+
+    let short = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", "short"),
+    ]);
+    assert!(Config::from_vars(&short).unwrap().validate().is_err());
+    let zero = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("TOKEN_EXPIRE", "0"),
+    ]);
+    assert!(Config::from_vars(&zero).unwrap().validate().is_err());
+    let weak_argon2 = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("ARGON2_MEMORY_COST", "1"),
+        ("ARGON2_PARALLELISM", "4"),
+    ]);
+    assert!(Config::from_vars(&weak_argon2).unwrap().validate().is_err());
+    let missing_root = vars(vec![
+        ("DATABASE_URL", "sqlite::memory:"),
+        ("SECRET_KEY", secret),
+        ("UPLOAD_ROOT", "/nonexistent/rumo/uploads"),
+    ]);
+    let config = Config::from_vars(&missing_root).unwrap();
+    assert!(config.validate().is_err());
+}
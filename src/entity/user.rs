@@ -9,6 +9,7 @@ pub struct Model {
     pub uid: u32,
     #[sea_orm(unique)]
     pub name: Option<String>,
+    #[serde(skip_serializing)]
     pub password: Option<String>,
     #[sea_orm(unique)]
     pub mail: Option<String>,
@@ -20,6 +21,7 @@ pub struct Model {
     pub logged: u32,
     pub group: String,
     #[sea_orm(column_name = "authCode")]
+    #[serde(skip_serializing)]
     pub auth_code: Option<String>,
 }
 
@@ -0,0 +1,105 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::{TimeZone, Utc};
+use minijinja::context;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::allow::method_not_allowed;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::models::PROTECTED_PLACEHOLDER;
+use crate::common::utils::{decode_text, get_excerpt, EXCERPT_LENGTH};
+use crate::posts::db as post_db;
+use crate::users::db as user_db;
+use crate::AppState;
+
+pub const FEED_SIZE: u64 = 20;
+
+const FEED_TPL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<channel>
+<title>{{ title }}</title>
+<link>{{ link }}</link>
+<description>{{ description }}</description>
+{%- for item in items %}
+<item>
+<title>{{ item.title }}</title>
+<link>{{ item.link }}</link>
+<guid isPermaLink="true">{{ item.link }}</guid>
+<pubDate>{{ item.pub_date }}</pubDate>
+<dc:creator>{{ item.author }}</dc:creator>
+<description>{{ item.description }}</description>
+</item>
+{%- endfor %}
+</channel>
+</rss>
+"#;
+
+#[derive(Serialize)]
+struct FeedItem {
+    title: String,
+    link: String,
+    pub_date: String,
+    author: String,
+    description: String,
+}
+
+pub fn feed_routers() -> Router<Arc<AppState>> {
+    Router::new().route("/api/feed.xml", get(feed).fallback(method_not_allowed))
+}
+
+pub async fn feed(State(state): State<Arc<AppState>>) -> Result<Response, FieldError> {
+    let mut options = HashMap::new();
+    for option in user_db::get_options_by_uid(&state, 0).await? {
+        options.insert(option.name, option.value.unwrap_or_default());
+    }
+    let site_url = common_db::get_site_url(&state).await?;
+
+    let posts = post_db::get_feed_posts(&state, FEED_SIZE).await?;
+    let mut items = vec![];
+    for (post, author) in posts {
+        let slug = post.slug.unwrap_or_default();
+        let protected = post.password.as_deref().is_some_and(|p| !p.is_empty());
+        let description = match protected {
+            true => PROTECTED_PLACEHOLDER.to_string(),
+            false => get_excerpt(&decode_text(post.text.unwrap_or_default()), EXCERPT_LENGTH),
+        };
+        let pub_date = Utc
+            .timestamp_opt(post.created as i64, 0)
+            .single()
+            .map(|d| d.to_rfc2822())
+            .unwrap_or_default();
+
+        items.push(FeedItem {
+            title: post.title.unwrap_or_default(),
+            link: format!("{site_url}/posts/{slug}"),
+            pub_date,
+            author: author.and_then(|a| a.screen_name).unwrap_or_default(),
+            description,
+        });
+    }
+
+    let template = state
+        .jinja_env
+        .template_from_named_str("feed.xml", FEED_TPL)
+        .map_err(|e| FieldError::DatabaseFailed(e.to_string()))?;
+    let output = template
+        .render(context! {
+            title => options.get("title"),
+            link => site_url,
+            description => options.get("description"),
+            items => items,
+        })
+        .map_err(|e| FieldError::DatabaseFailed(e.to_string()))?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        output,
+    )
+        .into_response())
+}
@@ -0,0 +1,107 @@
+use axum::body::{Body, HttpBody};
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::{json, Map, Value};
+
+pub const JSON_API_MEDIA_TYPE: &str = "application/vnd.api+json";
+
+fn wants_json_api(request: &Request) -> bool {
+    let by_query = request
+        .uri()
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .any(|pair| pair == "format=jsonapi");
+    let by_accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains(JSON_API_MEDIA_TYPE));
+    by_query || by_accept
+}
+
+/// Rewrites a bespoke response body into the JSON:API `{ data, meta, links }` shape. List
+/// bodies move `results` to `data` and their counters to `meta`, errors go to `errors`.
+pub fn to_json_api(success: bool, body: Value) -> Value {
+    if !success {
+        return json!({ "errors": [body] });
+    }
+
+    match body {
+        Value::Object(mut object) if object.contains_key("results") => {
+            let data = object.remove("results").unwrap_or_default();
+            let links = object.remove("links");
+            let mut document = Map::new();
+            document.insert("data".to_string(), data);
+            document.insert("meta".to_string(), Value::Object(object));
+            if let Some(links) = links {
+                document.insert("links".to_string(), links);
+            }
+            Value::Object(document)
+        }
+        body => json!({ "data": body }),
+    }
+}
+
+/// Applies the response format selected by `?format=jsonapi` or an `Accept` header naming
+/// the JSON:API media type. Streamed and non-JSON responses are passed through untouched.
+pub async fn negotiate_format(request: Request, next: Next) -> Response {
+    let json_api = wants_json_api(&request);
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+    if !json_api {
+        return response;
+    }
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type == "application/json");
+    if !is_json || response.body().size_hint().exact().is_none() {
+        return response;
+    }
+
+    let success = response.status().is_success();
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+    let body = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(body) => body,
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    let document = to_json_api(success, body).to_string();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.remove(header::ETAG);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(JSON_API_MEDIA_TYPE),
+    );
+    Response::from_parts(parts, Body::from(document))
+}
+
+#[test]
+fn test_to_json_api() {
+    let list = json!({
+        "page": 1,
+        "all_count": 2,
+        "results": [{"cid": 1}, {"cid": 2}],
+        "links": {"next": null},
+    });
+    let document = to_json_api(true, list);
+    assert_eq!(document["data"], json!([{"cid": 1}, {"cid": 2}]));
+    assert_eq!(document["meta"], json!({"page": 1, "all_count": 2}));
+    assert_eq!(document["links"], json!({"next": null}));
+
+    let single = to_json_api(true, json!({"cid": 1}));
+    assert_eq!(single, json!({"data": {"cid": 1}}));
+
+    let error = to_json_api(false, json!({"msg": "not found"}));
+    assert_eq!(error, json!({"errors": [{"msg": "not found"}]}));
+}
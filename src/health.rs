@@ -0,0 +1,46 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Json;
+use axum::routing::get;
+use axum::Router;
+use sea_orm::{ConnectionTrait, DatabaseBackend, Statement};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::allow::method_not_allowed;
+use crate::AppState;
+
+pub fn health_routers() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/health", get(health).fallback(method_not_allowed))
+        .route("/api/ready", get(ready).fallback(method_not_allowed))
+}
+
+pub async fn health() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+pub async fn ready(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    let backend = state.conn.get_database_backend();
+    let backend_name = match backend {
+        DatabaseBackend::MySql => "mysql",
+        DatabaseBackend::Postgres => "postgres",
+        DatabaseBackend::Sqlite => "sqlite",
+    };
+
+    let stmt = Statement::from_string(backend, "SELECT 1");
+    match state.conn.execute(stmt).await {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({ "status": "ok", "backend": backend_name })),
+        ),
+        Err(e) => {
+            warn!("database not ready: {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "unavailable", "backend": backend_name })),
+            )
+        }
+    }
+}
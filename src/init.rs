@@ -2,7 +2,9 @@ use std::time::SystemTime;
 
 use sea_orm::*;
 
-use super::entity::{option, option::Entity as Option, user};
+use super::entity::{
+    comment, content, field, meta, option, option::Entity as Option, relationship, user,
+};
 use super::users::{forms::UserRegister, utils::hash};
 use super::AppState;
 
@@ -99,7 +101,7 @@ pub async fn init_table(state: &AppState) {
             CREATE TABLE "typecho_users" (
                 "uid" INT NOT NULL DEFAULT nextval('typecho_users_seq') ,
                 "name" VARCHAR(32) NULL DEFAULT NULL,
-                "password" VARCHAR(64) NULL DEFAULT NULL,
+                "password" VARCHAR(128) NULL DEFAULT NULL,
                 "mail" VARCHAR(150) NULL DEFAULT NULL,
                 "url" VARCHAR(150) NULL DEFAULT NULL,
                 "screenName" VARCHAR(32) NULL DEFAULT NULL,
@@ -156,7 +158,8 @@ pub async fn init_table(state: &AppState) {
                 `parent` int(10) unsigned default '0',
                 PRIMARY KEY  (`cid`),
                 UNIQUE KEY `slug` (`slug`),
-                KEY `created` (`created`)
+                KEY `created` (`created`),
+                FULLTEXT KEY `search` (`title`, `text`)
             ) ENGINE=InnoDB  DEFAULT CHARSET=utf8mb4;
           
             CREATE TABLE `typecho_fields` (
@@ -200,7 +203,7 @@ pub async fn init_table(state: &AppState) {
             CREATE TABLE `typecho_users` (
                 `uid` int(10) unsigned NOT NULL auto_increment,
                 `name` varchar(32) default NULL,
-                `password` varchar(64) default NULL,
+                `password` varchar(128) default NULL,
                 `mail` varchar(150) default NULL,
                 `url` varchar(150) default NULL,
                 `screenName` varchar(32) default NULL,
@@ -298,7 +301,7 @@ pub async fn init_table(state: &AppState) {
             CREATE TABLE typecho_users (
                 "uid" INTEGER NOT NULL PRIMARY KEY, 
                 "name" varchar(32) default NULL ,
-                "password" varchar(64) default NULL , 
+                "password" varchar(128) default NULL , 
                 "mail" varchar(150) default NULL ,
                 "url" varchar(150) default NULL ,
                 "screenName" varchar(32) default NULL , 
@@ -325,14 +328,46 @@ pub async fn init_table(state: &AppState) {
             .await
             .expect("database already exists");
     }
+
+    #[cfg(feature = "fts5")]
+    if db_backend == DatabaseBackend::Sqlite {
+        for stmt in SQLITE_FTS5_SQL {
+            let _ = state
+                .conn
+                .execute(Statement::from_string(db_backend, stmt.to_string()))
+                .await
+                .expect("create fts5 table failed");
+        }
+    }
 }
 
+#[cfg(feature = "fts5")]
+const SQLITE_FTS5_SQL: [&str; 4] = [
+    r#"CREATE VIRTUAL TABLE typecho_contents_fts USING fts5(
+        title, text, content='typecho_contents', content_rowid='cid'
+    );"#,
+    r#"CREATE TRIGGER typecho_contents_fts_insert AFTER INSERT ON typecho_contents BEGIN
+        INSERT INTO typecho_contents_fts(rowid, title, text) VALUES (new.cid, new.title, new.text);
+    END;"#,
+    r#"CREATE TRIGGER typecho_contents_fts_delete AFTER DELETE ON typecho_contents BEGIN
+        INSERT INTO typecho_contents_fts(typecho_contents_fts, rowid, title, text)
+            VALUES ('delete', old.cid, old.title, old.text);
+    END;"#,
+    r#"CREATE TRIGGER typecho_contents_fts_update AFTER UPDATE ON typecho_contents BEGIN
+        INSERT INTO typecho_contents_fts(typecho_contents_fts, rowid, title, text)
+            VALUES ('delete', old.cid, old.title, old.text);
+        INSERT INTO typecho_contents_fts(rowid, title, text) VALUES (new.cid, new.title, new.text);
+    END;"#,
+];
+
 pub async fn init_admin(state: &AppState, user_register: UserRegister) {
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_secs() as u32;
-    let hashed_password = hash(&user_register.password);
+    let hashed_password = hash(state, &user_register.password)
+        .await
+        .expect("hash password failed");
 
     user::ActiveModel {
         name: Set(Some(user_register.name.clone())),
@@ -433,3 +468,51 @@ pub async fn init_options(state: &AppState) {
             .expect("insert option failed");
     }
 }
+
+async fn check_entity_columns<E: EntityTrait>(
+    conn: &DatabaseConnection,
+    missing: &mut Vec<String>,
+) {
+    let db_backend = conn.get_database_backend();
+    let table = E::default().table_name().to_string();
+
+    let mut missing_columns = vec![];
+    let mut all_count = 0;
+    for column in E::Column::iter() {
+        all_count += 1;
+        let stmt = E::find()
+            .select_only()
+            .column(column)
+            .limit(1)
+            .build(db_backend);
+        if conn.query_one(stmt).await.is_err() {
+            missing_columns.push(format!("{}.{}", table, column.as_str()));
+        }
+    }
+
+    if missing_columns.len() == all_count {
+        missing.push(format!("{} (table)", table));
+    } else {
+        missing.append(&mut missing_columns);
+    }
+}
+
+pub async fn check_schema(conn: &DatabaseConnection) -> Result<(), String> {
+    let mut missing = vec![];
+    check_entity_columns::<comment::Entity>(conn, &mut missing).await;
+    check_entity_columns::<content::Entity>(conn, &mut missing).await;
+    check_entity_columns::<field::Entity>(conn, &mut missing).await;
+    check_entity_columns::<meta::Entity>(conn, &mut missing).await;
+    check_entity_columns::<option::Entity>(conn, &mut missing).await;
+    check_entity_columns::<relationship::Entity>(conn, &mut missing).await;
+    check_entity_columns::<user::Entity>(conn, &mut missing).await;
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "database schema mismatch, missing: {}",
+            missing.join(", ")
+        ))
+    }
+}
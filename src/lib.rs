@@ -1,35 +1,67 @@
+use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::sync::Arc;
+use std::net::IpAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-use axum::Router;
+use axum::http::{header, HeaderValue, Method};
+use axum::{middleware, Router};
+use dashmap::DashMap;
 use minijinja::Environment;
 use sea_orm::{Database, DatabaseConnection};
+use tokio::sync::broadcast;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{info, warn};
 
 #[macro_use]
 extern crate lazy_static;
 
+mod allow;
 mod attachments;
 mod categories;
 mod comments;
 mod common;
+mod config;
 mod entity;
+mod feed;
+mod format;
+mod health;
 mod init;
+mod logging;
+mod mailer;
 mod pages;
 mod posts;
 mod preload;
+mod sitemap;
+mod slash;
+mod spam;
+mod stats;
 mod tags;
 mod users;
 use attachments::attachments_routers;
 use categories::categories_routers;
-use comments::comments_routers;
+use comments::{comments_routers, CommentEvent, COMMENT_EVENTS_CAPACITY};
+use common::utils::RenderedHtml;
+use feed::feed_routers;
+use health::health_routers;
 use pages::pages_routers;
 use posts::posts_routers;
+use sitemap::sitemap_routers;
+use stats::stats_routers;
 use tags::tags_routers;
 use users::{forms::UserRegister, users_routers};
 
+pub use common::utils::{HtmlPolicy, SlugMode};
+pub use config::Config;
+pub use init::check_schema;
+pub use mailer::{Mailer, NoopMailer};
+pub use posts::db::publish_due_posts;
+pub use spam::{DefaultSpamChecker, SpamChecker, SpamVerdict};
+
 lazy_static! {
     pub static ref INDEX_TPL: String = {
         let preload_index = match env::var("PRELOAD_INDEX") {
@@ -60,9 +92,63 @@ pub struct AppState {
     pub conn: DatabaseConnection,
     pub secret_key: String,
     pub access_token_expire_secondes: u64,
+    pub refresh_token_expire_secondes: u64,
+    pub password_reset_expire_secondes: u64,
+    /// Argon2id memory cost in KiB, see `ARGON2_MEMORY_COST`.
+    pub argon2_memory_cost: u32,
+    /// Argon2id iterations, see `ARGON2_TIME_COST`.
+    pub argon2_time_cost: u32,
+    /// Argon2id lanes, see `ARGON2_PARALLELISM`.
+    pub argon2_parallelism: u32,
+    /// Passwords upgraded on login since startup, reported by `/api/stats`.
+    pub password_rehashes: Arc<AtomicU64>,
+    pub revoked_tokens: Arc<Mutex<HashMap<String, u64>>>,
+    pub login_attempts: Arc<DashMap<String, (u32, u64)>>,
+    pub login_attempts_limit: u32,
+    pub login_attempts_window: u64,
+    pub comment_attempts: Arc<DashMap<String, (u32, u64)>>,
+    pub comment_rate_limit: u32,
+    pub comment_rate_limit_user: u32,
+    pub mailer: Arc<dyn Mailer>,
+    pub gravatar_default: String,
+    pub spam_checker: Arc<dyn SpamChecker>,
+    /// New comments for `/api/comments/stream` subscribers.
+    pub comment_events: broadcast::Sender<CommentEvent>,
+    /// CORS origins, empty keeps the API same-origin, see `CORS_ALLOWED_ORIGINS`.
+    pub allowed_origins: Vec<String>,
+    /// CORS methods, see `CORS_ALLOWED_METHODS`.
+    pub allowed_methods: Vec<String>,
+    /// CORS credentials, see `CORS_ALLOW_CREDENTIALS`.
+    pub allow_credentials: bool,
+    /// Proxies allowed to set `X-Forwarded-For`, see `TRUSTED_PROXIES`.
+    pub trusted_proxies: Vec<IpAddr>,
     pub upload_root: String,
+    /// Keep the sanitized original filename on upload, see `KEEP_UPLOAD_FILENAME`.
+    pub keep_upload_filename: bool,
+    /// Upload extensions accepted, empty for any, see `ALLOWED_EXTENSIONS`.
+    pub allowed_extensions: Vec<String>,
+    /// Upload extensions always refused, see `BLOCKED_EXTENSIONS`.
+    pub blocked_extensions: Vec<String>,
+    /// Store post and page text gzip compressed, see `COMPRESS_TEXT`.
+    pub compress_text: bool,
+    /// How generated slugs treat non-ASCII characters, see `SLUG_MODE`.
+    pub slug_mode: SlugMode,
+    /// Allowed markup for rendered and contributor written HTML, see `SANITIZE_TAGS`.
+    pub html_policy: HtmlPolicy,
+    /// Public site address used in feed and sitemap links, see `SITE_URL`.
+    pub site_url: String,
+    /// Earliest accepted post `created` timestamp, see `SITE_FOUNDED`.
+    pub site_founded: u64,
+    /// Largest `page_size` served by list endpoints, see `MAX_PAGE_SIZE`.
+    pub max_page_size: u64,
+    /// Attachment bytes a contributor may store, 0 for no limit, see `MAX_STORAGE_BYTES_PER_USER`.
+    pub max_storage_bytes_per_user: u64,
     pub read_only: bool,
+    /// Redirect `/api/` paths missing a trailing slash, see `TRAILING_SLASH_REDIRECT`.
+    pub trailing_slash_redirect: bool,
     pub preload_index: bool,
+    /// Rendered post HTML by cid, reused while `modified` is unchanged.
+    pub rendered_html: Arc<DashMap<u32, (u32, RenderedHtml)>>,
     pub jinja_env: Environment<'static>,
 }
 
@@ -70,50 +156,54 @@ async fn get_state(app_state: Option<AppState>) -> AppState {
     let state = match app_state {
         Some(s) => s,
         None => {
-            let conn =
-                Database::connect(&env::var("DATABASE_URL").expect("DATABASE_URL is required"))
-                    .await
-                    .expect("Database connect failed");
-            let secret_key = env::var("SECRET_KEY").expect("SECRET_KEY is required");
-
-            let access_token_expire_secondes = env::var("TOKEN_EXPIRE")
-                .unwrap_or("720".to_string())
-                .parse::<u64>()
-                .expect("TOKEN_EXPIRE is invalid");
-
-            let preload_index = match env::var("PRELOAD_INDEX") {
-                Ok(s) => {
-                    if s == "true" {
-                        true
-                    } else {
-                        false
-                    }
-                }
-                _ => false,
-            };
+            let config = Config::from_env().unwrap_or_else(|e| panic!("{e}"));
+            let conn = Database::connect(&config.database_url)
+                .await
+                .expect("Database connect failed");
 
             let mut jinja_env = Environment::new();
             jinja_env.add_template("index.html", &INDEX_TPL).unwrap();
 
-            let upload_root = env::var("UPLOAD_ROOT").unwrap_or(String::from("."));
-            let read_only = match env::var("READ_ONLY") {
-                Ok(s) => {
-                    if s == "true" {
-                        true
-                    } else {
-                        false
-                    }
-                }
-                _ => false,
-            };
-
             let s = AppState {
                 conn,
-                secret_key,
-                access_token_expire_secondes,
-                upload_root,
-                read_only,
-                preload_index,
+                secret_key: config.secret_key,
+                access_token_expire_secondes: config.access_token_expire_secondes,
+                refresh_token_expire_secondes: config.refresh_token_expire_secondes,
+                password_reset_expire_secondes: config.password_reset_expire_secondes,
+                argon2_memory_cost: config.argon2_memory_cost,
+                argon2_time_cost: config.argon2_time_cost,
+                argon2_parallelism: config.argon2_parallelism,
+                password_rehashes: Arc::new(AtomicU64::new(0)),
+                revoked_tokens: Arc::new(Mutex::new(HashMap::new())),
+                login_attempts: Arc::new(DashMap::new()),
+                login_attempts_limit: config.login_attempts_limit,
+                login_attempts_window: config.login_attempts_window,
+                comment_attempts: Arc::new(DashMap::new()),
+                comment_rate_limit: config.comment_rate_limit,
+                comment_rate_limit_user: config.comment_rate_limit_user,
+                mailer: Arc::new(NoopMailer),
+                gravatar_default: config.gravatar_default,
+                spam_checker: Arc::new(DefaultSpamChecker::default()),
+                comment_events: broadcast::channel(COMMENT_EVENTS_CAPACITY).0,
+                allowed_origins: config.allowed_origins,
+                allowed_methods: config.allowed_methods,
+                allow_credentials: config.allow_credentials,
+                trusted_proxies: config.trusted_proxies,
+                upload_root: config.upload_root,
+                keep_upload_filename: config.keep_upload_filename,
+                allowed_extensions: config.allowed_extensions,
+                blocked_extensions: config.blocked_extensions,
+                compress_text: config.compress_text,
+                slug_mode: config.slug_mode,
+                html_policy: config.html_policy,
+                site_url: config.site_url,
+                site_founded: config.site_founded,
+                max_page_size: config.max_page_size,
+                max_storage_bytes_per_user: config.max_storage_bytes_per_user,
+                read_only: config.read_only,
+                trailing_slash_redirect: config.trailing_slash_redirect,
+                preload_index: config.preload_index,
+                rendered_html: Arc::new(DashMap::new()),
                 jinja_env,
             };
             s
@@ -122,10 +212,81 @@ async fn get_state(app_state: Option<AppState>) -> AppState {
     state
 }
 
+fn get_cors_layer(state: &AppState) -> CorsLayer {
+    let origin = if state.allowed_origins.iter().any(|o| o == "*") {
+        // wildcard origins can not be combined with credentials, so echo the request origin
+        if state.allow_credentials {
+            AllowOrigin::mirror_request()
+        } else {
+            AllowOrigin::any()
+        }
+    } else {
+        AllowOrigin::list(
+            state
+                .allowed_origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    };
+    let methods: Vec<Method> = state
+        .allowed_methods
+        .iter()
+        .filter_map(|m| Method::from_bytes(m.as_bytes()).ok())
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(methods)
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+        .allow_credentials(state.allow_credentials)
+}
+
 pub async fn app(app_state: Option<AppState>) -> Router {
+    app_with_connection(app_state).await.0
+}
+
+/// Builds the app and hands back its database connection, so the pool can be closed once
+/// the server has drained.
+pub async fn app_with_connection(app_state: Option<AppState>) -> (Router, DatabaseConnection) {
+    let skip_schema_check = match env::var("SKIP_SCHEMA_CHECK") {
+        Ok(s) => app_state.is_some() || s == "true",
+        _ => app_state.is_some(),
+    };
     let state = Arc::new(get_state(app_state).await);
+    if !skip_schema_check {
+        if let Err(e) = check_schema(&state.conn).await {
+            panic!("{}", e);
+        }
+    }
+
+    let task_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            users::utils::prune_revoked_tokens(&task_state, now);
+            users::utils::prune_login_attempts(&task_state, now);
+            comments::prune_comment_attempts(&task_state, now);
+            if !task_state.read_only {
+                match posts::db::publish_due_posts(&task_state).await {
+                    Ok(0) => {}
+                    Ok(n) => info!("published {} scheduled posts", n),
+                    Err(_) => warn!("publish scheduled posts failed"),
+                }
+            }
+        }
+    });
+
     let ro = state.read_only;
     let mut router = Router::new()
+        .merge(health_routers())
+        .merge(feed_routers())
+        .merge(sitemap_routers())
+        .merge(stats_routers())
         .merge(users_routers(ro))
         .merge(categories_routers(ro))
         .merge(tags_routers(ro))
@@ -134,11 +295,28 @@ pub async fn app(app_state: Option<AppState>) -> Router {
         .merge(comments_routers(ro))
         .merge(attachments_routers(ro));
 
-    if state.preload_index {
+    if state.trailing_slash_redirect {
+        router = router.fallback(slash::redirect_trailing_slash);
+    } else if state.preload_index {
         router = router.fallback(preload::index);
     }
-    let app = router.layer(TraceLayer::new_for_http()).with_state(state);
-    app
+    let cors = get_cors_layer(&state);
+    let conn = state.conn.clone();
+    let trace = TraceLayer::new_for_http()
+        .make_span_with(logging::make_span)
+        .on_response(logging::on_response);
+    let app = router
+        .layer(middleware::from_fn(format::negotiate_format))
+        .layer(cors)
+        .layer(middleware::from_fn(allow::allow_methods))
+        .layer(PropagateRequestIdLayer::new(logging::REQUEST_ID_HEADER.clone()))
+        .layer(trace)
+        .layer(SetRequestIdLayer::new(
+            logging::REQUEST_ID_HEADER.clone(),
+            MakeRequestUuid,
+        ))
+        .with_state(state);
+    (app, conn)
 }
 
 pub async fn init(name: String, mail: String, password: String) {
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{HeaderName, Request, Response};
+use tracing::{info, info_span, Span};
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+pub fn make_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+    info_span!(
+        "request",
+        id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    )
+}
+
+pub fn on_response(response: &Response<Body>, latency: Duration, _span: &Span) {
+    info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "finished request"
+    );
+}
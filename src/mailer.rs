@@ -0,0 +1,15 @@
+use axum::async_trait;
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String>;
+}
+
+pub struct NoopMailer;
+
+#[async_trait]
+impl Mailer for NoopMailer {
+    async fn send(&self, _to: &str, _subject: &str, _body: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -1,12 +1,15 @@
 use std::env;
+use std::net::SocketAddr;
+use std::time::Duration;
 
 use getopts::Options;
-use tracing::{info, Level};
+use tracing::{info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 use tokio::signal;
+use tokio::sync::oneshot;
 use tokio::net::TcpListener;
 
-use rumo::{app, init};
+use rumo::{app_with_connection, init, Config};
 
 fn print_usage(program: &str, opts: Options) {
     let brief = format!("Usage: {} run|init [options]", program);
@@ -70,7 +73,18 @@ async fn main() {
 
     match command.as_str() {
         "run" => {
-            tracing_subscriber::fmt::init();
+            let config = match Config::from_env() {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let subscriber = FmtSubscriber::builder()
+                .with_max_level(config.log_level)
+                .finish();
+            tracing::subscriber::set_global_default(subscriber)
+                .expect("start log failed");
             let addr = env::var("LISTEN_ADDRESS").unwrap_or(String::from("127.0.0.1:3000"));
             info!("Listening on http://{}", addr);
             let listener = match TcpListener::bind(addr).await{
@@ -81,10 +95,32 @@ async fn main() {
                 }
             };
 
-            let app = app(None).await;
-            let _ = axum::serve(listener, app)
-                .with_graceful_shutdown(shutdown_signal())
-                .await;
+            let shutdown_timeout = config.shutdown_timeout;
+
+            let (app, conn) = app_with_connection(None).await;
+            let app = app.into_make_service_with_connect_info::<SocketAddr>();
+
+            // in-flight requests get shutdown_timeout seconds after the signal to finish
+            let (signaled_tx, signaled_rx) = oneshot::channel();
+            let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+                shutdown_signal().await;
+                let _ = signaled_tx.send(());
+            });
+            let deadline = async move {
+                if signaled_rx.await.is_ok() {
+                    tokio::time::sleep(Duration::from_secs(shutdown_timeout)).await;
+                } else {
+                    std::future::pending::<()>().await;
+                }
+            };
+            tokio::select! {
+                _ = async { server.await } => info!("all connections drained"),
+                _ = deadline => warn!("shutdown timeout reached, dropping remaining connections"),
+            }
+
+            if let Err(e) = conn.close().await {
+                warn!("close database failed: {}", e);
+            }
         }
         "init" => {
             let subscriber = FmtSubscriber::builder()
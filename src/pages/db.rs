@@ -5,6 +5,7 @@ use sea_orm::*;
 use super::forms::PageCreate;
 use crate::common::errors::FieldError;
 use crate::common::models::ContentWithFields;
+use crate::common::utils::{encode_text, get_order_by};
 use crate::entity::{content, content::Entity as Content, field::Entity as ContentField};
 use crate::AppState;
 
@@ -40,7 +41,8 @@ pub async fn create_page_by_page_create_with_uid(
         slug: Set(Some(page_create.slug.to_owned())),
         created: Set(now),
         modified: Set(now),
-        text: Set(Some(page_create.text.to_owned())),
+        text: Set(Some(encode_text(&page_create.text, state.compress_text))),
+        order: Set(page_create.order.unwrap_or(0)),
         author_id: Set(uid),
         status: Set(status.to_owned()),
         allow_comment: Set(allow_comment.to_string()),
@@ -102,7 +104,8 @@ pub async fn modify_page_by_page_modify_with_exist_page(
     c.slug = Set(Some(page_modify.slug.to_owned()));
     c.created = Set(now);
     c.modified = Set(now);
-    c.text = Set(Some(page_modify.text.to_owned()));
+    c.text = Set(Some(encode_text(&page_modify.text, state.compress_text)));
+    c.order = Set(page_modify.order.unwrap_or(exist_page.order));
     c.status = Set(status.to_owned());
     c.allow_comment = Set(allow_comment.to_string());
     c.allow_ping = Set(allow_ping.to_string());
@@ -117,6 +120,7 @@ pub async fn get_content_with_fields_by_slug(
     slug: &str,
 ) -> Result<ContentWithFields, FieldError> {
     let c = Content::find()
+        .filter(content::Column::Type.eq("page"))
         .filter(content::Column::Slug.eq(slug))
         .one(&state.conn)
         .await
@@ -151,13 +155,15 @@ pub async fn get_contents_with_fields_by_list_query_with_private(
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
     };
-    let stmt = match order_by {
-        "-cid" => stmt.order_by_desc(content::Column::Cid),
-        "cid" => stmt.order_by_asc(content::Column::Cid),
-        "-slug" => stmt.order_by_desc(content::Column::Slug),
-        "slug" => stmt.order_by_asc(content::Column::Slug),
-        _ => stmt.order_by_desc(content::Column::Cid),
-    };
+    let (column, order) = get_order_by(
+        order_by,
+        &[
+            ("cid", content::Column::Cid),
+            ("slug", content::Column::Slug),
+            ("order", content::Column::Order),
+        ],
+    )?;
+    let stmt = stmt.order_by(column, order);
     let paginator = stmt.paginate(&state.conn, page_size);
 
     let contents = paginator
@@ -12,6 +12,7 @@ pub struct PageCreate {
     pub text: String,
     #[validate(length(min = 1, max = 32, message = "template length must greater than 1"))]
     pub template: Option<String>,
+    pub order: Option<u32>,
     pub publish: Option<bool>,
     pub allowComment: Option<bool>,
     pub allowPing: Option<bool>,
@@ -5,15 +5,22 @@ use axum::{
 use std::sync::Arc;
 
 use super::views;
+use crate::allow::method_not_allowed;
 use crate::AppState;
 
 pub fn pages_routers(ro: bool) -> Router<Arc<AppState>> {
     let pages_route = Router::new()
-        .route("/api/pages/", get(views::list_pages))
-        .route("/api/pages/:slug", get(views::get_page_by_slug))
+        .route(
+            "/api/pages/",
+            get(views::list_pages).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/pages/:slug",
+            get(views::get_page_by_slug).fallback(method_not_allowed),
+        )
         .route(
             "/api/pages/:slug/fields/:name",
-            get(views::get_page_field_by_slug_and_name),
+            get(views::get_page_field_by_slug_and_name).fallback(method_not_allowed),
         );
     if !ro {
         pages_route
@@ -22,7 +29,7 @@ pub fn pages_routers(ro: bool) -> Router<Arc<AppState>> {
             .route("/api/pages/:slug", delete(views::delete_page_by_slug))
             .route(
                 "/api/pages/:slug/fields/",
-                post(views::create_page_field_by_slug),
+                post(views::create_page_field_by_slug).fallback(method_not_allowed),
             )
             .route(
                 "/api/pages/:slug/fields/:name",
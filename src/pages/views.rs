@@ -10,31 +10,35 @@ use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
 use crate::common::forms::FieldCreate;
-use crate::common::forms::ListQueryWithPrivate;
+use crate::common::forms::{ListQueryWithPrivate, TimeRange};
+use crate::common::utils::get_page_size;
 use crate::AppState;
 
 pub async fn create_page(
     State(state): State<Arc<AppState>>,
     PMEditor(user): PMEditor,
-    ValidatedJson(page_create): ValidatedJson<PageCreate>,
+    ValidatedJson(mut page_create): ValidatedJson<PageCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
     match common_db::get_content_by_slug(&state, &page_create.slug).await {
         Ok(Some(_)) => return Err(FieldError::AlreadyExist("page".to_owned())),
         _ => (),
     };
 
+    page_create.text = state
+        .html_policy
+        .sanitize_for_group(&user.group, &page_create.text);
     let _ = db::create_page_by_page_create_with_uid(&state, &page_create, user.uid).await?;
     Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
 }
 
 pub async fn modify_page_by_slug(
     State(state): State<Arc<AppState>>,
-    PMEditor(_): PMEditor,
+    PMEditor(user): PMEditor,
     Path(slug): Path<String>,
-    ValidatedJson(page_modify): ValidatedJson<PageCreate>,
+    ValidatedJson(mut page_modify): ValidatedJson<PageCreate>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_page = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "page" => p,
         _ => return Err(FieldError::NotFound("page".to_owned())),
     };
 
@@ -45,6 +49,9 @@ pub async fn modify_page_by_slug(
         };
     }
 
+    page_modify.text = state
+        .html_policy
+        .sanitize_for_group(&user.group, &page_modify.text);
     let _ =
         db::modify_page_by_page_modify_with_exist_page(&state, &page_modify, &exist_page).await?;
     Ok(Json(json!({ "msg": "ok" })))
@@ -61,12 +68,15 @@ pub async fn list_pages(
         return Err(FieldError::PermissionDeny);
     }
 
-    let all_count =
-        common_db::get_contents_count_with_private(&state, private, false, &user, "page").await;
+    let range = TimeRange::default();
+    let all_count = common_db::get_contents_count_with_private(
+        &state, private, false, &user, None, &range, "page",
+    )
+    .await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
-    let order_by = q.order_by.unwrap_or("-cid".to_string());
+    let page_size = get_page_size(q.page_size, state.max_page_size);
+    let order_by = q.order_by.unwrap_or("order".to_string());
 
     let pages = db::get_contents_with_fields_by_list_query_with_private(
         &state, private, page_size, page, &order_by, false,
@@ -101,8 +111,8 @@ pub async fn delete_page_by_slug(
     PMEditor(_): PMEditor,
     Path(slug): Path<String>,
 ) -> Result<Json<Value>, FieldError> {
-    let page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let page = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "page" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
@@ -118,8 +128,8 @@ pub async fn create_page_field_by_slug(
     Path(slug): Path<String>,
     ValidatedJson(field_create): ValidatedJson<FieldCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
-    let exist_page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_page = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "page" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
@@ -132,13 +142,13 @@ pub async fn get_page_field_by_slug_and_name(
     State(state): State<Arc<AppState>>,
     Path((slug, name)): Path<(String, String)>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_page = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "page" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
-    let field = match common_db::get_field_by_cid_and_name(&state, exist_page.cid, &name).await {
-        Ok(Some(f)) => f,
+    let field = match common_db::get_field_by_cid_and_name(&state, exist_page.cid, &name).await? {
+        Some(f) => f,
         _ => return Err(FieldError::NotFound("name".to_owned())),
     };
     Ok(Json(json!(field)))
@@ -149,13 +159,13 @@ pub async fn delete_page_field_by_slug_and_name(
     PMEditor(_): PMEditor,
     Path((slug, name)): Path<(String, String)>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_page = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "page" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
-    match common_db::get_field_by_cid_and_name(&state, exist_page.cid, &name).await {
-        Ok(Some(f)) => f,
+    match common_db::get_field_by_cid_and_name(&state, exist_page.cid, &name).await? {
+        Some(f) => f,
         _ => return Err(FieldError::NotFound("name".to_owned())),
     };
 
@@ -169,14 +179,14 @@ pub async fn modify_page_field_by_slug_and_name(
     Path((slug, name)): Path<(String, String)>,
     ValidatedJson(field_modfify): ValidatedJson<FieldCreate>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_page = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "page" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
     if name != field_modfify.name {
-        match common_db::get_field_by_cid_and_name(&state, exist_page.cid, &name).await {
-            Ok(Some(f)) => f,
+        match common_db::get_field_by_cid_and_name(&state, exist_page.cid, &name).await? {
+            Some(f) => f,
             _ => return Err(FieldError::NotFound("name".to_owned())),
         };
     }
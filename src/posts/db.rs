@@ -1,22 +1,175 @@
-use std::time::SystemTime;
+use std::collections::HashMap;
 
 use sea_orm::*;
 
 use super::forms::PostCreate;
-use crate::common::errors::FieldError;
-use crate::common::models::ContentWithMetasUsersFields;
-use crate::entity::{content, content::Entity as Content, field, meta, relationship, user};
+use super::models::{escape_like, SearchRow};
+use crate::common::db as common_db;
+use crate::common::errors::{insert_error, FieldError};
+use crate::common::forms::TimeRange;
+use crate::common::models::{ContentAuthor, ContentWithMetasUsersFields};
+use crate::common::utils::{
+    encode_text, get_cursor_ascending, get_order_by, COMPRESSED_TEXT_MARKER,
+};
+use crate::entity::{
+    comment, content, content::Entity as Content, field, meta, relationship, user,
+};
+use crate::users::utils::current_timestamp;
 use crate::AppState;
 
+/// Custom field keeping the status a trashed post is restored to.
+pub const TRASH_STATUS_FIELD: &str = "trash_status";
+
+/// Fields served with posts, the internal trash status is left out.
+fn get_public_fields() -> Select<field::Entity> {
+    field::Entity::find().filter(field::Column::Name.ne(TRASH_STATUS_FIELD))
+}
+
+/// A future `created` schedules the post, a past one is only kept with `keep_past` so imports
+/// retain their original dates.
+fn get_created_and_status(post_create: &PostCreate, now: u32, keep_past: bool) -> (u32, String) {
+    // created is range checked by the views, so it always fits in u32 here
+    let created = post_create.created.map_or(now, |c| c as u32);
+    if post_create.status == "publish" && created > now {
+        (created, String::from("waiting"))
+    } else if keep_past && created <= now {
+        (created, post_create.status.to_owned())
+    } else {
+        (now, post_create.status.to_owned())
+    }
+}
+
+pub async fn publish_due_posts(state: &AppState) -> Result<u64, FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?
+        as u32;
+
+    // scheduled posts are the only waiting posts whose created is later than modified
+    let result = Content::update_many()
+        .col_expr(content::Column::Status, sea_query::Expr::value("publish"))
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Status.eq("waiting"))
+        .filter(content::Column::Created.lte(now))
+        .filter(
+            sea_query::Expr::col(content::Column::Created)
+                .gt(sea_query::Expr::col(content::Column::Modified)),
+        )
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("publish due posts failed".to_string()))?;
+    Ok(result.rows_affected)
+}
+
+pub async fn get_feed_posts(
+    state: &AppState,
+    limit: u64,
+) -> Result<Vec<(content::Model, Option<user::Model>)>, FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?
+        as u32;
+
+    Content::find()
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Status.eq("publish"))
+        .filter(content::Column::AllowFeed.eq("1"))
+        .filter(content::Column::Created.lte(now))
+        .order_by_desc(content::Column::Created)
+        .limit(limit)
+        .find_also_related(user::Entity)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch feed posts failed".to_string()))
+}
+
+async fn get_or_create_tags_by_slugs<C: ConnectionTrait>(
+    conn: &C,
+    slugs: &[String],
+) -> Result<Vec<meta::Model>, FieldError> {
+    let mut tags: Vec<meta::Model> = vec![];
+    for slug in slugs {
+        if tags
+            .iter()
+            .any(|t| t.slug.as_deref() == Some(slug.as_str()))
+        {
+            continue;
+        }
+        let tag = meta::Entity::find()
+            .filter(meta::Column::Slug.eq(slug))
+            .filter(meta::Column::Type.eq("tag"))
+            .one(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("fetch meta failed".to_string()))?;
+        let tag = match tag {
+            Some(t) => t,
+            None => meta::ActiveModel {
+                r#type: Set("tag".to_string()),
+                name: Set(Some(slug.to_owned())),
+                slug: Set(Some(slug.to_owned())),
+                ..Default::default()
+            }
+            .insert(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("create tag failed".to_string()))?,
+        };
+        tags.push(tag);
+    }
+    Ok(tags)
+}
+
+async fn get_categories_by_slugs<C: ConnectionTrait>(
+    conn: &C,
+    slugs: &[String],
+) -> Result<Vec<meta::Model>, FieldError> {
+    let mut categories: Vec<meta::Model> = vec![];
+    for slug in slugs {
+        if categories
+            .iter()
+            .any(|c| c.slug.as_deref() == Some(slug.as_str()))
+        {
+            continue;
+        }
+        let category = meta::Entity::find()
+            .filter(meta::Column::Slug.eq(slug))
+            .filter(meta::Column::Type.eq("category"))
+            .one(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("fetch meta failed".to_string()))?
+            .ok_or(FieldError::InvalidParams("categories".to_string()))?;
+        categories.push(category);
+    }
+    Ok(categories)
+}
+
 pub async fn create_post_by_post_create_with_uid(
     state: &AppState,
     post_create: &PostCreate,
     uid: u32,
-) -> Result<content::ActiveModel, FieldError> {
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as u32;
+) -> Result<content::Model, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("create post failed".to_string()))?;
+
+    let post = insert_post_with_metas(state, &txn, post_create, uid, false).await?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("create post failed".to_string()))?;
+    Ok(post)
+}
+
+async fn insert_post_with_metas<C: ConnectionTrait>(
+    state: &AppState,
+    conn: &C,
+    post_create: &PostCreate,
+    uid: u32,
+    keep_past: bool,
+) -> Result<content::Model, FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?
+        as u32;
+    let (created, status) = get_created_and_status(post_create, now, keep_past);
     let allow_comment = match post_create.allowComment.unwrap_or(true) {
         true => "1",
         false => "0",
@@ -30,40 +183,245 @@ pub async fn create_post_by_post_create_with_uid(
         false => "0",
     };
 
-    content::ActiveModel {
+    let post = content::ActiveModel {
         r#type: Set("post".to_string()),
         title: Set(Some(post_create.title.to_owned())),
         slug: Set(Some(post_create.slug.to_owned())),
-        created: Set(now),
+        created: Set(created),
         modified: Set(now),
-        text: Set(Some(post_create.text.to_owned())),
+        text: Set(Some(encode_text(&post_create.text, state.compress_text))),
         author_id: Set(uid),
-        status: Set(post_create.status.to_owned()),
+        status: Set(status),
         password: Set(post_create.password.to_owned()),
         allow_comment: Set(allow_comment.to_string()),
         allow_ping: Set(allow_ping.to_string()),
         allow_feed: Set(allow_feed.to_string()),
         ..Default::default()
     }
-    .save(&state.conn)
+    .insert(conn)
     .await
-    .map_err(|_| FieldError::DatabaseFailed("create post failed".to_string()))
+    .map_err(|e| insert_error(e, "slug", "create post failed"))?;
+
+    let mut metas = match post_create.categories {
+        Some(ref slugs) => get_categories_by_slugs(conn, slugs).await?,
+        None => vec![],
+    };
+    if let Some(ref slugs) = post_create.tags {
+        metas.extend(get_or_create_tags_by_slugs(conn, slugs).await?);
+    }
+
+    for m in metas {
+        relationship::ActiveModel {
+            cid: Set(post.cid),
+            mid: Set(m.mid),
+        }
+        .insert(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("create relationship failed".to_string()))?;
+
+        let count = m.count + 1;
+        let mut m = meta::ActiveModel::from(m);
+        m.count = Set(count);
+        m.update(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))?;
+    }
+
+    Ok(post)
+}
+
+/// One page of post cids after `after` for export, oldest first.
+pub async fn get_post_cids_for_export(
+    state: &AppState,
+    after: u32,
+    status: Option<&str>,
+    since: Option<u32>,
+    until: Option<u32>,
+    limit: u64,
+) -> Result<Vec<u32>, FieldError> {
+    let stmt = Content::find()
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Cid.gt(after));
+    let stmt = match status {
+        Some(status) => stmt.filter(content::Column::Status.eq(status)),
+        None => stmt,
+    };
+    let stmt = match since {
+        Some(since) => stmt.filter(content::Column::Created.gte(since)),
+        None => stmt,
+    };
+    let stmt = match until {
+        Some(until) => stmt.filter(content::Column::Created.lte(until)),
+        None => stmt,
+    };
+    stmt.select_only()
+        .column(content::Column::Cid)
+        .order_by_asc(content::Column::Cid)
+        .limit(limit)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))
+}
+
+/// Imports all posts in one transaction, each post in its own savepoint so a failed post
+/// only drops itself. `Ok(None)` marks a post skipped because its slug is taken.
+pub async fn import_posts_with_uid(
+    state: &AppState,
+    posts: &mut [PostCreate],
+    suffix: bool,
+    uid: u32,
+) -> Result<Vec<Result<Option<content::Model>, FieldError>>, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("import posts failed".to_string()))?;
+
+    let mut results = vec![];
+    for post_create in posts.iter_mut() {
+        let slug = match get_available_slug(&txn, &post_create.slug, suffix).await {
+            Ok(Some(slug)) => slug,
+            Ok(None) => {
+                results.push(Ok(None));
+                continue;
+            }
+            Err(e) => {
+                results.push(Err(e));
+                continue;
+            }
+        };
+        post_create.slug = slug;
+
+        let savepoint = txn
+            .begin()
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("import posts failed".to_string()))?;
+        match insert_post_with_metas(state, &savepoint, post_create, uid, true).await {
+            Ok(post) => {
+                savepoint
+                    .commit()
+                    .await
+                    .map_err(|_| FieldError::DatabaseFailed("import posts failed".to_string()))?;
+                results.push(Ok(Some(post)));
+            }
+            Err(e) => {
+                let _ = savepoint.rollback().await;
+                results.push(Err(e));
+            }
+        }
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("import posts failed".to_string()))?;
+    Ok(results)
+}
+
+/// Returns the slug itself when free, otherwise `slug-1`, `slug-2`... with `suffix`, or None.
+async fn get_available_slug<C: ConnectionTrait>(
+    conn: &C,
+    slug: &str,
+    suffix: bool,
+) -> Result<Option<String>, FieldError> {
+    for i in 0..100 {
+        let candidate = match i {
+            0 => slug.to_string(),
+            _ => format!("{slug}-{i}"),
+        };
+        let exist = Content::find()
+            .filter(content::Column::Slug.eq(&candidate))
+            .one(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))?;
+        if exist.is_none() {
+            return Ok(Some(candidate));
+        }
+        if !suffix {
+            return Ok(None);
+        }
+    }
+    Err(FieldError::AlreadyExist("slug".to_string()))
+}
+
+pub async fn set_post_tags_by_exist_post(
+    state: &AppState,
+    exist_post: &content::Model,
+    slugs: &[String],
+) -> Result<Vec<meta::Model>, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("set post tags failed".to_string()))?;
+
+    let current = exist_post
+        .find_related(meta::Entity)
+        .filter(meta::Column::Type.eq("tag"))
+        .all(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?;
+
+    let mut tags = get_or_create_tags_by_slugs(&txn, slugs).await?;
+
+    for tag in tags.iter_mut() {
+        if current.iter().any(|c| c.mid == tag.mid) {
+            continue;
+        }
+        relationship::ActiveModel {
+            cid: Set(exist_post.cid),
+            mid: Set(tag.mid),
+        }
+        .insert(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("create relationship failed".to_string()))?;
+
+        let mut m = meta::ActiveModel::from(tag.clone());
+        m.count = Set(tag.count + 1);
+        *tag = m
+            .update(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))?;
+    }
+
+    for tag in current {
+        if tags.iter().any(|t| t.mid == tag.mid) {
+            continue;
+        }
+        relationship::Entity::delete_many()
+            .filter(relationship::Column::Cid.eq(exist_post.cid))
+            .filter(relationship::Column::Mid.eq(tag.mid))
+            .exec(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("delete relationship failed".to_string()))?;
+
+        let count = tag.count.saturating_sub(1);
+        let mut m = meta::ActiveModel::from(tag);
+        m.count = Set(count);
+        m.update(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("set post tags failed".to_string()))?;
+    Ok(tags)
 }
 
+/// Only writes while the post still has the `modified` it was read with, `Modified` otherwise,
+/// so concurrent editors can not silently overwrite each other.
 pub async fn modify_post_by_post_create_with_exist_post(
     state: &AppState,
     post_modify: &PostCreate,
     exist_post: &content::Model,
 ) -> Result<content::Model, FieldError> {
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as u32;
-    let now = if now > post_modify.created {
-        now
-    } else {
-        post_modify.created
-    };
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?
+        as u32;
+    let (created, status) = get_created_and_status(post_modify, now, false);
+    // every save moves modified forward, even twice within a second
+    let modified = now.max(exist_post.modified + 1);
 
     let allow_comment = match post_modify
         .allowComment
@@ -90,19 +448,192 @@ pub async fn modify_post_by_post_create_with_exist_post(
     let mut c = content::ActiveModel::from(exist_post.clone());
     c.title = Set(Some(post_modify.title.to_owned()));
     c.slug = Set(Some(post_modify.slug.to_owned()));
-    c.created = Set(now);
-    c.modified = Set(now);
-    c.text = Set(Some(post_modify.text.to_owned()));
-    c.status = Set(post_modify.status.to_owned());
+    c.created = Set(created);
+    c.modified = Set(modified);
+    c.text = Set(Some(encode_text(&post_modify.text, state.compress_text)));
+    c.status = Set(status);
     c.password = Set(post_modify.password.to_owned());
     c.allow_comment = Set(allow_comment.to_string());
     c.allow_ping = Set(allow_ping.to_string());
     c.allow_feed = Set(allow_feed.to_string());
-    c.update(&state.conn)
+    let result = Content::update_many()
+        .set(c.clone())
+        .filter(content::Column::Cid.eq(exist_post.cid))
+        .filter(content::Column::Modified.eq(exist_post.modified))
+        .exec(&state.conn)
         .await
+        .map_err(|e| insert_error(e, "post slug", "update post failed"))?;
+    if result.rows_affected == 0 {
+        return Err(FieldError::Modified("post".to_string()));
+    }
+    c.try_into_model()
         .map_err(|_| FieldError::DatabaseFailed("update post failed".to_string()))
 }
 
+pub async fn update_post_order_by_cid(
+    state: &AppState,
+    cid: u32,
+    order: u32,
+) -> Result<UpdateResult, FieldError> {
+    Content::update_many()
+        .col_expr(content::Column::Order, sea_query::Expr::value(order))
+        .filter(content::Column::Cid.eq(cid))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update post order failed".to_string()))
+}
+
+pub async fn update_post_status_by_exist_post(
+    state: &AppState,
+    exist_post: &content::Model,
+    status: &str,
+) -> Result<content::Model, FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?
+        as u32;
+
+    let mut c = content::ActiveModel::from(exist_post.clone());
+    if status == "publish" && exist_post.created > now {
+        c.created = Set(now);
+    }
+    c.status = Set(status.to_string());
+    c.modified = Set(now);
+    c.update(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update post status failed".to_string()))
+}
+
+pub async fn trash_post_by_exist_post(
+    state: &AppState,
+    exist_post: &content::Model,
+) -> Result<content::Model, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("trash post failed".to_string()))?;
+
+    field::Entity::delete_many()
+        .filter(field::Column::Cid.eq(exist_post.cid))
+        .filter(field::Column::Name.eq(TRASH_STATUS_FIELD))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete field failed".to_string()))?;
+    field::ActiveModel {
+        cid: Set(exist_post.cid),
+        name: Set(TRASH_STATUS_FIELD.to_string()),
+        r#type: Set("str".to_string()),
+        str_value: Set(Some(exist_post.status.to_owned())),
+        int_value: Set(0),
+        float_value: Set(0.0),
+    }
+    .insert(&txn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create field failed".to_string()))?;
+
+    let mut c = content::ActiveModel::from(exist_post.clone());
+    c.status = Set("trash".to_string());
+    let post = c
+        .update(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("trash post failed".to_string()))?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("trash post failed".to_string()))?;
+    Ok(post)
+}
+
+pub async fn restore_post_by_exist_post(
+    state: &AppState,
+    exist_post: &content::Model,
+) -> Result<content::Model, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("restore post failed".to_string()))?;
+
+    let trash_status = field::Entity::find()
+        .filter(field::Column::Cid.eq(exist_post.cid))
+        .filter(field::Column::Name.eq(TRASH_STATUS_FIELD))
+        .one(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch field failed".to_string()))?;
+    let status = trash_status
+        .and_then(|f| f.str_value)
+        .unwrap_or("publish".to_string());
+
+    field::Entity::delete_many()
+        .filter(field::Column::Cid.eq(exist_post.cid))
+        .filter(field::Column::Name.eq(TRASH_STATUS_FIELD))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete field failed".to_string()))?;
+
+    let mut c = content::ActiveModel::from(exist_post.clone());
+    c.status = Set(status);
+    let post = c
+        .update(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("restore post failed".to_string()))?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("restore post failed".to_string()))?;
+    Ok(post)
+}
+
+pub async fn purge_post_by_exist_post(
+    state: &AppState,
+    exist_post: &content::Model,
+) -> Result<(), FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("purge post failed".to_string()))?;
+
+    let metas = exist_post
+        .find_related(meta::Entity)
+        .all(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?;
+    for m in metas {
+        let count = m.count.saturating_sub(1);
+        let mut m = meta::ActiveModel::from(m);
+        m.count = Set(count);
+        m.update(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))?;
+    }
+
+    relationship::Entity::delete_many()
+        .filter(relationship::Column::Cid.eq(exist_post.cid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete relationships failed".to_string()))?;
+    field::Entity::delete_many()
+        .filter(field::Column::Cid.eq(exist_post.cid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete fields failed".to_string()))?;
+    comment::Entity::delete_many()
+        .filter(comment::Column::Cid.eq(exist_post.cid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete comments failed".to_string()))?;
+    Content::delete_by_id(exist_post.cid)
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete content failed".to_string()))?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("purge post failed".to_string()))?;
+    Ok(())
+}
+
 pub async fn get_contents_with_metas_user_and_fields_by_filter_and_list_query(
     state: &AppState,
     private: bool,
@@ -111,36 +642,55 @@ pub async fn get_contents_with_metas_user_and_fields_by_filter_and_list_query(
     page_size: u64,
     page: u64,
     order_by: &str,
+    after: Option<u32>,
+    status: Option<&str>,
+    range: &TimeRange,
     post: bool,
 ) -> Result<Vec<ContentWithMetasUsersFields>, FieldError> {
     let content_type = if post { "post" } else { "page" };
 
     let stmt = Content::find().filter(content::Column::Type.eq(content_type));
+    let stmt = match after {
+        None | Some(0) => stmt,
+        Some(after) if get_cursor_ascending(order_by, "cid") == Some(true) => {
+            stmt.filter(content::Column::Cid.gt(after))
+        }
+        Some(after) => stmt.filter(content::Column::Cid.lt(after)),
+    };
     let stmt = if own {
         stmt.filter(content::Column::AuthorId.eq(author.uid))
     } else {
         stmt
     };
-    let stmt = if !private {
-        stmt.filter(content::Column::Status.eq("publish"))
+    let stmt = common_db::filter_contents_by_status(stmt, private, status);
+    let stmt = common_db::filter_contents_by_time_range(stmt, range);
+
+    // sticky posts have a non-zero order and always come first, except in cursor mode
+    let stmt = if post && after.is_none() {
+        stmt.order_by_desc(content::Column::Order)
     } else {
         stmt
     };
 
-    let stmt = match order_by {
-        "-cid" => stmt.order_by_desc(content::Column::Cid),
-        "cid" => stmt.order_by_asc(content::Column::Cid),
-        "-slug" => stmt.order_by_desc(content::Column::Slug),
-        "slug" => stmt.order_by_asc(content::Column::Slug),
-        _ => stmt.order_by_desc(content::Column::Cid),
-    };
-
-    let paginator = stmt.paginate(&state.conn, page_size);
+    let (column, order) = get_order_by(
+        order_by,
+        &[
+            ("cid", content::Column::Cid),
+            ("slug", content::Column::Slug),
+            ("created", content::Column::Created),
+            ("modified", content::Column::Modified),
+        ],
+    )?;
+    // ties on the chosen column would shuffle rows between pages
+    let stmt = stmt
+        .order_by(column, order.clone())
+        .order_by(content::Column::Cid, order);
 
-    let contents = paginator
-        .fetch_page(page - 1)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
+    let contents = match after {
+        Some(_) => stmt.limit(page_size).all(&state.conn).await,
+        None => stmt.paginate(&state.conn, page_size).fetch_page(page - 1).await,
+    }
+    .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
 
     let metas = contents
         .load_many_to_many(meta::Entity, relationship::Entity, &state.conn)
@@ -148,7 +698,7 @@ pub async fn get_contents_with_metas_user_and_fields_by_filter_and_list_query(
         .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?;
 
     let fields = contents
-        .load_many(field::Entity, &state.conn)
+        .load_many(get_public_fields(), &state.conn)
         .await
         .map_err(|_| FieldError::DatabaseFailed("fetch fields failed".to_string()))?;
 
@@ -184,7 +734,29 @@ pub async fn get_content_with_metas_user_fields_by_slug_and_private(
     slug: &str,
     private: bool,
 ) -> Result<ContentWithMetasUsersFields, FieldError> {
-    let stmt = Content::find().filter(content::Column::Slug.eq(slug));
+    let condition = Condition::all().add(content::Column::Slug.eq(slug));
+    get_content_with_metas_user_fields_by_condition(state, condition, private, "slug").await
+}
+
+pub async fn get_content_with_metas_user_fields_by_cid_and_private(
+    state: &AppState,
+    cid: u32,
+    private: bool,
+) -> Result<ContentWithMetasUsersFields, FieldError> {
+    let condition = Condition::all()
+        .add(content::Column::Cid.eq(cid))
+        .add(content::Column::Type.eq("post"));
+    get_content_with_metas_user_fields_by_condition(state, condition, private, "cid").await
+}
+
+/// Shared by the slug and cid lookups, `field` names the key reported when nothing matches.
+async fn get_content_with_metas_user_fields_by_condition(
+    state: &AppState,
+    condition: Condition,
+    private: bool,
+    field: &str,
+) -> Result<ContentWithMetasUsersFields, FieldError> {
+    let stmt = Content::find().filter(condition);
     let stmt = if private {
         stmt
     } else {
@@ -197,7 +769,7 @@ pub async fn get_content_with_metas_user_fields_by_slug_and_private(
         .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))?;
     let content = match content {
         Some(c) => c,
-        None => return Err(FieldError::InvalidParams("slug".to_string())),
+        None => return Err(FieldError::NotFound(field.to_string())),
     };
 
     let metas = content
@@ -208,6 +780,7 @@ pub async fn get_content_with_metas_user_fields_by_slug_and_private(
 
     let fields = content
         .find_related(field::Entity)
+        .filter(field::Column::Name.ne(TRASH_STATUS_FIELD))
         .all(&state.conn)
         .await
         .map_err(|_| FieldError::DatabaseFailed("fetch fields failed".to_string()))?;
@@ -226,3 +799,395 @@ pub async fn get_content_with_metas_user_fields_by_slug_and_private(
     res.fields = fields;
     Ok(res)
 }
+
+/// Fetches the posts for `slugs` with their metas, fields and author, in no particular order.
+pub async fn get_contents_with_metas_user_fields_by_slugs_and_private(
+    state: &AppState,
+    slugs: &[String],
+    private: bool,
+) -> Result<Vec<ContentWithMetasUsersFields>, FieldError> {
+    let stmt = Content::find()
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Slug.is_in(slugs.iter().cloned()));
+    let stmt = if private {
+        stmt
+    } else {
+        stmt.filter(content::Column::Status.eq("publish"))
+    };
+    let contents = stmt
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
+
+    let metas = contents
+        .load_many_to_many(meta::Entity, relationship::Entity, &state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?;
+
+    let fields = contents
+        .load_many(get_public_fields(), &state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch fields failed".to_string()))?;
+
+    let authors = contents
+        .load_one(user::Entity, &state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch authors failed".to_string()))?;
+
+    let mut res = vec![];
+    for (((content, meta_list), field_list), author) in contents
+        .into_iter()
+        .zip(metas.into_iter())
+        .zip(fields.into_iter())
+        .zip(authors.into_iter())
+    {
+        let mut ct = ContentWithMetasUsersFields::from(content);
+        if let Some(author) = author {
+            ct.screen_name = author.screen_name;
+            ct.group = author.group;
+        }
+
+        let mut tags = vec![];
+        let mut categories = vec![];
+        for m in meta_list {
+            if m.r#type == "tag" {
+                tags.push(m);
+            } else {
+                categories.push(m);
+            }
+        }
+        ct.tags = tags;
+        ct.categories = categories;
+        ct.fields = field_list;
+        res.push(ct);
+    }
+    Ok(res)
+}
+
+/// Embeds the public author data of each post, for `include=author`.
+pub async fn attach_authors(
+    state: &AppState,
+    posts: &mut [ContentWithMetasUsersFields],
+) -> Result<(), FieldError> {
+    let uids: Vec<u32> = posts.iter().map(|p| p.author_id).collect();
+    let users = user::Entity::find()
+        .filter(user::Column::Uid.is_in(uids))
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch authors failed".to_string()))?;
+
+    let authors: HashMap<u32, user::Model> = users.into_iter().map(|u| (u.uid, u)).collect();
+    for post in posts.iter_mut() {
+        post.author = authors
+            .get(&post.author_id)
+            .cloned()
+            .map(ContentAuthor::from);
+    }
+    Ok(())
+}
+
+/// Finds the published post right before (`newer` false) or after a post by `created`, ties broken by cid.
+pub async fn get_sibling_post_by_cid_and_created(
+    state: &AppState,
+    cid: u32,
+    created: u32,
+    category_mid: Option<u32>,
+    newer: bool,
+) -> Result<Option<content::Model>, FieldError> {
+    let stmt = Content::find()
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Cid.ne(cid));
+    let stmt = common_db::filter_contents_by_status(stmt, false, None);
+    let stmt = match category_mid {
+        Some(mid) => {
+            let cids: Vec<u32> = relationship::Entity::find()
+                .filter(relationship::Column::Mid.eq(mid))
+                .all(&state.conn)
+                .await
+                .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))?
+                .into_iter()
+                .map(|r| r.cid)
+                .collect();
+            stmt.filter(content::Column::Cid.is_in(cids))
+        }
+        None => stmt,
+    };
+    let stmt = if newer {
+        stmt.filter(
+            Condition::any()
+                .add(content::Column::Created.gt(created))
+                .add(
+                    Condition::all()
+                        .add(content::Column::Created.eq(created))
+                        .add(content::Column::Cid.gt(cid)),
+                ),
+        )
+        .order_by_asc(content::Column::Created)
+        .order_by_asc(content::Column::Cid)
+    } else {
+        stmt.filter(
+            Condition::any()
+                .add(content::Column::Created.lt(created))
+                .add(
+                    Condition::all()
+                        .add(content::Column::Created.eq(created))
+                        .add(content::Column::Cid.lt(cid)),
+                ),
+        )
+        .order_by_desc(content::Column::Created)
+        .order_by_desc(content::Column::Cid)
+    };
+    stmt.one(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))
+}
+
+pub async fn get_related_posts_by_cid_and_metas(
+    state: &AppState,
+    cid: u32,
+    tag_mids: Vec<u32>,
+    category_mids: Vec<u32>,
+    limit: u64,
+) -> Result<Vec<ContentWithMetasUsersFields>, FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?
+        as u32;
+    let published = Content::find()
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Status.eq("publish"))
+        .filter(content::Column::Created.lte(now))
+        .filter(content::Column::Cid.ne(cid));
+
+    // fall back to posts in the same categories when the post has no tags
+    let by_tags = !tag_mids.is_empty();
+    let mids = if by_tags { tag_mids } else { category_mids };
+    if mids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let relationships = relationship::Entity::find()
+        .filter(relationship::Column::Mid.is_in(mids))
+        .filter(relationship::Column::Cid.ne(cid))
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))?;
+    let mut overlaps: HashMap<u32, u32> = HashMap::new();
+    for r in relationships {
+        *overlaps.entry(r.cid).or_default() += 1;
+    }
+
+    let stmt = published.filter(content::Column::Cid.is_in(overlaps.keys().cloned()));
+    let stmt = if by_tags {
+        stmt
+    } else {
+        stmt.order_by_desc(content::Column::Created).limit(limit)
+    };
+    let mut contents = stmt
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
+
+    if by_tags {
+        contents.sort_by(|a, b| {
+            overlaps[&b.cid]
+                .cmp(&overlaps[&a.cid])
+                .then(b.created.cmp(&a.created))
+        });
+        contents.truncate(limit as usize);
+    }
+
+    let metas = contents
+        .load_many_to_many(meta::Entity, relationship::Entity, &state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?;
+
+    let fields = contents
+        .load_many(get_public_fields(), &state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch fields failed".to_string()))?;
+
+    let authors = contents
+        .load_one(user::Entity, &state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch authors failed".to_string()))?;
+
+    let mut res = vec![];
+    for (((content, meta_list), field_list), author) in contents
+        .into_iter()
+        .zip(metas.into_iter())
+        .zip(fields.into_iter())
+        .zip(authors.into_iter())
+    {
+        let mut ct = ContentWithMetasUsersFields::from(content);
+        if let Some(author) = author {
+            ct.screen_name = author.screen_name;
+            ct.group = author.group;
+        }
+
+        let mut tags = vec![];
+        let mut categories = vec![];
+        for m in meta_list {
+            if m.r#type == "tag" {
+                tags.push(m);
+            } else {
+                categories.push(m);
+            }
+        }
+        ct.tags = tags;
+        ct.categories = categories;
+        ct.fields = field_list;
+        res.push(ct);
+    }
+    Ok(res)
+}
+
+const SEARCH_FILTER: &str =
+    "type = 'post' AND status = 'publish' AND (password IS NULL OR password = '')";
+
+fn get_search_statements(
+    db_backend: DatabaseBackend,
+    q: &str,
+    page_size: u64,
+    page: u64,
+) -> (Statement, Statement) {
+    let offset = page_size * (page - 1);
+    // compressed text is an opaque blob, such rows only match on their title
+    let marker =
+        |column: &str, op: &str| format!("coalesce({column}, '') {op} '{COMPRESSED_TEXT_MARKER}%'");
+    let (compressed, plain) = (marker("text", "LIKE"), marker("text", "NOT LIKE"));
+    match db_backend {
+        DatabaseBackend::MySql => {
+            let pattern = escape_like(q);
+            let condition = format!(
+                "{SEARCH_FILTER} AND (({plain} AND MATCH(title, text) AGAINST (? IN NATURAL LANGUAGE MODE)) \
+                OR ({compressed} AND title LIKE ?))"
+            );
+            (
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!("SELECT COUNT(*) AS count FROM typecho_contents WHERE {condition}"),
+                    [q.into(), pattern.clone().into()],
+                ),
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!(
+                        "SELECT cid, title, slug, created, text FROM typecho_contents WHERE {condition} \
+                        ORDER BY MATCH(title, text) AGAINST (? IN NATURAL LANGUAGE MODE) DESC LIMIT ? OFFSET ?"
+                    ),
+                    [
+                        q.into(),
+                        pattern.into(),
+                        q.into(),
+                        page_size.into(),
+                        offset.into(),
+                    ],
+                ),
+            )
+        }
+        DatabaseBackend::Postgres => {
+            let document = format!(
+                "to_tsvector('simple', coalesce(title, '') || ' ' || \
+                CASE WHEN {compressed} THEN '' ELSE coalesce(text, '') END)"
+            );
+            let condition =
+                format!("{SEARCH_FILTER} AND {document} @@ plainto_tsquery('simple', $1)");
+            (
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!("SELECT COUNT(*) AS count FROM typecho_contents WHERE {condition}"),
+                    [q.into()],
+                ),
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!(
+                        "SELECT cid, title, slug, created, text FROM typecho_contents WHERE {condition} \
+                        ORDER BY ts_rank({document}, plainto_tsquery('simple', $1)) DESC LIMIT $2 OFFSET $3"
+                    ),
+                    [q.into(), (page_size as i64).into(), (offset as i64).into()],
+                ),
+            )
+        }
+        DatabaseBackend::Sqlite if cfg!(feature = "fts5") => {
+            let condition = format!(
+                "{SEARCH_FILTER} AND cid IN (SELECT rowid FROM typecho_contents_fts WHERE typecho_contents_fts MATCH ?) \
+                AND ({plain} OR cid IN (SELECT rowid FROM typecho_contents_fts WHERE typecho_contents_fts MATCH ?))"
+            );
+            let phrase = format!("\"{}\"", q.replace('"', "\"\""));
+            let title_phrase = format!("title : {phrase}");
+            let c_plain = marker("c.text", "NOT LIKE");
+            (
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!("SELECT COUNT(*) AS count FROM typecho_contents WHERE {condition}"),
+                    [phrase.clone().into(), title_phrase.clone().into()],
+                ),
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!(
+                        "SELECT c.cid, c.title, c.slug, c.created, c.text FROM typecho_contents_fts f \
+                        JOIN typecho_contents c ON c.cid = f.rowid \
+                        WHERE typecho_contents_fts MATCH ? AND c.type = 'post' AND c.status = 'publish' \
+                        AND (c.password IS NULL OR c.password = '') \
+                        AND ({c_plain} OR c.cid IN (SELECT rowid FROM typecho_contents_fts WHERE typecho_contents_fts MATCH ?)) \
+                        ORDER BY bm25(typecho_contents_fts) LIMIT ? OFFSET ?"
+                    ),
+                    [
+                        phrase.into(),
+                        title_phrase.into(),
+                        page_size.into(),
+                        offset.into(),
+                    ],
+                ),
+            )
+        }
+        DatabaseBackend::Sqlite => {
+            let pattern = escape_like(q);
+            let condition = format!(
+                "{SEARCH_FILTER} AND (title LIKE ? ESCAPE '\\' OR ({plain} AND text LIKE ? ESCAPE '\\'))"
+            );
+            (
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!("SELECT COUNT(*) AS count FROM typecho_contents WHERE {condition}"),
+                    [pattern.clone().into(), pattern.clone().into()],
+                ),
+                Statement::from_sql_and_values(
+                    db_backend,
+                    format!(
+                        "SELECT cid, title, slug, created, text FROM typecho_contents WHERE {condition} \
+                        ORDER BY (CASE WHEN title LIKE ? ESCAPE '\\' THEN 1 ELSE 0 END) DESC, created DESC \
+                        LIMIT ? OFFSET ?"
+                    ),
+                    [
+                        pattern.clone().into(),
+                        pattern.clone().into(),
+                        pattern.into(),
+                        page_size.into(),
+                        offset.into(),
+                    ],
+                ),
+            )
+        }
+    }
+}
+
+pub async fn search_posts(
+    state: &AppState,
+    q: &str,
+    page_size: u64,
+    page: u64,
+) -> Result<(Vec<SearchRow>, u64), FieldError> {
+    let (count_stmt, stmt) =
+        get_search_statements(state.conn.get_database_backend(), q, page_size, page);
+
+    let all_count = match state.conn.query_one(count_stmt).await {
+        Ok(Some(row)) => row.try_get::<i64>("", "count").unwrap_or(0) as u64,
+        Ok(None) => 0,
+        Err(_) => return Err(FieldError::DatabaseFailed("count posts failed".to_string())),
+    };
+    let rows = SearchRow::find_by_statement(stmt)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("search posts failed".to_string()))?;
+    Ok((rows, all_count))
+}
@@ -1,6 +1,68 @@
 #![allow(non_snake_case)]
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::common::forms::TimeRange;
+
+pub const POST_STATUSES: [&str; 6] = [
+    "publish", "hidden", "password", "private", "waiting", "draft",
+];
+/// How far ahead a scheduled post may be published.
+pub const MAX_SCHEDULE_SECONDS: u64 = 10 * 365 * 24 * 3600;
+/// Clock drift tolerated for posts that are not scheduled.
+pub const CREATED_SKEW_SECONDS: u64 = 300;
+pub const IMPORT_CONFLICTS: [&str; 2] = ["skip", "suffix"];
+
+/// ASCII characters must match `[a-z0-9-]`. Lowercase non-ASCII letters and digits pass here
+/// and are refused by the views unless `SLUG_MODE` is `keep`.
+fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    if slug.chars().all(|c| match c.is_ascii() {
+        true => c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-',
+        false => c.is_alphanumeric() && !c.is_uppercase(),
+    }) {
+        return Ok(());
+    }
+    Err(ValidationError::new("slug").with_message(Cow::from(
+        "slug must only contain lowercase letters, digits and -",
+    )))
+}
+
+fn validate_render(render: &str) -> Result<(), ValidationError> {
+    if render == "html" {
+        return Ok(());
+    }
+    Err(ValidationError::new("render").with_message(Cow::from("render must be html")))
+}
+
+fn validate_include(include: &str) -> Result<(), ValidationError> {
+    if include == "author" {
+        return Ok(());
+    }
+    Err(ValidationError::new("include").with_message(Cow::from("include must be author")))
+}
+
+fn validate_meta_slugs(slugs: &[String]) -> Result<(), ValidationError> {
+    if slugs.iter().all(|s| !s.is_empty() && s.len() <= 150) {
+        return Ok(());
+    }
+    Err(ValidationError::new("slugs").with_message(Cow::from("slug length must between 1 and 150")))
+}
+
+fn validate_on_conflict(on_conflict: &str) -> Result<(), ValidationError> {
+    if IMPORT_CONFLICTS.contains(&on_conflict) {
+        return Ok(());
+    }
+    Err(ValidationError::new("on_conflict").with_message(Cow::from("on_conflict is unknown")))
+}
+
+fn validate_status(status: &str) -> Result<(), ValidationError> {
+    if POST_STATUSES.contains(&status) {
+        return Ok(());
+    }
+    Err(ValidationError::new("status").with_message(Cow::from("status is unknown")))
+}
 
 #[derive(Serialize, Deserialize, Validate)]
 pub struct PostsQuery {
@@ -12,23 +74,141 @@ pub struct PostsQuery {
     pub order_by: Option<String>,
     pub private: Option<bool>,
     pub own: Option<bool>,
+    pub excerpt_only: Option<bool>,
+    pub after: Option<u32>,
+    #[validate(length(min = 1, max = 16, message = "status length must greater than 1"))]
+    pub status: Option<String>,
+    pub created_after: Option<u32>,
+    pub created_before: Option<u32>,
+    pub modified_after: Option<u32>,
+    pub modified_before: Option<u32>,
+    #[validate(custom(function = "validate_include"))]
+    pub include: Option<String>,
+}
+
+impl PostsQuery {
+    pub fn time_range(&self) -> TimeRange {
+        TimeRange {
+            created_after: self.created_after,
+            created_before: self.created_before,
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct SearchQuery {
+    #[validate(length(min = 1, max = 100, message = "q length must greater than 1"))]
+    pub q: String,
+    #[validate(range(min = 1, message = "page must greater than 1"))]
+    pub page: Option<u64>,
+    #[validate(range(min = 1, message = "page_size must greater than 1"))]
+    pub page_size: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
 pub struct PostCreate {
-    #[validate(length(min = 1, max = 150, message = "title length must greater than 1"))]
+    #[validate(length(min = 1, max = 200, message = "title length must between 1 and 200"))]
     pub title: String,
-    #[validate(length(min = 1, max = 150, message = "slug length must greater than 1"))]
+    #[validate(
+        length(min = 1, max = 150, message = "slug length must between 1 and 150"),
+        custom(function = "validate_slug")
+    )]
     pub slug: String,
-    pub created: u32,
+    pub created: Option<u64>,
     pub text: String,
-    #[validate(length(min = 1, max = 16, message = "status length must greater than 1"))]
+    #[validate(custom(function = "validate_status"))]
     pub status: String,
     #[validate(length(min = 1, max = 32, message = "password length must greater than 1"))]
     pub password: Option<String>,
     pub allowComment: Option<bool>,
     pub allowPing: Option<bool>,
     pub allowFeed: Option<bool>,
+    #[validate(custom(function = "validate_meta_slugs"))]
+    pub categories: Option<Vec<String>>,
+    #[validate(custom(function = "validate_meta_slugs"))]
+    pub tags: Option<Vec<String>>,
+    /// The `modified` the client last saw, a newer stored value rejects the update.
+    pub modified: Option<u64>,
+}
+
+/// Items are validated one by one on import so a bad post is reported instead of failing all.
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PostsImport {
+    #[validate(length(min = 1, max = 500, message = "posts length must between 1 and 500"))]
+    pub posts: Vec<PostCreate>,
+    #[validate(custom(function = "validate_on_conflict"))]
+    pub on_conflict: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PostsExport {
+    #[validate(custom(function = "validate_status"))]
+    pub status: Option<String>,
+    pub since: Option<u32>,
+    pub until: Option<u32>,
+}
+
+/// Only a publish status schedules a post, so only it may be dated in the future.
+pub fn is_created_valid(created: u64, now: u64, founded: u64, status: &str) -> bool {
+    let latest = match status {
+        "publish" => now + MAX_SCHEDULE_SECONDS,
+        _ => now + CREATED_SKEW_SECONDS,
+    };
+    created >= founded && created <= latest
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct RelatedQuery {
+    #[validate(range(min = 1, max = 20, message = "limit must between 1 and 20"))]
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PostSticky {
+    pub sticky: bool,
+    #[validate(range(min = 1, message = "order must greater than 1"))]
+    pub order: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PostStatus {
+    #[validate(custom(function = "validate_status"))]
+    pub status: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PostTags {
+    #[validate(
+        length(max = 100, message = "tags can not be more than 100"),
+        custom(function = "validate_meta_slugs")
+    )]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PostsBatch {
+    #[validate(
+        length(min = 1, max = 50, message = "slugs length must between 1 and 50"),
+        custom(function = "validate_meta_slugs")
+    )]
+    pub slugs: Vec<String>,
+    pub private: Option<bool>,
+    /// Drop missing or hidden posts instead of returning null in their place.
+    pub omit_missing: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct SiblingsQuery {
+    #[validate(length(min = 1, max = 150, message = "category length must greater than 1"))]
+    pub category: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct SlugQuery {
+    #[validate(length(min = 1, max = 150, message = "slug length must greater than 1"))]
+    pub slug: String,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -36,4 +216,56 @@ pub struct PostQuery {
     #[validate(length(min = 1, max = 32, message = "password length must greater than 1"))]
     pub password: Option<String>,
     pub private: Option<bool>,
+    pub with_fields: Option<bool>,
+    #[validate(custom(function = "validate_render"))]
+    pub render: Option<String>,
+    #[validate(custom(function = "validate_include"))]
+    pub include: Option<String>,
+}
+
+#[test]
+fn test_validate_post_create() {
+    let post = |slug: &str, status: &str| PostCreate {
+        title: "title".to_string(),
+        slug: slug.to_string(),
+        created: None,
+        text: "text".to_string(),
+        status: status.to_string(),
+        password: None,
+        allowComment: None,
+        allowPing: None,
+        allowFeed: None,
+        categories: None,
+        tags: None,
+        modified: None,
+    };
+
+    assert!(post("test-post-1", "publish").validate().is_ok());
+    assert!(post("test post", "publish").validate().is_err());
+    assert!(post("Test-Post", "publish").validate().is_err());
+    assert!(post("test\tpost", "publish").validate().is_err());
+    assert!(post("test_post", "publish").validate().is_err());
+    assert!(post("test.post", "publish").validate().is_err());
+    assert!(post("café-déjà-vu", "publish").validate().is_ok());
+    assert!(post("Café", "publish").validate().is_err());
+    assert!(post("test-post", "unknown").validate().is_err());
+
+    let mut with_tags = post("test-post", "publish");
+    with_tags.tags = Some(vec!["rust".to_string()]);
+    assert!(with_tags.validate().is_ok());
+    with_tags.categories = Some(vec!["".to_string()]);
+    assert!(with_tags.validate().is_err());
+}
+
+#[test]
+fn test_is_created_valid() {
+    let now = 1700000000;
+    let founded = 946684800;
+
+    assert!(is_created_valid(now, now, founded, "publish"));
+    assert!(is_created_valid(founded, now, founded, "hidden"));
+    assert!(!is_created_valid(0, now, founded, "publish"));
+    assert!(is_created_valid(now + 3600, now, founded, "publish"));
+    assert!(!is_created_valid(now + 3600, now, founded, "hidden"));
+    assert!(!is_created_valid(32503680000, now, founded, "publish"));
 }
@@ -0,0 +1,111 @@
+use sea_orm::FromQueryResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::common::models::ContentWithMetasUsersFields;
+use crate::common::utils::decode_text;
+
+/// Bumped whenever the export layout changes, so importers can tell old dumps apart.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Uses the `PostCreate` field names so an exported post can be imported again as is.
+pub fn get_export_post(post: &ContentWithMetasUsersFields) -> Value {
+    json!({
+        "cid": post.cid,
+        "title": post.title,
+        "slug": post.slug,
+        "created": post.created,
+        "modified": post.modified,
+        "text": post.text,
+        "status": post.status,
+        "password": post.password,
+        "allowComment": post.allow_comment == "1",
+        "allowPing": post.allow_ping == "1",
+        "allowFeed": post.allow_feed == "1",
+        "categories": post.categories.iter().map(|m| &m.slug).collect::<Vec<_>>(),
+        "tags": post.tags.iter().map(|m| &m.slug).collect::<Vec<_>>(),
+        "fields": post.fields,
+    })
+}
+
+#[derive(FromQueryResult)]
+pub struct SearchRow {
+    pub cid: u32,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub created: u32,
+    pub text: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PostSearchResult {
+    pub cid: u32,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub created: u32,
+    pub snippet: String,
+}
+
+impl PostSearchResult {
+    pub fn from_row(row: SearchRow, q: &str) -> Self {
+        Self {
+            cid: row.cid,
+            title: row.title,
+            slug: row.slug,
+            created: row.created,
+            snippet: get_snippet(&decode_text(row.text.unwrap_or_default()), q, 60),
+        }
+    }
+}
+
+pub fn get_snippet(text: &str, q: &str, radius: usize) -> String {
+    let chars = text.chars().collect::<Vec<char>>();
+    let lower = text.to_lowercase().chars().collect::<Vec<char>>();
+    let q = q.to_lowercase().chars().collect::<Vec<char>>();
+
+    let position = if lower.len() == chars.len() && !q.is_empty() {
+        lower.windows(q.len()).position(|w| w == q.as_slice())
+    } else {
+        None
+    };
+
+    let (start, end) = match position {
+        Some(p) => (
+            p.saturating_sub(radius),
+            (p + q.len() + radius).min(chars.len()),
+        ),
+        None => (0, (radius * 2).min(chars.len())),
+    };
+
+    let mut snippet = chars[start..end].iter().collect::<String>();
+    if start > 0 {
+        snippet = format!("...{}", snippet);
+    }
+    if end < chars.len() {
+        snippet = format!("{}...", snippet);
+    }
+    snippet
+}
+
+pub fn escape_like(q: &str) -> String {
+    let mut escaped = String::new();
+    for c in q.chars() {
+        if c == '\\' || c == '%' || c == '_' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    format!("%{}%", escaped)
+}
+
+#[test]
+fn test_get_snippet() {
+    assert_eq!(get_snippet("hello rust world", "Rust", 2), "...o rust w...");
+    assert_eq!(get_snippet("hello", "rust", 2), "hell...");
+    assert_eq!(get_snippet("你好世界", "世", 1), "...好世界");
+}
+
+#[test]
+fn test_escape_like() {
+    assert_eq!(escape_like("100%_a\\b"), "%100\\%\\_a\\\\b%");
+}
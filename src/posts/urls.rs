@@ -5,24 +5,91 @@ use axum::{
 use std::sync::Arc;
 
 use super::views;
+use crate::allow::method_not_allowed;
 use crate::AppState;
 
 pub fn posts_routers(ro: bool) -> Router<Arc<AppState>> {
     let posts_route = Router::new()
-        .route("/api/posts/", get(views::list_posts))
-        .route("/api/posts/:slug", get(views::get_post_by_slug));
+        .route(
+            "/api/posts/",
+            get(views::list_posts).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/export",
+            get(views::export_posts).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/:slug",
+            get(views::get_post_by_slug).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/batch",
+            post(views::get_posts_by_slugs).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/by-cid/:cid",
+            get(views::get_post_by_cid).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/slug-available",
+            get(views::check_slug_available).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/me/posts",
+            get(views::list_own_posts).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/:slug/related",
+            get(views::list_related_posts_by_slug).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/:slug/siblings",
+            get(views::get_post_siblings_by_slug).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/:slug/fields/",
+            get(views::list_post_fields_by_slug).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/posts/:slug/fields/:name",
+            get(views::get_post_field_by_slug_and_name).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/search",
+            get(views::search_posts).fallback(method_not_allowed),
+        );
     if !ro {
         posts_route
             .route("/api/posts/", post(views::create_post))
+            .route(
+                "/api/posts/import",
+                post(views::import_posts).fallback(method_not_allowed),
+            )
             .route("/api/posts/:slug", patch(views::modify_post_by_slug))
             .route("/api/posts/:slug", delete(views::delete_post_by_slug))
             .route(
-                "/api/posts/:slug/fields/",
-                post(views::create_post_field_by_slug),
+                "/api/posts/:slug/status",
+                patch(views::modify_post_status_by_slug).fallback(method_not_allowed),
             )
             .route(
-                "/api/posts/:slug/fields/:name",
-                get(views::get_post_field_by_slug_and_name),
+                "/api/posts/:slug/restore",
+                post(views::restore_post_by_slug).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/posts/:slug/purge",
+                delete(views::purge_post_by_slug).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/posts/:slug/sticky",
+                post(views::set_post_sticky).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/posts/:slug/tags",
+                post(views::set_post_tags).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/posts/:slug/fields/",
+                post(views::create_post_field_by_slug),
             )
             .route(
                 "/api/posts/:slug/fields/:name",
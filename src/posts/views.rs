@@ -1,55 +1,281 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::SystemTime;
 
+use axum::body::Body;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
-use axum::response::Json;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
 use serde_json::{json, Value};
+use validator::Validate;
 
 use super::db;
-use super::forms::{PostCreate, PostQuery, PostsQuery};
+use super::forms::{
+    is_created_valid, PostCreate, PostQuery, PostStatus, PostSticky, PostTags, PostsBatch,
+    PostsExport, PostsImport, PostsQuery, RelatedQuery, SearchQuery, SiblingsQuery, SlugQuery,
+};
+use super::models::{get_export_post, PostSearchResult, EXPORT_VERSION};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::common::extractors::{PMContributor, PMVisitor, ValidatedJson, ValidatedQuery};
+use crate::common::extractors::{
+    PMAdministrator, PMContributor, PMEditor, PMVisitor, ValidatedJson, ValidatedQuery,
+};
 use crate::common::forms::FieldCreate;
+use crate::common::models::ContentWithMetasUsersFields;
+use crate::common::utils::{
+    generate_slug, get_conditional_response, get_cursor_ascending, get_page_links, get_page_size,
+    is_stale_update, render_content_html, RenderedHtml, SlugMode,
+};
+use crate::entity::user::Model as User;
 use crate::AppState;
 
 pub async fn create_post(
     State(state): State<Arc<AppState>>,
     PMContributor(user): PMContributor,
     ValidatedJson(mut post_create): ValidatedJson<PostCreate>,
-) -> Result<(StatusCode, Json<Value>), FieldError> {
+) -> Result<Response, FieldError> {
+    check_post_slug(&state, &post_create)?;
+    check_post_created(&state, &post_create)?;
     if let Ok(Some(_)) = common_db::get_content_by_slug(&state, &post_create.slug).await {
-        return Err(FieldError::AlreadyExist("slug".to_owned()));
+        return Err(FieldError::Conflict("slug".to_owned()));
     }
 
-    if user.group == "contributor" {
+    if user.group == "contributor" && post_create.status != "draft" {
         post_create.status = String::from("waiting");
     }
+    post_create.text = state
+        .html_policy
+        .sanitize_for_group(&user.group, &post_create.text);
+
+    let post = db::create_post_by_post_create_with_uid(&state, &post_create, user.uid).await?;
+    let slug = post.slug.unwrap_or_default();
+    let post =
+        db::get_content_with_metas_user_fields_by_slug_and_private(&state, &slug, true).await?;
+
+    let location = format!("/api/posts/{slug}");
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(json!(post)),
+    )
+        .into_response())
+}
 
-    let _ = db::create_post_by_post_create_with_uid(&state, &post_create, user.uid).await?;
-    Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
+pub async fn import_posts(
+    State(state): State<Arc<AppState>>,
+    PMEditor(user): PMEditor,
+    ValidatedJson(posts_import): ValidatedJson<PostsImport>,
+) -> Result<Json<Value>, FieldError> {
+    let suffix = posts_import.on_conflict.as_deref() == Some("suffix");
+
+    let mut failed = vec![];
+    let mut indexes = vec![];
+    let mut posts = vec![];
+    for (index, post_create) in posts_import.posts.into_iter().enumerate() {
+        let reason = match post_create.validate() {
+            Ok(_) if check_post_slug(&state, &post_create).is_err() => {
+                Some("Invalid slug".to_string())
+            }
+            Ok(_) => match check_post_created(&state, &post_create) {
+                Ok(_) => None,
+                Err(_) => Some("Invalid created".to_string()),
+            },
+            Err(e) => Some(format!("Input validation error: {e}").replace('\n', ", ")),
+        };
+        match reason {
+            Some(reason) => {
+                failed.push(json!({ "index": index, "slug": post_create.slug, "reason": reason }))
+            }
+            None => {
+                let mut post_create = post_create;
+                post_create.text = state
+                    .html_policy
+                    .sanitize_for_group(&user.group, &post_create.text);
+                indexes.push(index);
+                posts.push(post_create);
+            }
+        }
+    }
+
+    let results = db::import_posts_with_uid(&state, &mut posts, suffix, user.uid).await?;
+
+    let mut created = vec![];
+    let mut skipped = vec![];
+    for ((index, post_create), result) in indexes.into_iter().zip(posts).zip(results) {
+        match result {
+            Ok(Some(post)) => {
+                created.push(json!({ "index": index, "cid": post.cid, "slug": post.slug }))
+            }
+            Ok(None) => skipped.push(
+                json!({ "index": index, "slug": post_create.slug, "reason": "Slug already exists" }),
+            ),
+            Err(e) => {
+                let reason = match e {
+                    FieldError::DatabaseFailed(s) => s,
+                    FieldError::AlreadyExist(_) | FieldError::Conflict(_) => {
+                        "Slug already exists".to_string()
+                    }
+                    _ => "Invalid post".to_string(),
+                };
+                failed.push(json!({ "index": index, "slug": post_create.slug, "reason": reason }));
+            }
+        }
+    }
+    failed.sort_by_key(|f| f["index"].as_u64());
+
+    Ok(Json(json!({
+        "created_count": created.len(),
+        "skipped_count": skipped.len(),
+        "failed_count": failed.len(),
+        "created": created,
+        "skipped": skipped,
+        "failed": failed,
+    })))
+}
+
+const EXPORT_PAGE_SIZE: u64 = 100;
+
+enum ExportStage {
+    Start,
+    Page(u32, bool),
+    Done,
+}
+
+async fn get_export_chunk(
+    state: &AppState,
+    q: &PostsExport,
+    after: u32,
+    first: bool,
+) -> Result<(String, Option<u32>), FieldError> {
+    let cids = db::get_post_cids_for_export(
+        state,
+        after,
+        q.status.as_deref(),
+        q.since,
+        q.until,
+        EXPORT_PAGE_SIZE,
+    )
+    .await?;
+
+    let mut items = vec![];
+    for cid in cids.iter() {
+        let post =
+            db::get_content_with_metas_user_fields_by_cid_and_private(state, *cid, true).await?;
+        items.push(get_export_post(&post).to_string());
+    }
+    let separator = if first { "" } else { "," };
+    Ok((
+        format!("{separator}{}", items.join(",")),
+        cids.last().copied(),
+    ))
+}
+
+/// Streams `{"version": .., "posts": [..]}` one page at a time to keep memory flat on large sites.
+pub async fn export_posts(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    ValidatedQuery(q): ValidatedQuery<PostsExport>,
+) -> Response {
+    let q = Arc::new(q);
+    let stream = futures::stream::unfold(ExportStage::Start, move |stage| {
+        let state = state.clone();
+        let q = q.clone();
+        async move {
+            match stage {
+                ExportStage::Start => {
+                    let head = format!("{{\"version\":{EXPORT_VERSION},\"posts\":[");
+                    Some((Ok(head), ExportStage::Page(0, true)))
+                }
+                ExportStage::Page(after, first) => {
+                    match get_export_chunk(&state, &q, after, first).await {
+                        Ok((chunk, Some(last))) => {
+                            Some((Ok(chunk), ExportStage::Page(last, false)))
+                        }
+                        Ok((_, None)) => Some((Ok("]}".to_string()), ExportStage::Done)),
+                        Err(_) => Some((
+                            Err(std::io::Error::other("export posts failed")),
+                            ExportStage::Done,
+                        )),
+                    }
+                }
+                ExportStage::Done => None,
+            }
+        }
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+/// Non-ASCII slugs are only kept as typed in the `keep` slug mode.
+fn check_post_slug(state: &AppState, post: &PostCreate) -> Result<(), FieldError> {
+    if !post.slug.is_ascii() && state.slug_mode != SlugMode::Keep {
+        return Err(FieldError::InvalidParams("slug".to_string()));
+    }
+    Ok(())
+}
+
+fn check_post_created(state: &AppState, post: &PostCreate) -> Result<(), FieldError> {
+    let created = match post.created {
+        Some(c) => c,
+        None => return Ok(()),
+    };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if !is_created_valid(created, now, state.site_founded, &post.status) {
+        return Err(FieldError::InvalidParams("created".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn check_slug_available(
+    State(state): State<Arc<AppState>>,
+    PMContributor(_): PMContributor,
+    ValidatedQuery(q): ValidatedQuery<SlugQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let slug = generate_slug(&q.slug, state.slug_mode);
+    if slug.is_empty() {
+        return Err(FieldError::InvalidParams("slug".to_string()));
+    }
+
+    let available = common_db::get_content_by_slug(&state, &slug).await?.is_none();
+    Ok(Json(json!({ "slug": slug, "available": available })))
 }
 
 pub async fn modify_post_by_slug(
     State(state): State<Arc<AppState>>,
     PMContributor(user): PMContributor,
     Path(slug): Path<String>,
+    headers: HeaderMap,
     ValidatedJson(mut post_modify): ValidatedJson<PostCreate>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
+    if is_stale_update(&headers, post_modify.modified, exist_post.modified) {
+        return Err(FieldError::Modified("post".to_owned()));
+    }
+    check_post_slug(&state, &post_modify)?;
+    check_post_created(&state, &post_modify)?;
 
     if slug != post_modify.slug {
         if let Ok(Some(_)) = common_db::get_content_by_slug(&state, &post_modify.slug).await {
-            return Err(FieldError::AlreadyExist("post slug".to_owned()));
+            return Err(FieldError::Conflict("post slug".to_owned()));
         }
     }
 
-    if user.group == "contributor" {
+    if user.group == "contributor" && post_modify.status != "draft" {
         post_modify.status = String::from("waiting");
     }
+    post_modify.text = state
+        .html_policy
+        .sanitize_for_group(&user.group, &post_modify.text);
 
     let _ =
         db::modify_post_by_post_create_with_exist_post(&state, &post_modify, &exist_post).await?;
@@ -61,62 +287,332 @@ pub async fn list_posts(
     PMVisitor(user): PMVisitor,
     ValidatedQuery(q): ValidatedQuery<PostsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let private =
-        q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
+    let admin = user.group == "editor" || user.group == "administrator";
+    let private = q.private.unwrap_or(false) && admin;
     let own = q.own.unwrap_or(false) && user.group != "visitor";
+    let status = q.status.as_deref().filter(|_| admin);
+    let range = q.time_range();
+    if let Some(field) = range.invalid_field() {
+        return Err(FieldError::InvalidParams(field.to_string()));
+    }
 
-    let all_count =
-        common_db::get_contents_count_with_private(&state, private, own, &user, "post").await;
+    let all_count = common_db::get_contents_count_with_private(
+        &state, private, own, &user, status, &range, "post",
+    )
+    .await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-cid".to_string());
+    if q.after.is_some() && get_cursor_ascending(&order_by, "cid").is_none() {
+        return Err(FieldError::InvalidParams("order_by".to_string()));
+    }
 
-    let posts = db::get_contents_with_metas_user_and_fields_by_filter_and_list_query(
-        &state, private, own, &user, page_size, page, &order_by, true,
+    let mut posts = db::get_contents_with_metas_user_and_fields_by_filter_and_list_query(
+        &state, private, own, &user, page_size, page, &order_by, q.after, status, &range, true,
     )
     .await?;
+    let next_cursor = match q.after {
+        Some(_) if posts.len() as u64 == page_size => posts.last().map(|p| p.cid),
+        _ => None,
+    };
+    if q.include.as_deref() == Some("author") {
+        db::attach_authors(&state, &mut posts).await?;
+    }
+
+    let excerpt_only = q.excerpt_only.unwrap_or(false);
+    for post in posts.iter_mut() {
+        if post.is_password_protected() && !admin && post.author_id != user.uid {
+            post.hide_protected_content();
+        }
+        if excerpt_only {
+            post.text = None;
+        }
+    }
     Ok(Json(json!({
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
+        "links": get_page_links(page, page_size, all_count),
         "count": posts.len(),
+        "next_cursor": next_cursor,
         "results": posts
     })))
 }
 
+pub async fn list_own_posts(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    ValidatedQuery(q): ValidatedQuery<PostsQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let status = q.status.as_deref();
+    let range = q.time_range();
+    if let Some(field) = range.invalid_field() {
+        return Err(FieldError::InvalidParams(field.to_string()));
+    }
+
+    let all_count = common_db::get_contents_count_with_private(
+        &state, true, true, &user, status, &range, "post",
+    )
+    .await?;
+
+    let page = q.page.unwrap_or(1);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
+    let order_by = q.order_by.unwrap_or("-cid".to_string());
+    if q.after.is_some() && get_cursor_ascending(&order_by, "cid").is_none() {
+        return Err(FieldError::InvalidParams("order_by".to_string()));
+    }
+
+    let mut posts = db::get_contents_with_metas_user_and_fields_by_filter_and_list_query(
+        &state, true, true, &user, page_size, page, &order_by, q.after, status, &range, true,
+    )
+    .await?;
+    let next_cursor = match q.after {
+        Some(_) if posts.len() as u64 == page_size => posts.last().map(|p| p.cid),
+        _ => None,
+    };
+
+    if q.excerpt_only.unwrap_or(false) {
+        for post in posts.iter_mut() {
+            post.text = None;
+        }
+    }
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": all_count,
+        "links": get_page_links(page, page_size, all_count),
+        "count": posts.len(),
+        "next_cursor": next_cursor,
+        "results": posts
+    })))
+}
+
+pub async fn search_posts(
+    State(state): State<Arc<AppState>>,
+    ValidatedQuery(q): ValidatedQuery<SearchQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let page = q.page.unwrap_or(1);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
+
+    let (rows, all_count) = db::search_posts(&state, &q.q, page_size, page).await?;
+    let results = rows
+        .into_iter()
+        .map(|row| PostSearchResult::from_row(row, &q.q))
+        .collect::<Vec<PostSearchResult>>();
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": all_count,
+        "count": results.len(),
+        "results": results
+    })))
+}
+
 pub async fn get_post_by_slug(
     State(state): State<Arc<AppState>>,
     PMVisitor(user): PMVisitor,
     Path(slug): Path<String>,
     ValidatedQuery(q): ValidatedQuery<PostQuery>,
+    headers: HeaderMap,
+) -> Result<Response, FieldError> {
+    let admin = user.group == "editor" || user.group == "administrator";
+    let private = q.private.unwrap_or(false) && admin;
+
+    let mut post =
+        db::get_content_with_metas_user_fields_by_slug_and_private(&state, &slug, private).await?;
+    if q.include.as_deref() == Some("author") {
+        db::attach_authors(&state, std::slice::from_mut(&mut post)).await?;
+    }
+    get_post_response(&state, &user, q, &headers, post)
+}
+
+/// Posts come back in the order of `slugs`, a slug that is missing or not visible to the
+/// user becomes null unless `omit_missing` is set. Protected text is hidden as in the list.
+pub async fn get_posts_by_slugs(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    ValidatedJson(posts_batch): ValidatedJson<PostsBatch>,
 ) -> Result<Json<Value>, FieldError> {
+    let admin = user.group == "editor" || user.group == "administrator";
+    let private = posts_batch.private.unwrap_or(false) && admin;
+
+    let posts = db::get_contents_with_metas_user_fields_by_slugs_and_private(
+        &state,
+        &posts_batch.slugs,
+        private,
+    )
+    .await?;
+    let mut found = HashMap::new();
+    for mut post in posts {
+        if post.is_password_protected() && !admin && post.author_id != user.uid {
+            post.hide_protected_content();
+        }
+        if let Some(slug) = post.slug.clone() {
+            found.insert(slug, json!(post));
+        }
+    }
+
+    let omit_missing = posts_batch.omit_missing.unwrap_or(false);
+    let results: Vec<Value> = posts_batch
+        .slugs
+        .iter()
+        .map(|slug| found.get(slug).cloned().unwrap_or(Value::Null))
+        .filter(|post| !(omit_missing && post.is_null()))
+        .collect();
+    Ok(Json(json!({
+        "count": results.len(),
+        "results": results
+    })))
+}
+
+pub async fn get_post_by_cid(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(cid): Path<u32>,
+    ValidatedQuery(q): ValidatedQuery<PostQuery>,
+    headers: HeaderMap,
+) -> Result<Response, FieldError> {
     let admin = user.group == "editor" || user.group == "administrator";
     let private = q.private.unwrap_or(false) && admin;
 
-    let post = db::get_content_with_metas_user_fields_by_slug_and_private(&state, &slug, private)
-        .await
-        .map_err(|_| FieldError::NotFound("slug".to_string()))?;
+    let mut post =
+        db::get_content_with_metas_user_fields_by_cid_and_private(&state, cid, private).await?;
+    if q.include.as_deref() == Some("author") {
+        db::attach_authors(&state, std::slice::from_mut(&mut post)).await?;
+    }
+    get_post_response(&state, &user, q, &headers, post)
+}
 
+fn get_post_response(
+    state: &AppState,
+    user: &User,
+    q: PostQuery,
+    headers: &HeaderMap,
+    mut post: ContentWithMetasUsersFields,
+) -> Result<Response, FieldError> {
+    let admin = user.group == "editor" || user.group == "administrator";
     let status = &post.status;
-    if admin || status == "publish" || status == "hidden" || status == "password" {
-        if post.password.is_none() {
-            return Ok(Json(json!(post)));
-        }
+    if !(admin || status == "publish" || status == "hidden" || status == "password") {
+        return Err(FieldError::PermissionDeny);
+    }
 
-        let password = post.password.clone().unwrap();
-        if let Some(query_password) = q.password {
-            if password == query_password {
-                return Ok(Json(json!(post)));
-            }
+    if post.is_password_protected() && !admin && post.author_id != user.uid {
+        if q.password.as_deref() == post.password.as_deref() {
+            post.password = None;
         } else {
-            return Err(FieldError::PasswordRequired);
+            post.hide_protected_content();
         }
-    } else {
-        return Err(FieldError::PermissionDeny);
     }
+    if !q.with_fields.unwrap_or(true) {
+        post.fields = vec![];
+    }
+    let modified = post.modified;
+    let rendered = match q.render.as_deref() {
+        Some("html") => Some(get_rendered_html(state, &post)),
+        _ => None,
+    };
+    let mut value = json!(post);
+    if let Some(rendered) = rendered {
+        value["content_html"] = json!(rendered.content);
+        value["excerpt_html"] = json!(rendered.excerpt);
+    }
+    Ok(get_conditional_response(headers, value, modified))
+}
+
+/// Hidden password protected text is rendered on the fly so the cache never holds it.
+fn get_rendered_html(state: &AppState, post: &ContentWithMetasUsersFields) -> RenderedHtml {
+    let text = post.text.as_deref().unwrap_or_default();
+    if post.protected {
+        return render_content_html(&state.html_policy, text);
+    }
+    if let Some(cached) = state.rendered_html.get(&post.cid) {
+        if cached.0 == post.modified {
+            return cached.1.clone();
+        }
+    }
+    let rendered = render_content_html(&state.html_policy, text);
+    state
+        .rendered_html
+        .insert(post.cid, (post.modified, rendered.clone()));
+    rendered
+}
+
+pub async fn list_related_posts_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(slug): Path<String>,
+    ValidatedQuery(q): ValidatedQuery<RelatedQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let post =
+        db::get_content_with_metas_user_fields_by_slug_and_private(&state, &slug, false).await?;
+
+    let limit = q.limit.unwrap_or(5);
+    let tag_mids = post.tags.iter().map(|m| m.mid).collect();
+    let category_mids = post.categories.iter().map(|m| m.mid).collect();
+    let mut posts =
+        db::get_related_posts_by_cid_and_metas(&state, post.cid, tag_mids, category_mids, limit)
+            .await?;
+
+    let admin = user.group == "editor" || user.group == "administrator";
+    for post in posts.iter_mut() {
+        if post.is_password_protected() && !admin && post.author_id != user.uid {
+            post.hide_protected_content();
+        }
+    }
+    Ok(Json(json!({
+        "count": posts.len(),
+        "results": posts,
+    })))
+}
+
+pub async fn get_post_siblings_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(slug): Path<String>,
+    ValidatedQuery(q): ValidatedQuery<SiblingsQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let post =
+        db::get_content_with_metas_user_fields_by_slug_and_private(&state, &slug, false).await?;
+
+    let category_mid = match q.category {
+        Some(category) => match common_db::get_meta_by_slug(&state, &category, false).await? {
+            Some(c) => Some(c.mid),
+            None => return Err(FieldError::NotFound("category".to_string())),
+        },
+        None => None,
+    };
 
-    Err(FieldError::NotFound("slug".to_string()))
+    let admin = user.group == "editor" || user.group == "administrator";
+    let mut siblings = vec![];
+    for newer in [false, true] {
+        let sibling = db::get_sibling_post_by_cid_and_created(
+            &state,
+            post.cid,
+            post.created,
+            category_mid,
+            newer,
+        )
+        .await?;
+        let sibling = match sibling.and_then(|s| s.slug) {
+            Some(slug) => {
+                let mut sibling = db::get_content_with_metas_user_fields_by_slug_and_private(
+                    &state, &slug, false,
+                )
+                .await?;
+                if sibling.is_password_protected() && !admin && sibling.author_id != user.uid {
+                    sibling.hide_protected_content();
+                }
+                Some(sibling)
+            }
+            None => None,
+        };
+        siblings.push(sibling);
+    }
+    Ok(Json(json!({
+        "prev": siblings[0],
+        "next": siblings[1],
+    })))
 }
 
 pub async fn delete_post_by_slug(
@@ -135,21 +631,130 @@ pub async fn delete_post_by_slug(
     if post.author_id != user.uid && !admin {
         return Err(FieldError::PermissionDeny);
     }
+    if post.status == "trash" {
+        return Err(FieldError::InvalidParams("status".to_string()));
+    }
 
-    let _ = common_db::delete_fields_by_cid(&state, post.cid).await?;
+    let _ = db::trash_post_by_exist_post(&state, &post).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn modify_post_status_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path(slug): Path<String>,
+    ValidatedJson(mut post_status): ValidatedJson<PostStatus>,
+) -> Result<Json<Value>, FieldError> {
+    let post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "post" => p,
+        _ => return Err(FieldError::NotFound("slug".to_string())),
+    };
+
+    let admin = user.group == "editor" || user.group == "administrator";
+    if post.author_id != user.uid && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+    if post.status == "trash" {
+        return Err(FieldError::InvalidParams("status".to_string()));
+    }
+
+    if user.group == "contributor" && post_status.status != "draft" {
+        post_status.status = String::from("waiting");
+    }
+
+    let post = db::update_post_status_by_exist_post(&state, &post, &post_status.status).await?;
+    Ok(Json(json!({ "status": post.status })))
+}
+
+pub async fn restore_post_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path(slug): Path<String>,
+) -> Result<Json<Value>, FieldError> {
+    let post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "post" => p,
+        _ => return Err(FieldError::NotFound("slug".to_string())),
+    };
 
-    let _ = common_db::delete_content_by_cid(&state, post.cid).await?;
+    let admin = user.group == "editor" || user.group == "administrator";
+    if post.author_id != user.uid && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+    if post.status != "trash" {
+        return Err(FieldError::InvalidParams("status".to_string()));
+    }
+
+    let _ = db::restore_post_by_exist_post(&state, &post).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
 
+pub async fn purge_post_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    Path(slug): Path<String>,
+) -> Result<Json<Value>, FieldError> {
+    let post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "post" => p,
+        _ => return Err(FieldError::NotFound("slug".to_string())),
+    };
+    if post.status != "trash" {
+        return Err(FieldError::InvalidParams("status".to_string()));
+    }
+
+    let _ = db::purge_post_by_exist_post(&state, &post).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn set_post_sticky(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(slug): Path<String>,
+    ValidatedJson(post_sticky): ValidatedJson<PostSticky>,
+) -> Result<Json<Value>, FieldError> {
+    let post = match common_db::get_content_by_slug(&state, &slug).await {
+        Ok(Some(p)) if p.r#type == "post" => p,
+        _ => return Err(FieldError::InvalidParams("slug".to_string())),
+    };
+
+    let order = match post_sticky.sticky {
+        true => post_sticky.order.unwrap_or(1),
+        false => 0,
+    };
+    let _ = db::update_post_order_by_cid(&state, post.cid, order).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn set_post_tags(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path(slug): Path<String>,
+    ValidatedJson(post_tags): ValidatedJson<PostTags>,
+) -> Result<Json<Value>, FieldError> {
+    let exist_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "post" => p,
+        _ => return Err(FieldError::NotFound("slug".to_owned())),
+    };
+
+    let admin = user.group == "editor" || user.group == "administrator";
+    if exist_post.author_id != user.uid && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+
+    let tags = db::set_post_tags_by_exist_post(&state, &exist_post, &post_tags.tags).await?;
+    Ok(Json(json!({
+        "count": tags.len(),
+        "results": tags,
+    })))
+}
+
 pub async fn create_post_field_by_slug(
     State(state): State<Arc<AppState>>,
     PMContributor(user): PMContributor,
     Path(slug): Path<String>,
     ValidatedJson(field_create): ValidatedJson<FieldCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
-    let exist_post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
@@ -157,23 +762,58 @@ pub async fn create_post_field_by_slug(
     if exist_post.author_id != user.uid && !admin {
         return Err(FieldError::PermissionDeny);
     }
+    if field_create.name == db::TRASH_STATUS_FIELD {
+        return Err(FieldError::InvalidParams("name".to_owned()));
+    }
 
     let _ = common_db::create_field_by_cid_with_field_create(&state, exist_post.cid, &field_create)
         .await?;
     Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
 }
 
+pub async fn list_post_fields_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(slug): Path<String>,
+) -> Result<Json<Value>, FieldError> {
+    let exist_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "post" => p,
+        _ => return Err(FieldError::NotFound("slug".to_owned())),
+    };
+
+    let admin = user.group == "editor" || user.group == "administrator";
+    if exist_post.status != "publish" && exist_post.author_id != user.uid && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+
+    let mut fields = common_db::get_fields_by_cid(&state, exist_post.cid).await?;
+    fields.retain(|f| f.name != db::TRASH_STATUS_FIELD);
+    Ok(Json(json!({
+        "count": fields.len(),
+        "results": fields,
+    })))
+}
+
 pub async fn get_post_field_by_slug_and_name(
     State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
     Path((slug, name)): Path<(String, String)>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) if p.r#type == "post" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
-    let field = match common_db::get_field_by_cid_and_name(&state, exist_post.cid, &name).await {
-        Ok(Some(f)) => f,
+    let admin = user.group == "editor" || user.group == "administrator";
+    if exist_post.status != "publish" && exist_post.author_id != user.uid && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+    if name == db::TRASH_STATUS_FIELD {
+        return Err(FieldError::NotFound("name".to_owned()));
+    }
+
+    let field = match common_db::get_field_by_cid_and_name(&state, exist_post.cid, &name).await? {
+        Some(f) => f,
         _ => return Err(FieldError::NotFound("name".to_owned())),
     };
     Ok(Json(json!(field)))
@@ -185,8 +825,8 @@ pub async fn modify_post_field_by_slug_and_name(
     Path((slug, name)): Path<(String, String)>,
     ValidatedJson(field_modify): ValidatedJson<FieldCreate>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
@@ -195,9 +835,13 @@ pub async fn modify_post_field_by_slug_and_name(
         return Err(FieldError::PermissionDeny);
     }
 
+    if name == db::TRASH_STATUS_FIELD || field_modify.name == db::TRASH_STATUS_FIELD {
+        return Err(FieldError::InvalidParams("name".to_owned()));
+    }
+
     if name != field_modify.name {
-        match common_db::get_field_by_cid_and_name(&state, exist_post.cid, &name).await {
-            Ok(Some(f)) => f,
+        match common_db::get_field_by_cid_and_name(&state, exist_post.cid, &name).await? {
+            Some(f) => f,
             _ => return Err(FieldError::NotFound("name".to_owned())),
         };
     }
@@ -217,8 +861,8 @@ pub async fn delete_post_field_by_slug_and_name(
     PMContributor(user): PMContributor,
     Path((slug, name)): Path<(String, String)>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+    let exist_post = match common_db::get_content_by_slug(&state, &slug).await? {
+        Some(p) => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
@@ -226,9 +870,12 @@ pub async fn delete_post_field_by_slug_and_name(
     if exist_post.author_id != user.uid && !admin {
         return Err(FieldError::PermissionDeny);
     }
+    if name == db::TRASH_STATUS_FIELD {
+        return Err(FieldError::NotFound("name".to_owned()));
+    }
 
-    match common_db::get_field_by_cid_and_name(&state, exist_post.cid, &name).await {
-        Ok(Some(f)) => f,
+    match common_db::get_field_by_cid_and_name(&state, exist_post.cid, &name).await? {
+        Some(f) => f,
         _ => return Err(FieldError::NotFound("name".to_owned())),
     };
 
@@ -0,0 +1,151 @@
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Json, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::{TimeZone, Utc};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use validator::Validate;
+
+use crate::allow::method_not_allowed;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::extractors::ValidatedQuery;
+use crate::AppState;
+
+/// The sitemap protocol caps a single sitemap at 50,000 URLs.
+pub const SITEMAP_SIZE: u64 = 50_000;
+
+const URLSET_TPL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+{%- for url in urls %}
+<url>
+<loc>{{ url.loc }}</loc>
+<lastmod>{{ url.lastmod }}</lastmod>
+</url>
+{%- endfor %}
+</urlset>
+"#;
+
+const INDEX_TPL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+{%- for loc in sitemaps %}
+<sitemap>
+<loc>{{ loc }}</loc>
+</sitemap>
+{%- endfor %}
+</sitemapindex>
+"#;
+
+#[derive(Serialize)]
+struct SitemapUrl {
+    loc: String,
+    lastmod: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SlugsQuery {
+    pub since: Option<u32>,
+}
+
+pub fn sitemap_routers() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/sitemap.xml",
+            get(sitemap).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/sitemaps/:page",
+            get(sitemap_by_page).fallback(method_not_allowed),
+        )
+        .route("/api/slugs", get(list_slugs).fallback(method_not_allowed))
+}
+
+fn xml_response(output: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        output,
+    )
+        .into_response()
+}
+
+async fn render_urlset(state: &AppState, page: u64) -> Result<Response, FieldError> {
+    let site_url = common_db::get_site_url(state).await?;
+    let contents = common_db::get_sitemap_contents(state, SITEMAP_SIZE, page).await?;
+
+    let mut urls = vec![];
+    for (content_type, slug, modified) in contents {
+        let lastmod = Utc
+            .timestamp_opt(modified as i64, 0)
+            .single()
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        urls.push(SitemapUrl {
+            loc: format!("{site_url}/{content_type}s/{}", slug.unwrap_or_default()),
+            lastmod,
+        });
+    }
+
+    let template = state
+        .jinja_env
+        .template_from_named_str("sitemap.xml", URLSET_TPL)
+        .map_err(|e| FieldError::DatabaseFailed(e.to_string()))?;
+    let output = template
+        .render(context! { urls => urls })
+        .map_err(|e| FieldError::DatabaseFailed(e.to_string()))?;
+    Ok(xml_response(output))
+}
+
+pub async fn sitemap(State(state): State<Arc<AppState>>) -> Result<Response, FieldError> {
+    let count = common_db::get_sitemap_contents_count(&state).await?;
+    if count <= SITEMAP_SIZE {
+        return render_urlset(&state, 1).await;
+    }
+
+    let site_url = common_db::get_site_url(&state).await?;
+    let pages = count.div_ceil(SITEMAP_SIZE);
+    let sitemaps: Vec<String> = (1..=pages)
+        .map(|page| format!("{site_url}/api/sitemaps/{page}"))
+        .collect();
+
+    let template = state
+        .jinja_env
+        .template_from_named_str("sitemap_index.xml", INDEX_TPL)
+        .map_err(|e| FieldError::DatabaseFailed(e.to_string()))?;
+    let output = template
+        .render(context! { sitemaps => sitemaps })
+        .map_err(|e| FieldError::DatabaseFailed(e.to_string()))?;
+    Ok(xml_response(output))
+}
+
+pub async fn sitemap_by_page(
+    State(state): State<Arc<AppState>>,
+    Path(page): Path<u64>,
+) -> Result<Response, FieldError> {
+    let count = common_db::get_sitemap_contents_count(&state).await?;
+    if page == 0 || page > count.div_ceil(SITEMAP_SIZE).max(1) {
+        return Err(FieldError::NotFound("page".to_string()));
+    }
+    render_urlset(&state, page).await
+}
+
+pub async fn list_slugs(
+    State(state): State<Arc<AppState>>,
+    ValidatedQuery(q): ValidatedQuery<SlugsQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let contents = common_db::get_public_slugs(&state, q.since).await?;
+
+    let mut posts = vec![];
+    let mut pages = vec![];
+    for (content_type, slug, modified) in contents {
+        let item = json!({ "slug": slug.unwrap_or_default(), "modified": modified });
+        match content_type.as_str() {
+            "page" => pages.push(item),
+            _ => posts.push(item),
+        }
+    }
+    Ok(Json(json!({ "posts": posts, "pages": pages })))
+}
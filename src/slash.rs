@@ -0,0 +1,30 @@
+use axum::extract::State;
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+
+use crate::preload;
+use crate::AppState;
+
+/// Redirects unmatched `/api/` paths without a trailing slash to the slashed form, so
+/// `/api/posts` reaches `/api/posts/`. 308 keeps the method and body for write requests.
+/// Only slashes are ever added, a path already ending with one is never redirected again.
+pub async fn redirect_trailing_slash(State(state): State<Arc<AppState>>, uri: Uri) -> Response {
+    let path = uri.path();
+    if path.starts_with("/api/") && !path.ends_with('/') {
+        let location = match uri.query() {
+            Some(query) => format!("{path}/?{query}"),
+            None => format!("{path}/"),
+        };
+        return (
+            StatusCode::PERMANENT_REDIRECT,
+            [(header::LOCATION, location)],
+        )
+            .into_response();
+    }
+
+    if state.preload_index {
+        return preload::index(State(state)).await.into_response();
+    }
+    StatusCode::NOT_FOUND.into_response()
+}
@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use axum::async_trait;
+
+const SPAM_KEYWORDS: [&str; 8] = [
+    "viagra",
+    "cialis",
+    "casino",
+    "porn",
+    "payday loan",
+    "crypto giveaway",
+    "buy followers",
+    "replica watches",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum SpamVerdict {
+    Ham,
+    Spam,
+}
+
+#[async_trait]
+pub trait SpamChecker: Send + Sync {
+    async fn check(&self, author: &str, mail: &str, text: &str, ip: &str) -> SpamVerdict;
+}
+
+pub struct DefaultSpamChecker {
+    max_links: usize,
+    duplicate_window: u64,
+    recent: Mutex<VecDeque<(u64, String)>>,
+}
+
+impl Default for DefaultSpamChecker {
+    fn default() -> Self {
+        Self {
+            max_links: 3,
+            duplicate_window: 600,
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl DefaultSpamChecker {
+    fn is_duplicate(&self, text: &str, now: u64) -> bool {
+        let mut recent = match self.recent.lock() {
+            Ok(r) => r,
+            Err(_) => return false,
+        };
+        while let Some((created, _)) = recent.front() {
+            if created + self.duplicate_window > now {
+                break;
+            }
+            recent.pop_front();
+        }
+
+        if recent.iter().any(|(_, t)| t == text) {
+            return true;
+        }
+        recent.push_back((now, text.to_string()));
+        false
+    }
+}
+
+#[async_trait]
+impl SpamChecker for DefaultSpamChecker {
+    async fn check(&self, author: &str, _mail: &str, text: &str, _ip: &str) -> SpamVerdict {
+        let text = text.trim().to_lowercase();
+
+        let links = text.matches("http://").count() + text.matches("https://").count();
+        if links > self.max_links {
+            return SpamVerdict::Spam;
+        }
+
+        let author = author.to_lowercase();
+        if SPAM_KEYWORDS
+            .iter()
+            .any(|k| text.contains(k) || author.contains(k))
+        {
+            return SpamVerdict::Spam;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        if self.is_duplicate(&text, now) {
+            return SpamVerdict::Spam;
+        }
+        SpamVerdict::Ham
+    }
+}
+
+#[tokio::test]
+async fn test_default_spam_checker() {
+    let checker = DefaultSpamChecker::default();
+    let ip = "127.0.0.1";
+    let mail = "test@local.host";
+
+    assert_eq!(
+        checker.check("author", mail, "nice post", ip).await,
+        SpamVerdict::Ham
+    );
+    assert_eq!(
+        checker.check("author", mail, "Nice post ", ip).await,
+        SpamVerdict::Spam
+    );
+    assert_eq!(
+        checker
+            .check("author", mail, "http://a http://b https://c https://d", ip)
+            .await,
+        SpamVerdict::Spam
+    );
+    assert_eq!(
+        checker.check("Casino King", mail, "hello", ip).await,
+        SpamVerdict::Spam
+    );
+}
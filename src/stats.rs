@@ -0,0 +1,62 @@
+use axum::extract::State;
+use axum::response::Json;
+use axum::routing::get;
+use axum::Router;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::allow::method_not_allowed;
+use crate::attachments::models::get_attachments_size;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::extractors::PMEditor;
+use crate::AppState;
+
+pub fn stats_routers() -> Router<Arc<AppState>> {
+    Router::new().route("/api/stats", get(get_stats).fallback(method_not_allowed))
+}
+
+pub async fn get_stats(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+) -> Result<Json<Value>, FieldError> {
+    let mut posts = Map::new();
+    let mut pages = Map::new();
+    for (content_type, status, count) in
+        common_db::get_contents_count_group_by_type_and_status(&state).await?
+    {
+        match content_type.as_str() {
+            "post" => posts.insert(status, json!(count)),
+            "page" => pages.insert(status, json!(count)),
+            _ => None,
+        };
+    }
+
+    let attachments = common_db::get_attachments(&state).await?;
+    let attachments_count = attachments.len();
+    let size = get_attachments_size(attachments);
+
+    let comments: Map<String, Value> = common_db::get_comments_count_group_by_status(&state)
+        .await?
+        .into_iter()
+        .map(|(status, count)| (status, json!(count)))
+        .collect();
+    let users: Map<String, Value> = common_db::get_users_count_group_by_group(&state)
+        .await?
+        .into_iter()
+        .map(|(group, count)| (group, json!(count)))
+        .collect();
+
+    Ok(Json(json!({
+        "posts": posts,
+        "pages": pages,
+        "attachments": {
+            "count": attachments_count,
+            "size": size,
+        },
+        "comments": comments,
+        "users": users,
+        "password_rehashes": state.password_rehashes.load(Ordering::Relaxed),
+    })))
+}
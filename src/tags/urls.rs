@@ -5,13 +5,23 @@ use axum::{
 use std::sync::Arc;
 
 use super::views;
+use crate::allow::method_not_allowed;
 use crate::AppState;
 
 pub fn tags_routers(ro: bool) -> Router<Arc<AppState>> {
     let tags_route = Router::new()
-        .route("/api/tags/", get(views::list_tags))
-        .route("/api/tags/:slug", get(views::get_tag_by_slug))
-        .route("/api/tags/:slug/posts/", get(views::list_tag_posts_by_slug));
+        .route(
+            "/api/tags/",
+            get(views::list_tags).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/tags/:slug",
+            get(views::get_tag_by_slug).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/tags/:slug/posts/",
+            get(views::list_tag_posts_by_slug).fallback(method_not_allowed),
+        );
     if !ro {
         tags_route
             .route("/api/tags/", post(views::create_tag))
@@ -20,7 +30,11 @@ pub fn tags_routers(ro: bool) -> Router<Arc<AppState>> {
             .route("/api/tags/:slug/posts/", post(views::add_post_to_tag))
             .route(
                 "/api/tags/:slug/posts/:post_slug",
-                delete(views::delete_post_from_tag),
+                delete(views::delete_post_from_tag).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/admin/metas/recount",
+                post(views::recount_metas).fallback(method_not_allowed),
             )
     } else {
         tags_route
@@ -8,8 +8,11 @@ use super::db::{self};
 use super::forms::{TagCreate, TagPostAdd};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
+use crate::common::extractors::{
+    PMAdministrator, PMEditor, PMVisitor, ValidatedJson, ValidatedQuery,
+};
 use crate::common::forms::ListQuery;
+use crate::common::utils::get_page_size;
 use crate::posts::forms::PostsQuery;
 use crate::AppState;
 
@@ -31,10 +34,10 @@ pub async fn list_tags(
     State(state): State<Arc<AppState>>,
     ValidatedQuery(q): ValidatedQuery<ListQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let all_count = common_db::get_metas_count(&state, true).await;
+    let all_count = common_db::get_metas_count(&state, true).await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-mid".to_string());
 
     let tags = common_db::get_metas_by_list_query(&state, page_size, page, &order_by, true).await?;
@@ -51,8 +54,8 @@ pub async fn get_tag_by_slug(
     State(state): State<Arc<AppState>>,
     Path(slug): Path<String>,
 ) -> Result<Json<Value>, FieldError> {
-    match common_db::get_meta_by_slug(&state, &slug, true).await {
-        Ok(Some(tag)) => Ok(Json(json!(tag))),
+    match common_db::get_meta_by_slug(&state, &slug, true).await? {
+        Some(tag) => Ok(Json(json!(tag))),
         _ => Err(FieldError::NotFound("slug".to_string())),
     }
 }
@@ -153,18 +156,19 @@ pub async fn list_tag_posts_by_slug(
     Path(slug): Path<String>,
     ValidatedQuery(q): ValidatedQuery<PostsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let mid = match common_db::get_meta_by_slug(&state, &slug, true).await {
-        Ok(Some(tag)) => tag.mid,
-        _ => return Err(FieldError::InvalidParams("slug".to_string())),
+    let mid = match common_db::get_meta_by_slug(&state, &slug, true).await? {
+        Some(tag) => tag.mid,
+        None => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
     let private =
         q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
 
-    let all_count = common_db::get_meta_posts_count_by_mid_with_private(&state, mid, private).await;
+    let all_count =
+        common_db::get_meta_posts_count_by_mid_with_private(&state, mid, private).await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size, state.max_page_size);
     let order_by = q.order_by.unwrap_or("-cid".to_string());
 
     let posts = common_db::get_contents_with_metas_user_and_fields_by_mid_list_query_and_private(
@@ -179,3 +183,12 @@ pub async fn list_tag_posts_by_slug(
         "results": posts
     })))
 }
+
+/// Repairs the `count` of every category and tag, covering both meta types at once.
+pub async fn recount_metas(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+) -> Result<Json<Value>, FieldError> {
+    let corrected = common_db::recount_metas(&state).await?;
+    Ok(Json(json!({ "corrected": corrected })))
+}
@@ -2,10 +2,14 @@ use std::time::SystemTime;
 
 use sea_orm::*;
 
-use super::forms::{OptionCreate, OptionModify, UserModify, UserRegister};
+use super::forms::{OptionCreate, OptionModify, UserCreate, UserModify, UserRegister};
 use super::utils::hash;
 use crate::common::errors::FieldError;
-use crate::entity::{option, option::Entity as UserOption, user, user::Entity as User};
+use crate::common::utils::{get_cursor_ascending, get_order_by};
+use crate::entity::{
+    comment, comment::Entity as Comment, content, content::Entity as Content, option,
+    option::Entity as UserOption, user, user::Entity as User,
+};
 use crate::AppState;
 
 pub async fn get_user_by_mail(
@@ -19,6 +23,17 @@ pub async fn get_user_by_mail(
         .map_err(|_| FieldError::InvalidParams("mail".to_string()))
 }
 
+pub async fn get_user_by_name(
+    state: &AppState,
+    name: &str,
+) -> Result<Option<user::Model>, FieldError> {
+    User::find()
+        .filter(user::Column::Name.eq(name))
+        .one(&state.conn)
+        .await
+        .map_err(|_| FieldError::InvalidParams("name".to_string()))
+}
+
 pub async fn get_user_by_uid(
     state: &AppState,
     uid: u32,
@@ -30,18 +45,76 @@ pub async fn get_user_by_uid(
         .map_err(|_| FieldError::InvalidParams("uid".to_string()))
 }
 
-pub async fn delete_user_by_uid(state: &AppState, uid: u32) -> Result<DeleteResult, FieldError> {
-    let u = get_user_by_uid(state, uid).await?;
+pub async fn delete_user_by_uid_with_reassign(
+    state: &AppState,
+    uid: u32,
+    reassign_to: Option<u32>,
+) -> Result<DeleteResult, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete user failed".to_string()))?;
+
+    let u = User::find()
+        .filter(user::Column::Uid.eq(uid))
+        .one(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete user failed".to_string()))?
+        .ok_or(FieldError::InvalidParams("uid".to_string()))?;
 
-    if u.is_none() {
-        return Err(FieldError::InvalidParams("uid".to_string()));
+    match reassign_to {
+        Some(reassign_to) => {
+            Content::update_many()
+                .col_expr(content::Column::AuthorId, sea_query::Expr::value(reassign_to))
+                .filter(content::Column::AuthorId.eq(uid))
+                .exec(&txn)
+                .await
+                .map_err(|_| FieldError::DatabaseFailed("reassign contents failed".to_string()))?;
+            Comment::update_many()
+                .col_expr(comment::Column::AuthorId, sea_query::Expr::value(reassign_to))
+                .filter(comment::Column::AuthorId.eq(uid))
+                .exec(&txn)
+                .await
+                .map_err(|_| FieldError::DatabaseFailed("reassign comments failed".to_string()))?;
+            Comment::update_many()
+                .col_expr(comment::Column::OwnerId, sea_query::Expr::value(reassign_to))
+                .filter(comment::Column::OwnerId.eq(uid))
+                .exec(&txn)
+                .await
+                .map_err(|_| FieldError::DatabaseFailed("reassign comments failed".to_string()))?;
+        }
+        None => {
+            let contents_count = Content::find()
+                .filter(content::Column::AuthorId.eq(uid))
+                .count(&txn)
+                .await
+                .map_err(|_| FieldError::DatabaseFailed("delete user failed".to_string()))?;
+            let comments_count = Comment::find()
+                .filter(comment::Column::AuthorId.eq(uid))
+                .count(&txn)
+                .await
+                .map_err(|_| FieldError::DatabaseFailed("delete user failed".to_string()))?;
+            if contents_count > 0 || comments_count > 0 {
+                return Err(FieldError::InvalidParams("reassign_to".to_string()));
+            }
+        }
     }
 
-    let u = u.unwrap();
+    UserOption::delete_many()
+        .filter(option::Column::User.eq(uid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete options failed".to_string()))?;
+    let res = u
+        .delete(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete user failed".to_string()))?;
 
-    u.delete(&state.conn)
+    txn.commit()
         .await
-        .map_err(|_| FieldError::DatabaseFailed("delete user failed".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("delete user failed".to_string()))?;
+    Ok(res)
 }
 
 pub async fn update_user_by_uid_for_activity(
@@ -100,12 +173,12 @@ pub async fn update_user_by_uid_for_password(
 pub async fn create_user_with_user_register(
     state: &AppState,
     user_register: &UserRegister,
-) -> Result<user::ActiveModel, FieldError> {
+) -> Result<user::Model, FieldError> {
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_secs() as u32;
-    let hashed_password = hash(&user_register.password);
+    let hashed_password = hash(state, &user_register.password).await?;
 
     user::ActiveModel {
         name: Set(Some(user_register.name.to_owned())),
@@ -117,13 +190,53 @@ pub async fn create_user_with_user_register(
         group: Set("subscriber".to_owned()),
         ..Default::default()
     }
-    .save(&state.conn)
+    .insert(&state.conn)
     .await
     .map_err(|_| FieldError::DatabaseFailed("create user failed".to_string()))
 }
 
-pub async fn get_users_count(state: &AppState) -> u64 {
-    User::find().count(&state.conn).await.unwrap_or(0)
+pub async fn create_user_with_user_create(
+    state: &AppState,
+    user_create: &UserCreate,
+) -> Result<user::Model, FieldError> {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32;
+    let hashed_password = hash(state, &user_create.password).await?;
+    let screen_name = user_create
+        .screenName
+        .clone()
+        .unwrap_or(user_create.name.to_owned());
+
+    user::ActiveModel {
+        name: Set(Some(user_create.name.to_owned())),
+        mail: Set(Some(user_create.mail.to_owned())),
+        url: Set(Some(user_create.url.to_owned())),
+        screen_name: Set(Some(screen_name)),
+        password: Set(Some(hashed_password.to_owned())),
+        created: Set(now),
+        group: Set(user_create.group.to_owned()),
+        ..Default::default()
+    }
+    .insert(&state.conn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create user failed".to_string()))
+}
+
+pub async fn get_users_count(state: &AppState) -> Result<u64, FieldError> {
+    User::find()
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count users failed".to_string()))
+}
+
+pub async fn get_users_count_by_group(state: &AppState, group: &str) -> Result<u64, FieldError> {
+    User::find()
+        .filter(user::Column::Group.eq(group))
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count users failed".to_string()))
 }
 
 pub async fn get_users_by_list_query(
@@ -131,19 +244,45 @@ pub async fn get_users_by_list_query(
     page_size: u64,
     page: u64,
     order_by: String,
+    after: Option<u32>,
 ) -> Result<(Vec<user::Model>, u64), FieldError> {
     let stmt = User::find();
-    let stmt = match order_by.as_str() {
-        "-uid" => stmt.order_by_desc(user::Column::Uid),
-        "uid" => stmt.order_by_asc(user::Column::Uid),
-        "-name" => stmt.order_by_desc(user::Column::Name),
-        "name" => stmt.order_by_asc(user::Column::Name),
-        "-mail" => stmt.order_by_desc(user::Column::Mail),
-        "mail" => stmt.order_by_asc(user::Column::Mail),
-        _ => stmt.order_by_asc(user::Column::Uid),
+    let stmt = match after {
+        None | Some(0) => stmt,
+        Some(after) if get_cursor_ascending(&order_by, "uid") == Some(true) => {
+            stmt.filter(user::Column::Uid.gt(after))
+        }
+        Some(after) => stmt.filter(user::Column::Uid.lt(after)),
     };
+    let (column, order) = get_order_by(
+        &order_by,
+        &[
+            ("uid", user::Column::Uid),
+            ("name", user::Column::Name),
+            ("mail", user::Column::Mail),
+        ],
+    )?;
+    // ties on the chosen column would shuffle rows between pages
+    let stmt = stmt
+        .order_by(column, order.clone())
+        .order_by(user::Column::Uid, order);
+    if after.is_some() {
+        return stmt
+            .limit(page_size)
+            .all(&state.conn)
+            .await
+            .map(|u| {
+                let count = u.len() as u64;
+                (u, count)
+            })
+            .map_err(|_| FieldError::DatabaseFailed("fetch user failed".to_string()));
+    }
+
     let paginator = stmt.paginate(&state.conn, page_size);
-    let num_pages = paginator.num_pages().await.unwrap_or(0);
+    let num_pages = paginator
+        .num_pages()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count users failed".to_string()))?;
 
     paginator
         .fetch_page(page - 1)
@@ -192,6 +331,24 @@ pub async fn create_option_by_uid_with_option_create(
     .map_err(|_| FieldError::DatabaseFailed("create option failed".to_string()))
 }
 
+pub async fn create_option_with_params(
+    state: &AppState,
+    uid: u32,
+    name: &str,
+    value: &str,
+) -> Result<InsertResult<option::ActiveModel>, FieldError> {
+    let opt = option::Model {
+        name: name.to_owned(),
+        user: uid,
+        value: Some(value.to_owned()),
+    };
+
+    UserOption::insert(opt.into_active_model())
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("create option failed".to_string()))
+}
+
 pub async fn modify_option_by_uid_and_name_with_option_modify(
     state: &AppState,
     uid: u32,
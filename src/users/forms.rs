@@ -1,11 +1,33 @@
 #![allow(non_snake_case)]
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+pub const USER_GROUPS: [&str; 4] = ["subscriber", "contributor", "editor", "administrator"];
+pub const API_TOKEN_SCOPES: [&str; 2] = ["read", "write"];
+
+fn validate_group(group: &str) -> Result<(), ValidationError> {
+    if USER_GROUPS.contains(&group) {
+        return Ok(());
+    }
+    Err(ValidationError::new("group").with_message(Cow::from("group is unknown")))
+}
+
+fn validate_scope(scope: &str) -> Result<(), ValidationError> {
+    if API_TOKEN_SCOPES.contains(&scope) {
+        return Ok(());
+    }
+    Err(ValidationError::new("scope").with_message(Cow::from("scope is unknown")))
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct TokenData {
     pub sub: String,
     pub exp: u64,
+    pub iat: u64,
+    pub nbf: u64,
+    pub jti: String,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -16,6 +38,32 @@ pub struct UserLogin {
     pub password: String,
 }
 
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TokenRefresh {
+    #[validate(length(min = 1, max = 150, message = "refresh_token length must greater than 1"))]
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PasswordResetRequest {
+    #[validate(email)]
+    pub mail: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PasswordResetConfirm {
+    #[validate(length(min = 1, max = 150, message = "token length must greater than 1"))]
+    pub token: String,
+    #[validate(length(min = 1, max = 150, message = "password can not be longer than 150"))]
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct AdminPasswordReset {
+    #[validate(length(min = 1, max = 150, message = "password can not be longer than 150"))]
+    pub password: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Validate)]
 pub struct UserRegister {
     #[validate(length(min = 1, max = 32, message = "name can not be longer than 32"))]
@@ -28,6 +76,22 @@ pub struct UserRegister {
     pub url: String,
 }
 
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UserCreate {
+    #[validate(length(min = 1, max = 32, message = "name can not be longer than 32"))]
+    pub name: String,
+    #[validate(length(min = 1, max = 32, message = "screenName can not be longer than 32"))]
+    pub screenName: Option<String>,
+    #[validate(email)]
+    pub mail: String,
+    #[validate(length(min = 1, max = 150, message = "password can not be longer than 150"))]
+    pub password: String,
+    #[validate(url)]
+    pub url: String,
+    #[validate(custom(function = "validate_group"))]
+    pub group: String,
+}
+
 #[derive(Serialize, Deserialize, Validate)]
 pub struct UserModify {
     #[validate(length(min = 1, max = 32, message = "name can not be longer than 32"))]
@@ -44,6 +108,24 @@ pub struct UserModify {
     pub group: String,
 }
 
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UserDelete {
+    #[validate(range(min = 1, message = "reassign_to must greater than 1"))]
+    pub reassign_to: Option<u32>,
+}
+
+pub const USER_FIELDS: [&str; 9] = [
+    "uid",
+    "name",
+    "mail",
+    "url",
+    "screen_name",
+    "created",
+    "activated",
+    "logged",
+    "group",
+];
+
 #[derive(Serialize, Deserialize, Validate)]
 pub struct UsersQuery {
     #[validate(range(min = 1, message = "page must greater than 1"))]
@@ -52,6 +134,33 @@ pub struct UsersQuery {
     pub page_size: Option<i32>,
     #[validate(length(min = 1, max = 13, message = "order_by length must greater than 1"))]
     pub order_by: Option<String>,
+    pub after: Option<u32>,
+    #[validate(length(min = 1, max = 200, message = "fields length must greater than 1"))]
+    pub fields: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UserQuery {
+    #[validate(length(min = 1, max = 200, message = "fields length must greater than 1"))]
+    pub fields: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ApiTokenCreate {
+    #[validate(length(min = 1, max = 64, message = "label length must between 1 and 64"))]
+    pub label: String,
+    #[validate(custom(function = "validate_scope"))]
+    pub scope: Option<String>,
+}
+
+/// Stored as the value of an `apiToken:` user option, only the hash of the secret is kept.
+#[derive(Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub hash: String,
+    pub label: String,
+    pub scope: Option<String>,
+    pub created: u64,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -6,22 +6,86 @@ use axum::{
 };
 
 use super::views;
+use crate::allow::method_not_allowed;
 use crate::AppState;
 
 pub fn users_routers(ro: bool) -> Router<Arc<AppState>> {
     let users_route = Router::new()
-        .route("/api/users/", get(views::list_users))
-        .route("/api/users/:uid", get(views::get_user_by_id))
-        .route("/api/users/:uid/options/", get(views::list_options))
-        .route("/api/users/:uid/options/:name", get(views::get_option_by_uid_and_name));
+        .route(
+            "/api/users/",
+            get(views::list_users).fallback(method_not_allowed),
+        )
+        .route("/api/me", get(views::get_me).fallback(method_not_allowed))
+        .route(
+            "/api/me/tokens",
+            get(views::list_api_tokens_for_me).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/users/:uid",
+            get(views::get_user_by_id).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/users/:uid/options/",
+            get(views::list_options).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/users/:uid/options/:name",
+            get(views::get_option_by_uid_and_name).fallback(method_not_allowed),
+        )
+        .route(
+            "/api/users/:uid/avatar",
+            get(views::get_avatar).fallback(method_not_allowed),
+        );
     if !ro {
         users_route
+            .route("/api/me/tokens", post(views::create_api_token_for_me))
+            .route(
+                "/api/me/tokens/:id",
+                delete(views::revoke_api_token_for_me).fallback(method_not_allowed),
+            )
             .route("/api/users/:uid", patch(views::modify_user_by_id))
             .route("/api/users/:uid", delete(views::delete_user_by_id))
-            .route("/api/users/:uid/options/", post(views::create_option_by_option_create))
-            .route("/api/users/:uid/options/:name", patch(views::modify_option_by_uid_and_name))
-            .route("/api/users/:uid/options/:name", delete(views::delete_option_by_uid_and_name))
-            .route("/api/users/token", post(views::login_for_access_token))
+            .route("/api/users/:uid/avatar", post(views::upload_avatar))
+            .route(
+                "/api/users/:uid/options/",
+                post(views::create_option_by_option_create),
+            )
+            .route(
+                "/api/users/:uid/options/:name",
+                patch(views::modify_option_by_uid_and_name),
+            )
+            .route(
+                "/api/users/:uid/options/:name",
+                delete(views::delete_option_by_uid_and_name),
+            )
+            .route(
+                "/api/admin/users/",
+                post(views::create_user).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/admin/users/:uid/password-reset",
+                post(views::admin_reset_password).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/users/token",
+                post(views::login_for_access_token).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/refresh",
+                post(views::refresh_access_token).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/logout",
+                post(views::logout).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/password-reset/request",
+                post(views::request_password_reset).fallback(method_not_allowed),
+            )
+            .route(
+                "/api/password-reset/confirm",
+                post(views::confirm_password_reset).fallback(method_not_allowed),
+            )
             .route("/api/users/", post(views::register))
     } else {
         users_route
@@ -1,20 +1,55 @@
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
 use axum::{http::request::Parts, RequestPartsExt};
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
 use hmac::{Hmac, Mac};
-use jwt::VerifyWithKey;
+use jwt::{SignWithKey, VerifyWithKey};
 use md5::{Digest, Md5};
-use rand::Rng;
+use rand::{distributions::Alphanumeric, Rng};
 use sha2::Sha256;
 
-use super::db::{get_user_by_mail, get_user_by_uid};
-use super::forms::{TokenData, UserLogin};
+use super::db::{
+    create_option_with_params, delete_option_by_uid_and_name, get_option_by_uid_and_name,
+    get_options_by_uid, get_user_by_mail, get_user_by_uid, update_user_by_uid_for_password,
+};
+use super::forms::{ApiToken, TokenData, UserLogin};
 use crate::entity::user::Model as User;
-use crate::common::errors::AuthError;
+use crate::attachments::utils::filename_is_valid;
+use crate::common::errors::{AuthError, FieldError};
 use crate::AppState;
 
+const REFRESH_TOKEN_PREFIX: &str = "refreshToken:";
+const API_TOKEN_PREFIX: &str = "apiToken:";
+const PASSWORD_RESET_PREFIX: &str = "passwordReset:";
+const PASSWORD_CHANGED_AT: &str = "passwordChangedAt";
+const TEMPORARY_PASSWORD_LENGTH: usize = 16;
+const TOKEN_LEEWAY: u64 = 60;
+pub const AVATAR_OPTION: &str = "avatar";
+pub const AVATAR_DIR: &str = "usr/avatars";
+
+/// Auth records and the avatar path kept in user options, the options API must neither
+/// expose nor change them.
+pub fn is_reserved_option(name: &str) -> bool {
+    // MySQL compares names case-insensitively and ignores trailing spaces
+    let name = name.trim_end().to_lowercase();
+    let prefixes = [
+        REFRESH_TOKEN_PREFIX,
+        API_TOKEN_PREFIX,
+        PASSWORD_RESET_PREFIX,
+    ];
+    let prefixed = prefixes.iter().any(|p| name.starts_with(&p.to_lowercase()));
+    let names = [PASSWORD_CHANGED_AT, AVATAR_OPTION];
+    prefixed || names.iter().any(|n| name == n.to_lowercase())
+}
+
 const ITOA64: [&str; 64] = [
     ".", "/", "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D", "E", "F", "G",
     "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z",
@@ -52,12 +87,6 @@ fn encode64(input: &[u8], count: usize) -> String {
     output
 }
 
-fn get_salt() -> String {
-    let seed: [u8; 6] = rand::thread_rng().gen();
-    let salt = encode64(&seed, 6);
-    salt
-}
-
 fn hash_password(password: &str, salt: &str) -> String {
     let password_bytes = password.as_bytes();
     let salt_bytes = salt.as_bytes();
@@ -78,7 +107,7 @@ fn hash_password(password: &str, salt: &str) -> String {
     hash_string
 }
 
-fn verify(plain_password: &str, hashed_password: &str) -> bool {
+fn verify_phpass(plain_password: &str, hashed_password: &str) -> bool {
     if hashed_password.len() < 12 {
         return false;
     }
@@ -87,44 +116,506 @@ fn verify(plain_password: &str, hashed_password: &str) -> bool {
     return hash == hashed_password[12..];
 }
 
-pub fn hash(password: &str) -> String {
-    let salt = get_salt();
-    let hashed_password = format!("$P$B{}{}", salt, hash_password(password, &salt));
-    hashed_password
+fn get_argon2(memory_cost: u32, time_cost: u32, parallelism: u32) -> Option<Argon2<'static>> {
+    let params = Params::new(memory_cost, time_cost, parallelism, None).ok()?;
+    Some(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+/// Argon2 is slow on purpose, so hashing and verifying run on the blocking pool.
+pub async fn hash(state: &AppState, password: &str) -> Result<String, FieldError> {
+    let (m_cost, t_cost, p_cost) = (
+        state.argon2_memory_cost,
+        state.argon2_time_cost,
+        state.argon2_parallelism,
+    );
+    let password = password.to_string();
+    let hashed = tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        get_argon2(m_cost, t_cost, p_cost)?
+            .hash_password(password.as_bytes(), &salt)
+            .ok()
+            .map(|h| h.to_string())
+    })
+    .await;
+    let failed = FieldError::DatabaseFailed("hash password failed".to_string());
+    hashed.ok().flatten().ok_or(failed)
+}
+
+pub async fn verify_password(
+    state: &AppState,
+    plain_password: &str,
+    hashed_password: &str,
+) -> bool {
+    if !hashed_password.starts_with("$argon2") {
+        return verify_phpass(plain_password, hashed_password);
+    }
+
+    let (m_cost, t_cost, p_cost) = (
+        state.argon2_memory_cost,
+        state.argon2_time_cost,
+        state.argon2_parallelism,
+    );
+    let plain_password = plain_password.to_string();
+    let hashed_password = hashed_password.to_string();
+    tokio::task::spawn_blocking(move || {
+        let parsed_hash = PasswordHash::new(&hashed_password).ok()?;
+        let valid = get_argon2(m_cost, t_cost, p_cost)?
+            .verify_password(plain_password.as_bytes(), &parsed_hash)
+            .is_ok();
+        Some(valid)
+    })
+    .await
+    .ok()
+    .flatten()
+    .unwrap_or(false)
+}
+
+/// Legacy phpass hashes and argon2id hashes made with other costs than configured are
+/// upgraded on the next successful login.
+fn needs_rehash(hashed_password: &str, memory_cost: u32, time_cost: u32, parallelism: u32) -> bool {
+    if !hashed_password.starts_with("$argon2id$") {
+        return true;
+    }
+    match PasswordHash::new(hashed_password).map(|h| Params::try_from(&h)) {
+        Ok(Ok(params)) => {
+            params.m_cost() != memory_cost
+                || params.t_cost() != time_cost
+                || params.p_cost() != parallelism
+        }
+        _ => true,
+    }
 }
 
 pub async fn authenticate_user(state: &AppState, user_login: &UserLogin) -> Option<User> {
     if let Ok(Some(user)) = get_user_by_mail(&state, &user_login.mail).await {
         let user_password = user.password.clone().unwrap_or(String::from(""));
-        let valid = verify(&user_login.password, &user_password);
+        let valid = verify_password(state, &user_login.password, &user_password).await;
         if valid {
+            if needs_rehash(
+                &user_password,
+                state.argon2_memory_cost,
+                state.argon2_time_cost,
+                state.argon2_parallelism,
+            ) {
+                if let Ok(hashed_password) = hash(state, &user_login.password).await {
+                    if update_user_by_uid_for_password(state, user.uid, &hashed_password)
+                        .await
+                        .is_ok()
+                    {
+                        state.password_rehashes.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
             return Some(user);
         }
     }
     None
 }
 
-pub async fn get_user(parts: &mut Parts, state: AppState) -> Result<User, AuthError> {
-    let TypedHeader(Authorization(bearer)) = parts
-        .extract::<TypedHeader<Authorization<Bearer>>>()
-        .await
-        .map_err(|_| AuthError::InvalidToken)?;
-
+pub fn verify_access_token(state: &AppState, token: &str) -> Result<TokenData, AuthError> {
     let secret_key = &state.secret_key;
 
     let key: Hmac<Sha256> =
         Hmac::new_from_slice(secret_key.as_bytes()).map_err(|_| AuthError::InvalidToken)?;
-    let token_data: TokenData = bearer
-        .token()
+    let token_data: TokenData = token
         .verify_with_key(&key)
         .map_err(|_| AuthError::InvalidToken)?;
 
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if token_data.exp <= now
+        || token_data.nbf > now + TOKEN_LEEWAY
+        || token_data.iat > now + TOKEN_LEEWAY
+    {
+        return Err(AuthError::InvalidToken);
+    }
+
+    let revoked_tokens = state
+        .revoked_tokens
+        .lock()
+        .map_err(|_| AuthError::InvalidToken)?;
+    if revoked_tokens.contains_key(&token_data.jti) {
+        return Err(AuthError::InvalidToken);
+    }
+    Ok(token_data)
+}
+
+pub fn revoke_access_token(state: &AppState, token_data: &TokenData, now: u64) {
+    if let Ok(mut revoked_tokens) = state.revoked_tokens.lock() {
+        revoked_tokens.retain(|_, exp| *exp > now);
+        revoked_tokens.insert(token_data.jti.to_owned(), token_data.exp);
+    }
+}
+
+pub fn prune_revoked_tokens(state: &AppState, now: u64) {
+    if let Ok(mut revoked_tokens) = state.revoked_tokens.lock() {
+        revoked_tokens.retain(|_, exp| *exp > now);
+    }
+}
+
+pub async fn get_user(parts: &mut Parts, state: AppState) -> Result<User, AuthError> {
+    let TypedHeader(Authorization(bearer)) = parts
+        .extract::<TypedHeader<Authorization<Bearer>>>()
+        .await
+        .map_err(|_| AuthError::InvalidToken)?;
+    get_user_by_token(&state, bearer.token(), parts.method.is_safe()).await
+}
+
+/// Resolves an access token or personal access token, `safe` tells whether the request only
+/// reads. Long-lived responses call this again to notice expiry, logout and demotion.
+pub async fn get_user_by_token(
+    state: &AppState,
+    token: &str,
+    safe: bool,
+) -> Result<User, AuthError> {
+    let token_data = match verify_access_token(state, token) {
+        Ok(token_data) => token_data,
+        Err(e) => {
+            let (user, scope) = verify_api_token(state, token).await.ok_or(e)?;
+            if scope.as_deref() == Some("read") && !safe {
+                return Err(AuthError::PermissionDeny);
+            }
+            return Ok(user);
+        }
+    };
+
     let user_id = token_data
         .sub
         .parse::<u32>()
         .map_err(|_| AuthError::InvalidToken)?;
-    if let Ok(Some(user)) = get_user_by_uid(&state, user_id).await {
+    if let Ok(Some(user)) = get_user_by_uid(state, user_id).await {
+        if token_data.iat < get_password_changed_at(state, user.uid).await {
+            return Err(AuthError::InvalidToken);
+        }
         return Ok(user);
     }
     Err(AuthError::InvalidToken)
 }
+
+/// Avatar paths live in user options, which users can edit, so only trust our own directory.
+pub fn is_avatar_path(path: &str, uid: u32) -> bool {
+    path.strip_prefix(&format!("/{AVATAR_DIR}/"))
+        .is_some_and(|name| filename_is_valid(name) && name.starts_with(&format!("{uid}-")))
+}
+
+async fn get_password_changed_at(state: &AppState, uid: u32) -> u64 {
+    match get_option_by_uid_and_name(state, uid, PASSWORD_CHANGED_AT).await {
+        Ok(Some(option)) => option.value.and_then(|v| v.parse().ok()).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Rejects access tokens issued before now and drops refresh and personal access tokens,
+/// forcing a new login.
+pub async fn invalidate_user_tokens(
+    state: &AppState,
+    uid: u32,
+    now: u64,
+) -> Result<(), FieldError> {
+    if let Ok(options) = get_options_by_uid(state, uid).await {
+        for option in options {
+            let is_token = option.name.starts_with(REFRESH_TOKEN_PREFIX)
+                || option.name.starts_with(API_TOKEN_PREFIX);
+            if is_token || option.name == PASSWORD_CHANGED_AT {
+                let _ = delete_option_by_uid_and_name(state, uid, &option.name).await;
+            }
+        }
+    }
+    create_option_with_params(state, uid, PASSWORD_CHANGED_AT, &now.to_string()).await?;
+    Ok(())
+}
+
+pub fn is_login_throttled(state: &AppState, keys: &[String], now: u64) -> bool {
+    keys.iter().any(|key| match state.login_attempts.get(key) {
+        Some(attempts) => {
+            let (count, start) = *attempts;
+            now < start + state.login_attempts_window && count >= state.login_attempts_limit
+        }
+        None => false,
+    })
+}
+
+pub fn record_login_failure(state: &AppState, keys: &[String], now: u64) {
+    for key in keys {
+        let mut attempts = state
+            .login_attempts
+            .entry(key.to_owned())
+            .or_insert((0, now));
+        if now >= attempts.1 + state.login_attempts_window {
+            *attempts = (0, now);
+        }
+        attempts.0 += 1;
+    }
+}
+
+pub fn reset_login_attempts(state: &AppState, key: &str) {
+    state.login_attempts.remove(key);
+}
+
+/// Drops counters whose window has passed, the keys are chosen by clients and would pile up.
+pub fn prune_login_attempts(state: &AppState, now: u64) {
+    state
+        .login_attempts
+        .retain(|_, (_, start)| now < *start + state.login_attempts_window);
+}
+
+/// One-time password handed out by an administrator reset, shown only in that response.
+pub fn generate_temporary_password() -> String {
+    random_string(TEMPORARY_PASSWORD_LENGTH)
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Seconds since the epoch for issuing tokens, a clock before 1970 fails instead of panicking.
+pub fn current_timestamp() -> Result<u64, AuthError> {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .map_err(|_| AuthError::TokenCreationFailed)
+}
+
+pub fn create_access_token(state: &AppState, uid: u32, now: u64) -> Result<String, AuthError> {
+    let key: Hmac<Sha256> = Hmac::new_from_slice(state.secret_key.as_bytes())
+        .map_err(|_| AuthError::TokenCreationFailed)?;
+    let token_data = TokenData {
+        sub: format!("{}", uid),
+        exp: now + state.access_token_expire_secondes,
+        iat: now,
+        nbf: now,
+        jti: random_string(16),
+    };
+    token_data
+        .sign_with_key(&key)
+        .map_err(|_| AuthError::TokenCreationFailed)
+}
+
+async fn create_option_token(
+    state: &AppState,
+    uid: u32,
+    prefix: &str,
+    expire: u64,
+    now: u64,
+) -> Result<String, FieldError> {
+    if let Ok(options) = get_options_by_uid(state, uid).await {
+        for option in options {
+            if let Some(value) = option.name.strip_prefix(prefix).and(option.value) {
+                let exp = value
+                    .rsplit(':')
+                    .next()
+                    .and_then(|e| e.parse::<u64>().ok())
+                    .unwrap_or(0);
+                if exp <= now {
+                    let _ = delete_option_by_uid_and_name(state, uid, &option.name).await;
+                }
+            }
+        }
+    }
+
+    let id = random_string(16);
+    let secret = random_string(32);
+    let value = format!("{}:{}", hash_token(&secret), now + expire);
+    create_option_with_params(state, uid, &format!("{prefix}{id}"), &value).await?;
+
+    Ok(format!("{uid}.{id}.{secret}"))
+}
+
+/// Checks and deletes a single-use token, only the request whose delete removes the row
+/// accepts it, so concurrent requests with the same token can not both pass.
+async fn consume_option_token(
+    state: &AppState,
+    prefix: &str,
+    token: &str,
+    now: u64,
+) -> Option<u32> {
+    let mut parts = token.splitn(3, '.');
+    let (uid, id, secret) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(uid), Some(id), Some(secret)) => (uid, id, secret),
+        _ => return None,
+    };
+    let uid = uid.parse::<u32>().ok()?;
+    let name = format!("{prefix}{id}");
+
+    let value = match get_option_by_uid_and_name(state, uid, &name).await {
+        Ok(Some(option)) => option.value.unwrap_or_default(),
+        _ => return None,
+    };
+    let (hashed_secret, exp) = value.rsplit_once(':')?;
+    let exp = exp.parse::<u64>().ok()?;
+    if hashed_secret != hash_token(secret) {
+        return None;
+    }
+    if exp <= now {
+        let _ = delete_option_by_uid_and_name(state, uid, &name).await;
+        return None;
+    }
+    match delete_option_by_uid_and_name(state, uid, &name).await {
+        Ok(res) if res.rows_affected == 1 => Some(uid),
+        _ => None,
+    }
+}
+
+pub async fn create_refresh_token(
+    state: &AppState,
+    uid: u32,
+    now: u64,
+) -> Result<String, AuthError> {
+    create_option_token(
+        state,
+        uid,
+        REFRESH_TOKEN_PREFIX,
+        state.refresh_token_expire_secondes,
+        now,
+    )
+    .await
+    .map_err(|_| AuthError::TokenCreationFailed)
+}
+
+pub async fn rotate_refresh_token(
+    state: &AppState,
+    refresh_token: &str,
+    now: u64,
+) -> Result<(User, String), AuthError> {
+    let uid = consume_option_token(state, REFRESH_TOKEN_PREFIX, refresh_token, now)
+        .await
+        .ok_or(AuthError::InvalidToken)?;
+
+    let user = match get_user_by_uid(state, uid).await {
+        Ok(Some(user)) => user,
+        _ => return Err(AuthError::InvalidToken),
+    };
+    let refresh_token = create_refresh_token(state, uid, now).await?;
+    Ok((user, refresh_token))
+}
+
+pub async fn create_password_reset_token(
+    state: &AppState,
+    uid: u32,
+    now: u64,
+) -> Result<String, FieldError> {
+    create_option_token(
+        state,
+        uid,
+        PASSWORD_RESET_PREFIX,
+        state.password_reset_expire_secondes,
+        now,
+    )
+    .await
+}
+
+pub async fn consume_password_reset_token(state: &AppState, token: &str, now: u64) -> Option<u32> {
+    consume_option_token(state, PASSWORD_RESET_PREFIX, token, now).await
+}
+
+/// Personal access tokens never expire, they last until revoked.
+pub async fn create_api_token(
+    state: &AppState,
+    uid: u32,
+    label: &str,
+    scope: Option<String>,
+    now: u64,
+) -> Result<(String, ApiToken), FieldError> {
+    let id = random_string(16);
+    let secret = random_string(32);
+    let api_token = ApiToken {
+        id: id.clone(),
+        hash: hash_token(&secret),
+        label: label.to_string(),
+        scope,
+        created: now,
+    };
+    let value = serde_json::to_string(&api_token)
+        .map_err(|_| FieldError::InvalidParams("token".to_string()))?;
+    create_option_with_params(state, uid, &format!("{API_TOKEN_PREFIX}{id}"), &value).await?;
+
+    Ok((format!("{uid}.{id}.{secret}"), api_token))
+}
+
+pub async fn list_api_tokens(state: &AppState, uid: u32) -> Result<Vec<ApiToken>, FieldError> {
+    let options = get_options_by_uid(state, uid).await?;
+    Ok(options
+        .into_iter()
+        .filter(|o| o.name.starts_with(API_TOKEN_PREFIX))
+        .filter_map(|o| serde_json::from_str(&o.value.unwrap_or_default()).ok())
+        .collect())
+}
+
+pub async fn revoke_api_token(state: &AppState, uid: u32, id: &str) -> Result<(), FieldError> {
+    let name = format!("{API_TOKEN_PREFIX}{id}");
+    let option = get_option_by_uid_and_name(state, uid, &name).await?;
+    if option.is_none() {
+        return Err(FieldError::NotFound("id".to_string()));
+    }
+    delete_option_by_uid_and_name(state, uid, &name).await?;
+    Ok(())
+}
+
+async fn verify_api_token(state: &AppState, token: &str) -> Option<(User, Option<String>)> {
+    let mut parts = token.splitn(3, '.');
+    let (uid, id, secret) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(uid), Some(id), Some(secret)) => (uid, id, secret),
+        _ => return None,
+    };
+    let uid = uid.parse::<u32>().ok()?;
+
+    let name = format!("{API_TOKEN_PREFIX}{id}");
+    let value = match get_option_by_uid_and_name(state, uid, &name).await {
+        Ok(Some(option)) => option.value.unwrap_or_default(),
+        _ => return None,
+    };
+    let api_token: ApiToken = serde_json::from_str(&value).ok()?;
+    if api_token.hash != hash_token(secret) {
+        return None;
+    }
+
+    match get_user_by_uid(state, uid).await {
+        Ok(Some(user)) => Some((user, api_token.scope)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_needs_rehash() {
+    let params = Params::new(1024, 1, 1, None).unwrap();
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let salt = SaltString::generate(&mut OsRng);
+    let hashed = argon2
+        .hash_password(b"password", &salt)
+        .unwrap()
+        .to_string();
+
+    assert!(!needs_rehash(&hashed, 1024, 1, 1));
+    assert!(needs_rehash(&hashed, 4096, 1, 1));
+    assert!(needs_rehash(&hashed, 1024, 2, 1));
+    assert!(needs_rehash("$P$B12345678abcdefghijklmno", 1024, 1, 1));
+    assert!(needs_rehash("$argon2id$broken", 1024, 1, 1));
+}
+
+#[test]
+fn test_is_reserved_option() {
+    assert!(is_reserved_option("refreshToken:abc"));
+    assert!(is_reserved_option("ApiToken:1"));
+    assert!(is_reserved_option("passwordReset:abc"));
+    assert!(is_reserved_option("passwordchangedat "));
+    assert!(is_reserved_option("Avatar"));
+    assert!(!is_reserved_option("passwordChangedAtNote"));
+}
+
+#[test]
+fn test_is_avatar_path() {
+    assert!(is_avatar_path("/usr/avatars/1-1234567890.png", 1));
+    assert!(!is_avatar_path("/usr/avatars/2-1234567890.png", 1));
+    assert!(!is_avatar_path("/usr/avatars/12-1234567890.png", 1));
+    assert!(!is_avatar_path("/usr/uploads/1-1234567890.png", 1));
+}
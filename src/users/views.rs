@@ -1,54 +1,209 @@
 use std::sync::Arc;
-use std::time::SystemTime;
 
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
-use axum::response::Json;
-use hmac::{Hmac, Mac};
-use jwt::SignWithKey;
+use axum::extract::{Multipart, Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use rand::Rng;
 use serde_json::{json, Value};
-use sha2::Sha256;
 
 use super::db;
 use super::forms::{
-    OptionCreate, OptionModify, TokenData, UserLogin, UserModify, UserRegister, UsersQuery,
+    AdminPasswordReset, ApiToken, ApiTokenCreate, OptionCreate, OptionModify, PasswordResetConfirm,
+    PasswordResetRequest, TokenRefresh, UserCreate, UserDelete, UserLogin, UserModify, UserQuery,
+    UserRegister, UsersQuery, USER_FIELDS,
+};
+use super::utils::{
+    authenticate_user, consume_password_reset_token, create_access_token, create_api_token,
+    create_password_reset_token, create_refresh_token, current_timestamp,
+    generate_temporary_password, hash, invalidate_user_tokens, is_avatar_path, is_login_throttled,
+    is_reserved_option, list_api_tokens, record_login_failure, reset_login_attempts,
+    revoke_access_token, revoke_api_token, rotate_refresh_token, verify_access_token, AVATAR_DIR,
+    AVATAR_OPTION,
 };
-use super::utils::{authenticate_user, hash};
+use crate::attachments::utils::{delete_file, get_image_ext, get_image_mime, stream_to_file};
 use crate::common::errors::{AuthError, FieldError};
-use crate::common::extractors::{PMAdministrator, PMSubscriber, ValidatedJson, ValidatedQuery};
+use crate::common::extractors::{
+    ClientIp, PMAdministrator, PMSubscriber, ValidatedJson, ValidatedQuery,
+};
+use crate::common::utils::{
+    get_cursor_ascending, get_fields, get_page_links, get_page_size, select_fields,
+};
 use crate::AppState;
 
 pub async fn login_for_access_token(
     State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
     ValidatedJson(user_login): ValidatedJson<UserLogin>,
 ) -> Result<Json<Value>, AuthError> {
+    let now = current_timestamp()?;
+    let mail_key = format!("mail:{}", user_login.mail);
+    let mut keys = vec![mail_key.clone()];
+    if let Some(ip) = ip {
+        keys.push(format!("ip:{}", ip));
+    }
+    if is_login_throttled(&state, &keys, now) {
+        return Err(AuthError::TooManyAttempts);
+    }
+
     if let Some(user) = authenticate_user(&state, &user_login).await {
-        let key: Hmac<Sha256> = Hmac::new_from_slice(state.secret_key.as_bytes()).unwrap();
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let token_data = TokenData {
-            sub: format!("{}", user.uid),
-            exp: now + state.access_token_expire_secondes,
-        };
-        let access_token = token_data.sign_with_key(&key).unwrap();
+        reset_login_attempts(&state, &mail_key);
+        let access_token = create_access_token(&state, user.uid, now)?;
+        let refresh_token = create_refresh_token(&state, user.uid, now).await?;
 
         let _ = db::update_user_by_uid_for_activity(&state, user.uid, now as u32).await;
 
-        return Ok(Json(
-            json!({"access_token": access_token, "token_type": "Bearer"}),
-        ));
+        return Ok(Json(json!({
+            "access_token": access_token,
+            "token_type": "Bearer",
+            "refresh_token": refresh_token,
+            "uid": user.uid,
+            "screenName": user.screen_name,
+            "group": user.group
+        })));
     }
+    record_login_failure(&state, &keys, now);
     Err(AuthError::WrongCredentials)
 }
 
+pub async fn refresh_access_token(
+    State(state): State<Arc<AppState>>,
+    ValidatedJson(token_refresh): ValidatedJson<TokenRefresh>,
+) -> Result<Json<Value>, AuthError> {
+    let now = current_timestamp()?;
+    let (user, refresh_token) =
+        rotate_refresh_token(&state, &token_refresh.refresh_token, now).await?;
+    let access_token = create_access_token(&state, user.uid, now)?;
+
+    Ok(Json(json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "refresh_token": refresh_token
+    })))
+}
+
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Value>, AuthError> {
+    let token_data = verify_access_token(&state, bearer.token())?;
+    let now = current_timestamp()?;
+    revoke_access_token(&state, &token_data, now);
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn request_password_reset(
+    State(state): State<Arc<AppState>>,
+    ValidatedJson(password_reset): ValidatedJson<PasswordResetRequest>,
+) -> Result<Json<Value>, FieldError> {
+    if let Ok(Some(user)) = db::get_user_by_mail(&state, &password_reset.mail).await {
+        let now = current_timestamp()
+            .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?;
+        if let Ok(token) = create_password_reset_token(&state, user.uid, now).await {
+            let body = format!("Your password reset token is: {}", token);
+            let _ = state
+                .mailer
+                .send(&password_reset.mail, "Password reset", &body)
+                .await;
+        }
+    }
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn confirm_password_reset(
+    State(state): State<Arc<AppState>>,
+    ValidatedJson(password_reset): ValidatedJson<PasswordResetConfirm>,
+) -> Result<Json<Value>, FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?;
+    let uid = consume_password_reset_token(&state, &password_reset.token, now)
+        .await
+        .ok_or(FieldError::InvalidParams("token".to_string()))?;
+
+    let hashed_password = hash(&state, &password_reset.password).await?;
+    let user = db::update_user_by_uid_for_password(&state, uid, &hashed_password).await?;
+    invalidate_user_tokens(&state, user.uid, now).await?;
+    Ok(Json(json!({
+        "msg": format!("{} password changed", user.uid)
+    })))
+}
+
+pub async fn admin_reset_password(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    Path(uid): Path<u32>,
+    ValidatedJson(password_reset): ValidatedJson<AdminPasswordReset>,
+) -> Result<Json<Value>, FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?;
+    let (password, temporary) = match password_reset.password {
+        Some(password) => (password, false),
+        None => (generate_temporary_password(), true),
+    };
+
+    let hashed_password = hash(&state, &password).await?;
+    let user = db::update_user_by_uid_for_password(&state, uid, &hashed_password).await?;
+    invalidate_user_tokens(&state, user.uid, now).await?;
+
+    let mut res = json!({
+        "msg": format!("{} password reset", user.uid)
+    });
+    if temporary {
+        res["password"] = json!(password);
+    }
+    Ok(Json(res))
+}
+
+async fn check_name_and_mail_available(
+    state: &AppState,
+    name: &str,
+    mail: &str,
+) -> Result<(), FieldError> {
+    if db::get_user_by_name(state, name).await?.is_some() {
+        return Err(FieldError::AlreadyExist("name".to_string()));
+    }
+    if db::get_user_by_mail(state, mail).await?.is_some() {
+        return Err(FieldError::AlreadyExist("mail".to_string()));
+    }
+    Ok(())
+}
+
 pub async fn register(
     State(state): State<Arc<AppState>>,
     ValidatedJson(user_register): ValidatedJson<UserRegister>,
-) -> Result<(StatusCode, Json<Value>), FieldError> {
-    let _ = db::create_user_with_user_register(&state, &user_register).await?;
-    return Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))));
+) -> Result<Response, FieldError> {
+    check_name_and_mail_available(&state, &user_register.name, &user_register.mail).await?;
+
+    let user = db::create_user_with_user_register(&state, &user_register).await?;
+
+    let location = format!("/api/users/{}", user.uid);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(json!(user)),
+    )
+        .into_response())
+}
+
+pub async fn create_user(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    ValidatedJson(user_create): ValidatedJson<UserCreate>,
+) -> Result<Response, FieldError> {
+    check_name_and_mail_available(&state, &user_create.name, &user_create.mail).await?;
+
+    let user = db::create_user_with_user_create(&state, &user_create).await?;
+
+    let location = format!("/api/users/{}", user.uid);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(json!(user)),
+    )
+        .into_response())
 }
 
 pub async fn list_users(
@@ -56,36 +211,121 @@ pub async fn list_users(
     PMAdministrator(_): PMAdministrator,
     ValidatedQuery(q): ValidatedQuery<UsersQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let all_count = db::get_users_count(&state).await;
+    let fields = match q.fields {
+        Some(ref fields) => Some(get_fields(fields, &USER_FIELDS)?),
+        None => None,
+    };
+    let all_count = db::get_users_count(&state).await?;
 
     let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
+    let page_size = get_page_size(q.page_size.map(|s| s as u64), state.max_page_size) as i32;
     let order_by = q.order_by.unwrap_or("-uid".to_string());
+    if q.after.is_some() && get_cursor_ascending(&order_by, "uid").is_none() {
+        return Err(FieldError::InvalidParams("order_by".to_string()));
+    }
 
     let (users, count) =
-        db::get_users_by_list_query(&state, page_size as u64, page as u64, order_by).await?;
+        db::get_users_by_list_query(&state, page_size as u64, page as u64, order_by, q.after)
+            .await?;
+    let next_cursor = match q.after {
+        Some(_) if users.len() as i32 == page_size => users.last().map(|u| u.uid),
+        _ => None,
+    };
+    let results: Vec<Value> = users
+        .into_iter()
+        .map(|u| match fields {
+            Some(ref fields) => select_fields(json!(u), fields),
+            None => json!(u),
+        })
+        .collect();
     Ok(Json(json!({
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
+        "links": get_page_links(page as u64, page_size as u64, all_count),
         "count": count,
-        "results": users
+        "next_cursor": next_cursor,
+        "results": results
     })))
 }
 
+pub async fn get_me(PMSubscriber(user): PMSubscriber) -> Json<Value> {
+    Json(json!(user))
+}
+
+fn api_token_json(api_token: &ApiToken) -> Value {
+    json!({
+        "id": api_token.id,
+        "label": api_token.label,
+        "scope": api_token.scope,
+        "created": api_token.created,
+    })
+}
+
+pub async fn create_api_token_for_me(
+    State(state): State<Arc<AppState>>,
+    PMSubscriber(user): PMSubscriber,
+    ValidatedJson(token_create): ValidatedJson<ApiTokenCreate>,
+) -> Result<(StatusCode, Json<Value>), FieldError> {
+    let now = current_timestamp()
+        .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?;
+    let (token, api_token) = create_api_token(
+        &state,
+        user.uid,
+        &token_create.label,
+        token_create.scope,
+        now,
+    )
+    .await?;
+
+    let mut value = api_token_json(&api_token);
+    value["token"] = json!(token);
+    Ok((StatusCode::CREATED, Json(value)))
+}
+
+pub async fn list_api_tokens_for_me(
+    State(state): State<Arc<AppState>>,
+    PMSubscriber(user): PMSubscriber,
+) -> Result<Json<Value>, FieldError> {
+    let tokens = list_api_tokens(&state, user.uid).await?;
+    let results: Vec<Value> = tokens.iter().map(api_token_json).collect();
+    Ok(Json(json!({
+        "count": results.len(),
+        "results": results,
+    })))
+}
+
+pub async fn revoke_api_token_for_me(
+    State(state): State<Arc<AppState>>,
+    PMSubscriber(user): PMSubscriber,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, FieldError> {
+    revoke_api_token(&state, user.uid, &id).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
 pub async fn get_user_by_id(
     State(state): State<Arc<AppState>>,
     PMSubscriber(user): PMSubscriber,
     Path(uid): Path<u32>,
+    ValidatedQuery(q): ValidatedQuery<UserQuery>,
 ) -> Result<Json<Value>, FieldError> {
+    let fields = match q.fields {
+        Some(ref fields) => Some(get_fields(fields, &USER_FIELDS)?),
+        None => None,
+    };
+    let project = |value: Value| match fields {
+        Some(ref fields) => select_fields(value, fields),
+        None => value,
+    };
+
     if user.uid == uid {
-        return Ok(Json(json!(user)));
+        return Ok(Json(project(json!(user))));
     }
 
     if user.group == "administrator" {
-        if let Ok(Some(mut target_user)) = db::get_user_by_uid(&state, uid).await {
-            target_user.password = None;
-            Ok(Json(json!(target_user)))
+        if let Ok(Some(target_user)) = db::get_user_by_uid(&state, uid).await {
+            Ok(Json(project(json!(target_user))))
         } else {
             Err(FieldError::InvalidParams("uid".to_string()))
         }
@@ -108,7 +348,14 @@ pub async fn modify_user_by_id(
 
         let exist_user = db::get_user_by_uid(&state, uid).await?;
 
-        if exist_user.is_some() {
+        if let Some(exist_user) = exist_user {
+            if exist_user.group == "administrator"
+                && user_modify.group != "administrator"
+                && db::get_users_count_by_group(&state, "administrator").await? <= 1
+            {
+                return Err(FieldError::InvalidParams("last administrator".to_string()));
+            }
+
             if user_modify.password.is_none() {
                 let user = db::update_user_by_uid_with_user_modify_for_data_without_password(
                     &state,
@@ -121,10 +368,13 @@ pub async fn modify_user_by_id(
                 })))
             } else {
                 let password = user_modify.password.unwrap();
-                let hashed_password = hash(&password);
+                let hashed_password = hash(&state, &password).await?;
 
                 let user =
                     db::update_user_by_uid_for_password(&state, uid, &hashed_password).await?;
+                let now = current_timestamp()
+                    .map_err(|_| FieldError::DatabaseFailed("system time failed".to_string()))?;
+                invalidate_user_tokens(&state, user.uid, now).await?;
                 Ok(Json(json!({
                     "msg": format!("{} password changed", user.uid)
                 })))
@@ -139,15 +389,31 @@ pub async fn modify_user_by_id(
 
 pub async fn delete_user_by_id(
     State(state): State<Arc<AppState>>,
-    PMAdministrator(_): PMAdministrator,
+    PMSubscriber(user): PMSubscriber,
     Path(uid): Path<u32>,
+    ValidatedQuery(q): ValidatedQuery<UserDelete>,
 ) -> Result<Json<Value>, FieldError> {
-    let exist_user = db::get_user_by_uid(&state, uid).await?;
-    if exist_user.is_none() {
-        return Err(FieldError::InvalidParams("uid".to_string()));
+    if uid != user.uid && user.group != "administrator" {
+        return Err(FieldError::PermissionDeny);
     }
 
-    let _ = db::delete_user_by_uid(&state, uid).await?;
+    let exist_user = match db::get_user_by_uid(&state, uid).await? {
+        Some(u) => u,
+        None => return Err(FieldError::InvalidParams("uid".to_string())),
+    };
+    if exist_user.group == "administrator"
+        && db::get_users_count_by_group(&state, "administrator").await? <= 1
+    {
+        return Err(FieldError::InvalidParams("last administrator".to_string()));
+    }
+
+    if let Some(reassign_to) = q.reassign_to {
+        if reassign_to == uid || db::get_user_by_uid(&state, reassign_to).await?.is_none() {
+            return Err(FieldError::InvalidParams("reassign_to".to_string()));
+        }
+    }
+
+    let _ = db::delete_user_by_uid_with_reassign(&state, uid, q.reassign_to).await?;
     Ok(Json(json!({"msg": "ok"})))
 }
 
@@ -160,7 +426,11 @@ pub async fn list_options(
         return Err(FieldError::PermissionDeny);
     }
 
-    let options = db::get_options_by_uid(&state, uid).await?;
+    let options: Vec<_> = db::get_options_by_uid(&state, uid)
+        .await?
+        .into_iter()
+        .filter(|o| !is_reserved_option(&o.name))
+        .collect();
     Ok(Json(json!({
         "page": 1,
         "page_size": options.len(),
@@ -178,6 +448,9 @@ pub async fn get_option_by_uid_and_name(
     if user.uid != uid && !(user.group == "administrator") {
         return Err(FieldError::PermissionDeny);
     }
+    if is_reserved_option(&name) {
+        return Err(FieldError::NotFound("uid or name".to_string()));
+    }
 
     let option = match db::get_option_by_uid_and_name(&state, uid, &name).await?{
         Some(option) => option,
@@ -192,6 +465,9 @@ pub async fn create_option_by_option_create(
     Path(_): Path<u32>,
     ValidatedJson(option_create): ValidatedJson<OptionCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
+    if is_reserved_option(&option_create.name) {
+        return Err(FieldError::InvalidParams("name".to_string()));
+    }
     match db::get_option_by_uid_and_name(&state, user.uid, &option_create.name).await {
         Ok(Some(_)) => return Err(FieldError::InvalidParams("name".to_string())),
         _ => (),
@@ -210,6 +486,9 @@ pub async fn modify_option_by_uid_and_name(
     if user.uid != uid && !(user.group == "administrator") {
         return Err(FieldError::PermissionDeny);
     }
+    if is_reserved_option(&name) {
+        return Err(FieldError::InvalidParams("name".to_string()));
+    }
 
     let option = db::get_option_by_uid_and_name(&state, uid, &name).await;
     if option.is_err() {
@@ -230,6 +509,9 @@ pub async fn delete_option_by_uid_and_name(
     if user.uid != uid && !(user.group == "administrator") {
         return Err(FieldError::PermissionDeny);
     }
+    if is_reserved_option(&name) {
+        return Err(FieldError::InvalidParams("name".to_string()));
+    }
 
     match db::get_option_by_uid_and_name(&state, uid, &name).await {
         Ok(Some(_)) => (),
@@ -239,3 +521,68 @@ pub async fn delete_option_by_uid_and_name(
     let _ = db::delete_option_by_uid_and_name(&state, uid, &name).await?;
     Ok(Json(json!({"msg": "ok"})))
 }
+
+pub async fn upload_avatar(
+    State(state): State<Arc<AppState>>,
+    PMSubscriber(user): PMSubscriber,
+    Path(uid): Path<u32>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<Value>), FieldError> {
+    if user.uid != uid && user.group != "administrator" {
+        return Err(FieldError::PermissionDeny);
+    }
+    if db::get_user_by_uid(&state, uid).await?.is_none() {
+        return Err(FieldError::InvalidParams("uid".to_string()));
+    }
+
+    let field = match multipart.next_field().await {
+        Ok(Some(f)) => f,
+        _ => return Err(FieldError::InvalidParams("file".to_string())),
+    };
+    let ext = match field.content_type().and_then(get_image_ext) {
+        Some(e) => e,
+        None => return Err(FieldError::InvalidParams("file".to_string())),
+    };
+
+    let rand_name: u64 = rand::thread_rng().gen_range(1_000_000_000..9_999_999_999);
+    let name = format!("{uid}-{rand_name}.{ext}");
+    let upload_root = std::path::Path::new(&state.upload_root);
+    let _ = stream_to_file(upload_root.join(AVATAR_DIR), &name, field).await?;
+
+    if let Some(exist_avatar) = db::get_option_by_uid_and_name(&state, uid, AVATAR_OPTION).await? {
+        if let Some(path) = exist_avatar.value.filter(|p| is_avatar_path(p, uid)) {
+            let _ = delete_file(upload_root.to_path_buf(), &path).await;
+        }
+        let _ = db::delete_option_by_uid_and_name(&state, uid, AVATAR_OPTION).await?;
+    }
+    let path = format!("/{AVATAR_DIR}/{name}");
+    let _ = db::create_option_with_params(&state, uid, AVATAR_OPTION, &path).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "msg": "ok", "avatar": path })),
+    ))
+}
+
+pub async fn get_avatar(
+    State(state): State<Arc<AppState>>,
+    Path(uid): Path<u32>,
+) -> Result<Response, FieldError> {
+    let path = match db::get_option_by_uid_and_name(&state, uid, AVATAR_OPTION).await? {
+        Some(avatar) => avatar.value.unwrap_or_default(),
+        None => return Err(FieldError::NotFound("avatar".to_string())),
+    };
+    if !is_avatar_path(&path, uid) {
+        return Err(FieldError::NotFound("avatar".to_string()));
+    }
+    let mime = path
+        .rsplit('.')
+        .next()
+        .and_then(get_image_mime)
+        .ok_or(FieldError::NotFound("avatar".to_string()))?;
+
+    let filepath = std::path::Path::new(&state.upload_root).join(path.trim_start_matches('/'));
+    let data = tokio::fs::read(filepath)
+        .await
+        .map_err(|_| FieldError::NotFound("avatar".to_string()))?;
+    Ok(([(header::CONTENT_TYPE, mime)], data).into_response())
+}
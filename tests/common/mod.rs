@@ -1,17 +1,26 @@
+use std::collections::HashMap;
 use std::env;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use axum::Router;
 use axum::{
     body::Body,
     http::{self, Request, StatusCode},
 };
+use dashmap::DashMap;
 use http_body_util::BodyExt;
 use minijinja::Environment;
-use sea_orm::Database;
+use sea_orm::{ConnectionTrait, Database};
 use serde_json::{json, Value};
+use tokio::sync::broadcast;
 use tower::ServiceExt;
 
-use rumo::{app, AppState, INDEX_TPL};
+use rumo::{
+    app, publish_due_posts, AppState, DefaultSpamChecker, HtmlPolicy, Mailer, NoopMailer, SlugMode,
+    INDEX_TPL,
+};
 
 async fn setup_state() -> AppState {
     let conn = Database::connect(&env::var("DATABASE_URL").unwrap())
@@ -20,19 +29,88 @@ async fn setup_state() -> AppState {
 
     let secret_key = env::var("SECRET_KEY").unwrap();
     let access_token_expire_secondes = 3600 * 24 * 30;
+    let refresh_token_expire_secondes = 3600 * 24 * 60;
+    let password_reset_expire_secondes = 1800;
+    let argon2_memory_cost = 4096;
+    let argon2_time_cost = 1;
+    let argon2_parallelism = 1;
+    let password_rehashes = Arc::new(AtomicU64::new(0));
+    let revoked_tokens = Arc::new(Mutex::new(HashMap::new()));
+    let login_attempts = Arc::new(DashMap::new());
+    let login_attempts_limit = 5;
+    let login_attempts_window = 300;
+    let comment_attempts = Arc::new(DashMap::new());
+    let comment_rate_limit = 5;
+    let comment_rate_limit_user = 20;
+    let mailer = Arc::new(NoopMailer);
+    let gravatar_default = "mp".to_string();
+    let spam_checker = Arc::new(DefaultSpamChecker::default());
+    let comment_events = broadcast::channel(16).0;
+    let allowed_origins = vec!["http://example.com".to_string()];
+    let allowed_methods = vec!["GET".to_string(), "POST".to_string()];
+    let allow_credentials = false;
+    let trusted_proxies = vec!["2.2.2.2".parse().unwrap()];
     let preload_index = false;
+    let rendered_html = Arc::new(DashMap::new());
     let mut jinja_env = Environment::new();
     jinja_env.add_template("index.html", &INDEX_TPL).unwrap();
     let upload_root = ".".to_string();
+    let keep_upload_filename = false;
+    let allowed_extensions = vec![];
+    let blocked_extensions = vec!["php".to_string(), "exe".to_string()];
+    let compress_text = false;
+    let slug_mode = SlugMode::Transliterate;
+    let html_policy = HtmlPolicy {
+        write_groups: vec!["contributor".to_string()],
+        ..Default::default()
+    };
+    let site_url = "http://example.com".to_string();
+    let site_founded = 946684800;
+    let max_page_size = 1000;
+    let max_storage_bytes_per_user = 0;
     let read_only = false;
+    let trailing_slash_redirect = false;
 
     AppState {
         conn,
         secret_key,
         access_token_expire_secondes,
+        refresh_token_expire_secondes,
+        password_reset_expire_secondes,
+        argon2_memory_cost,
+        argon2_time_cost,
+        argon2_parallelism,
+        password_rehashes,
+        revoked_tokens,
+        login_attempts,
+        login_attempts_limit,
+        login_attempts_window,
+        comment_attempts,
+        comment_rate_limit,
+        comment_rate_limit_user,
+        mailer,
+        gravatar_default,
+        spam_checker,
+        comment_events,
+        allowed_origins,
+        allowed_methods,
+        allow_credentials,
+        trusted_proxies,
         upload_root,
+        keep_upload_filename,
+        allowed_extensions,
+        blocked_extensions,
+        compress_text,
+        slug_mode,
+        html_policy,
+        site_url,
+        site_founded,
+        max_page_size,
+        max_storage_bytes_per_user,
         read_only,
+        trailing_slash_redirect,
         preload_index,
+        rendered_html,
         jinja_env,
     }
 }
@@ -59,6 +137,150 @@ pub async fn get(url: &str) -> (StatusCode, Option<Value>) {
     (status_code, body)
 }
 
+#[allow(dead_code)]
+pub async fn get_text(url: &str) -> (StatusCode, http::HeaderMap, String) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&body).to_string();
+    (status_code, headers, body)
+}
+
+#[allow(dead_code)]
+pub async fn get_headers(url: &str) -> (StatusCode, http::HeaderMap) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    (response.status(), response.headers().clone())
+}
+
+#[allow(dead_code)]
+pub async fn get_with_header(
+    url: &str,
+    name: http::HeaderName,
+    value: &str,
+) -> (StatusCode, http::HeaderMap) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .header(name, value)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    (response.status(), response.headers().clone())
+}
+
+#[allow(dead_code)]
+pub async fn preflight(url: &str, origin: &str) -> (StatusCode, http::HeaderMap) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::OPTIONS)
+        .uri(url)
+        .header(http::header::ORIGIN, origin)
+        .header(http::header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .header(
+            http::header::ACCESS_CONTROL_REQUEST_HEADERS,
+            "authorization",
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    (response.status(), response.headers().clone())
+}
+
+#[allow(dead_code)]
+pub async fn read_only_request(method: http::Method, url: &str, data: String) -> StatusCode {
+    let mut state = setup_state().await;
+    state.read_only = true;
+    let app = setup_app(state).await;
+
+    let request = Request::builder()
+        .method(method)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    response.status()
+}
+
+/// Sends the request against an empty in-memory database, so every query fails.
+#[allow(dead_code)]
+pub async fn broken_database_get(url: &str) -> (StatusCode, Option<Value>) {
+    let mut state = setup_state().await;
+    state.conn = Database::connect("sqlite::memory:").await.unwrap();
+    let app = setup_app(state).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&body).unwrap_or(None);
+    (status_code, body)
+}
+
+#[allow(dead_code)]
+pub async fn trailing_slash_request(
+    method: http::Method,
+    url: &str,
+    redirect: bool,
+) -> (StatusCode, http::HeaderMap) {
+    let mut state = setup_state().await;
+    state.trailing_slash_redirect = redirect;
+    let app = setup_app(state).await;
+
+    let request = Request::builder()
+        .method(method)
+        .uri(url)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    (response.status(), response.headers().clone())
+}
+
+#[allow(dead_code)]
+pub async fn allowed_methods(url: &str, read_only: bool) -> (StatusCode, Option<String>) {
+    let mut state = setup_state().await;
+    state.read_only = read_only;
+    let app = setup_app(state).await;
+
+    let request = Request::builder()
+        .method(http::Method::OPTIONS)
+        .uri(url)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let allow = response
+        .headers()
+        .get(http::header::ALLOW)
+        .map(|v| v.to_str().unwrap().to_string());
+    (response.status(), allow)
+}
+
 #[allow(dead_code)]
 pub async fn post(url: &str, data: String) -> (StatusCode, Option<Value>) {
     let state = setup_state().await;
@@ -79,6 +301,94 @@ pub async fn post(url: &str, data: String) -> (StatusCode, Option<Value>) {
     (status_code, body)
 }
 
+#[allow(dead_code)]
+pub async fn post_many(url: &str, data: String, times: usize) -> Vec<StatusCode> {
+    let state = setup_state().await;
+
+    let mut status_codes = vec![];
+    for _ in 0..times {
+        let app = setup_app(state.clone()).await;
+        let request = Request::builder()
+            .method(http::Method::POST)
+            .uri(url)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header("User-Agent", "test")
+            .header("X-Forwarded-For", "1.1.1.1, 2.2.2.2")
+            .body(Body::from(data.clone()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        status_codes.push(response.status());
+    }
+    status_codes
+}
+
+#[allow(dead_code)]
+pub async fn post_concurrently(url: &str, data: String, times: usize) -> Vec<StatusCode> {
+    let state = setup_state().await;
+
+    let requests = (0..times).map(|_| {
+        let state = state.clone();
+        let data = data.clone();
+        async move {
+            let app = setup_app(state).await;
+            let request = Request::builder()
+                .method(http::Method::POST)
+                .uri(url)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .header("User-Agent", "test")
+                .header("X-Forwarded-For", "1.1.1.1, 2.2.2.2")
+                .body(Body::from(data))
+                .unwrap();
+            app.oneshot(request).await.unwrap().status()
+        }
+    });
+    futures::future::join_all(requests).await
+}
+
+/// Keeps the last mail body so tests can read tokens sent by mail.
+struct LastMailer(Mutex<String>);
+
+#[axum::async_trait]
+impl Mailer for LastMailer {
+    async fn send(&self, _to: &str, _subject: &str, body: &str) -> Result<(), String> {
+        *self.0.lock().unwrap() = body.to_string();
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+pub async fn request_password_reset_token(mail: &str) -> String {
+    let mut state = setup_state().await;
+    let mailer = Arc::new(LastMailer(Mutex::new(String::new())));
+    state.mailer = mailer.clone();
+    let app = setup_app(state).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/password-reset/request")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "mail": mail }).to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = mailer.0.lock().unwrap().clone();
+    body.rsplit(' ').next().unwrap().to_string()
+}
+
+#[allow(dead_code)]
+pub async fn publish_posts() -> u64 {
+    let state = setup_state().await;
+    publish_due_posts(&state).await.unwrap()
+}
+
+#[allow(dead_code)]
+pub async fn set_content_status(cid: u64, status: &str) {
+    let state = setup_state().await;
+    let sql = format!("UPDATE typecho_contents SET status = '{status}' WHERE cid = {cid}");
+    state.conn.execute_unprepared(&sql).await.unwrap();
+}
+
 #[allow(dead_code)]
 pub async fn admin_get(url: &str) -> (StatusCode, Option<Value>) {
     let state = setup_state().await;
@@ -111,6 +421,149 @@ pub async fn admin_get(url: &str) -> (StatusCode, Option<Value>) {
     (status_code, body)
 }
 
+#[allow(dead_code)]
+pub async fn admin_get_text(url: &str) -> (StatusCode, http::HeaderMap, String) {
+    let (_, token) = login("admin@local.host", "admin").await;
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&body).to_string();
+    (status_code, headers, body)
+}
+
+#[allow(dead_code)]
+pub async fn login(mail: &str, password: &str) -> (u64, String) {
+    let (_, body) = post(
+        "/api/users/token",
+        json!({"mail": mail, "password": password}).to_string(),
+    )
+    .await;
+    let body = body.unwrap();
+    let uid = body.get("uid").unwrap().as_u64().unwrap();
+    let token = body.get("access_token").unwrap().as_str().unwrap();
+    (uid, token.to_string())
+}
+
+/// Opens the comment stream with `token`, creates a comment through `url` on the same state
+/// and returns the stream status with its first event.
+#[allow(dead_code)]
+pub async fn stream_comment_event(
+    token: &str,
+    url: &str,
+    data: String,
+) -> (StatusCode, Option<String>) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri("/api/comments/stream")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    if status_code != StatusCode::OK {
+        return (status_code, None);
+    }
+
+    let app = setup_app(state.clone()).await;
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header("User-Agent", "test")
+        .header("X-Forwarded-For", "1.1.1.1, 2.2.2.2")
+        .body(Body::from(data))
+        .unwrap();
+    let created = app.oneshot(request).await.unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+
+    let mut body = response.into_body();
+    let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let event = String::from_utf8_lossy(&frame.into_data().unwrap()).to_string();
+    (status_code, Some(event))
+}
+
+#[allow(dead_code)]
+pub async fn token_request(
+    method: http::Method,
+    url: &str,
+    token: &str,
+    data: String,
+) -> (StatusCode, Option<Value>) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(method)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&body).unwrap_or(None);
+    (status_code, body)
+}
+
+#[allow(dead_code)]
+pub async fn admin_logout_then_get(url: &str) -> (StatusCode, StatusCode) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+    let login_data = json!({"mail": "admin@local.host", "password": "admin"}).to_string();
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/users/token")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(login_data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let token = body.get("access_token").unwrap().as_str().unwrap();
+
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/logout")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let logout_status_code = response.status();
+
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    (logout_status_code, response.status())
+}
+
 #[allow(dead_code)]
 pub async fn admin_delete(url: &str) -> (StatusCode, Option<Value>) {
     let state = setup_state().await;
@@ -175,6 +628,28 @@ pub async fn admin_post(url: &str, data: String) -> (StatusCode, Option<Value>)
     (status_code, body)
 }
 
+/// Posts as the administrator with `COMPRESS_TEXT` enabled.
+#[allow(dead_code)]
+pub async fn compressed_admin_post(url: &str, data: String) -> (StatusCode, Option<Value>) {
+    let (_, token) = login("admin@local.host", "admin").await;
+    let mut state = setup_state().await;
+    state.compress_text = true;
+    let app = setup_app(state).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&body).unwrap_or(None);
+    (status_code, body)
+}
+
 #[allow(dead_code)]
 pub async fn admin_patch(url: &str, data: String) -> (StatusCode, Option<Value>) {
     let state = setup_state().await;
@@ -287,3 +762,24 @@ pub fn get_multipart(filename: &str, content_type: &str) -> Vec<u8> {
 
     data
 }
+
+#[allow(dead_code)]
+pub fn get_multipart_files(files: &[(&str, &str)]) -> Vec<u8> {
+    let boundary = "testfileboundary";
+    let mut data = String::new();
+    for (filename, content_type) in files {
+        data.push_str(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\naabbccddeeff\r\n"
+        ));
+    }
+    data.push_str(&format!("--{boundary}--\r\n"));
+
+    data.into_bytes()
+}
+
+#[allow(dead_code)]
+pub async fn set_meta_count(mid: u64, count: u64) {
+    let state = setup_state().await;
+    let sql = format!("UPDATE typecho_metas SET count = {count} WHERE mid = {mid}");
+    state.conn.execute_unprepared(&sql).await.unwrap();
+}
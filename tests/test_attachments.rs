@@ -1,8 +1,12 @@
-use axum::http::StatusCode;
+use axum::http::{Method, StatusCode};
 use serde_json::json;
 
 mod common;
-use common::{admin_delete, admin_get, admin_post, admin_post_file, get_multipart, admin_patch_file};
+use common::{
+    admin_delete, admin_get, admin_get_text, admin_patch, admin_patch_file, admin_post,
+    admin_post_file, get_multipart, get_multipart_files, login, post, set_content_status,
+    token_request,
+};
 
 #[tokio::test]
 async fn create_then_list_attachments_success() {
@@ -188,3 +192,279 @@ async fn create_then_delete_post_attachments_success() {
     let new_count = body.get("all_count").unwrap().as_u64().unwrap();
     assert!(new_count <= count);
 }
+
+#[tokio::test]
+async fn list_attachments_count_matches_results() {
+    let mut cids = vec![];
+    for name in ["testFileCount1.png", "testFileCount2.png", "testFileCount3.png"] {
+        let data = get_multipart(name, "image/png");
+        let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+        cids.push(body.unwrap().get("cid").unwrap().as_u64().unwrap());
+    }
+    set_content_status(cids[1], "hidden").await;
+    set_content_status(cids[2], "trash").await;
+
+    for url in [
+        "/api/attachments/?page_size=1000",
+        "/api/attachments/?page_size=1000&private=true",
+        "/api/attachments/?page_size=1000&status=trash",
+    ] {
+        let (status_code, body) = admin_get(url).await;
+        assert_eq!(status_code, StatusCode::OK);
+
+        let body = body.unwrap();
+        let all_count = body.get("all_count").unwrap().as_u64().unwrap();
+        let results = body.get("results").unwrap().as_array().unwrap();
+        assert_eq!(all_count, results.len() as u64);
+    }
+
+    let (_, body) = admin_get("/api/attachments/?page_size=1000&private=true").await;
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(results.iter().any(|a| a["cid"] == cids[1]));
+    assert!(!results.iter().any(|a| a["cid"] == cids[2]));
+}
+
+#[tokio::test]
+async fn list_attachments_with_unknown_order_by_failed() {
+    let (status_code, _) = admin_get("/api/attachments/?order_by=created").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = admin_get("/api/attachments/?order_by=slug").await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn list_attachments_by_mime_prefix_success() {
+    let data = get_multipart("testFileMime.png", "image/png");
+    let (status_code, _) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let data = get_multipart("testFileMime.txt", "text/plain");
+    let (status_code, _) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = admin_get("/api/attachments/?mime_prefix=image/&page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(body["all_count"].as_u64().unwrap(), results.len() as u64);
+    assert!(results
+        .iter()
+        .all(|a| a["mime"].as_str().unwrap().starts_with("image/")));
+    assert!(results.iter().any(|a| a["name"] == "testFileMime.png"));
+
+    let (status_code, body) = admin_get("/api/attachments/?kind=document&page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(results.iter().any(|a| a["name"] == "testFileMime.txt"));
+    assert!(!results.iter().any(|a| a["name"] == "testFileMime.png"));
+
+    let (status_code, _) = admin_get("/api/attachments/?kind=picture").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn reassign_attachment_author_success() {
+    let data = json!({"name": "attachment_owner", "mail": "attachment_owner@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (uid, _) = login("attachment_owner@test.local", "password").await;
+
+    let data = get_multipart("testFileReassign.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap().get("cid").unwrap().as_u64().unwrap();
+
+    let data = json!({"uid": 99999}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/attachments/{cid}/author"), data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let data = json!({"uid": uid}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/attachments/{cid}/author"), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = json!({"from_uid": uid, "uid": 1}).to_string();
+    let (status_code, body) = admin_post("/api/attachments/reassign", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let count = body.unwrap().get("count").unwrap().as_u64().unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn patch_attachment_meta_success() {
+    let data = get_multipart("testFileMeta.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let body = body.unwrap();
+    let cid = body.get("cid").unwrap().as_u64().unwrap();
+    let path = body.get("path").unwrap().as_str().unwrap().to_string();
+
+    let url = format!("/api/attachments/{cid}/meta");
+    let data = json!({"name": "renamed.png", "slug": "test-file-meta-renamed"}).to_string();
+    let (status_code, body) = admin_patch(&url, data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("name").unwrap(), "renamed.png");
+    assert_eq!(body.get("path").unwrap().as_str().unwrap(), path);
+
+    let (status_code, _) = admin_patch(&url, json!({}).to_string()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let data = get_multipart("testFileMeta2.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let other_cid = body.unwrap().get("cid").unwrap().as_u64().unwrap();
+    let data = json!({"slug": "test-file-meta-renamed"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/attachments/{other_cid}/meta"), data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn prune_orphaned_attachments_success() {
+    let data = get_multipart("testFilePrune.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let path = body.unwrap()["path"].as_str().unwrap().to_string();
+
+    let dir = std::path::Path::new("usr/uploads/prune-test");
+    std::fs::create_dir_all(dir).unwrap();
+    let orphan = dir.join("orphan.png");
+    let file = std::fs::File::create(&orphan).unwrap();
+    let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(7200);
+    file.set_modified(modified).unwrap();
+    let recent = dir.join("recent.png");
+    std::fs::File::create(&recent).unwrap();
+
+    let (status_code, body) =
+        admin_post("/api/attachments/prune?dry_run=true", String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let files = body["files"].as_array().unwrap();
+    let orphan_path = "/usr/uploads/prune-test/orphan.png";
+    let recent_path = "/usr/uploads/prune-test/recent.png";
+    assert!(files.iter().any(|f| f == orphan_path));
+    assert!(!files.iter().any(|f| f == recent_path));
+    assert!(!files.iter().any(|f| f == path.as_str()));
+    assert!(orphan.exists());
+
+    let (status_code, _) = admin_post("/api/attachments/prune", String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(!orphan.exists());
+    assert!(recent.exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn create_multiple_attachments_success() {
+    let data = get_multipart_files(&[
+        ("testFileMulti1.png", "image/png"),
+        ("testFileMultiNoExt", "image/png"),
+        ("testFileMulti2.png", "image/png"),
+    ]);
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let body = body.unwrap();
+    assert_eq!(body.get("count").unwrap().as_u64().unwrap(), 2);
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    let errors = body.get("errors").unwrap().as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].get("index").unwrap().as_u64().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn create_attachment_with_blocked_extension_failed() {
+    let data = get_multipart("testFileBlocked.php", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap()["field"], "extension");
+
+    let data = get_multipart("testFileBlocked.php.png", "image/png");
+    let (status_code, _) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let data = get_multipart("testFileAllowed.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+
+    let url = format!("/api/attachments/{cid}");
+    let data = get_multipart("testFileBlocked.EXE", "image/png");
+    let (status_code, body) = admin_patch_file(&url, data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap()["field"], "extension");
+
+    let (status_code, body) = admin_get(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["name"], "testFileAllowed.png");
+}
+
+#[tokio::test]
+async fn list_attachments_by_author_success() {
+    let data = json!({"name": "attachment_author", "mail": "attachment_author@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (uid, _) = login("attachment_author@test.local", "password").await;
+    let data = json!({"name": "attachment_author", "screenName": "attachment_author", "mail": "attachment_author@test.local", "url": "http://127.0.0.1", "group": "contributor"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/users/{uid}"), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = get_multipart("testFileAuthor.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+    let data = json!({"uid": uid}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/attachments/{cid}/author"), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let url = format!("/api/attachments/?author={uid}&page_size=100");
+    let (status_code, body) = admin_get(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["all_count"], 1);
+    assert_eq!(body["results"][0]["cid"], cid);
+
+    let (_, token) = login("attachment_author@test.local", "password").await;
+    let (status_code, _) = token_request(Method::GET, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn verify_attachment_integrity_success() {
+    let data = get_multipart("testFileVerify.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+
+    let url = format!("/api/attachments/verify?cid={cid}");
+    let (status_code, headers, body) = admin_get_text(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/x-ndjson");
+    let lines: Vec<serde_json::Value> = body
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["cid"], cid);
+    assert_eq!(lines[0]["status"], "ok");
+    assert_eq!(lines[0]["hashed"], true);
+    assert_eq!(lines[1]["summary"]["checked"], 1);
+    assert_eq!(lines[1]["summary"]["ok"], 1);
+
+    let path = lines[0]["path"].as_str().unwrap();
+    std::fs::write(format!(".{path}"), b"tampered").unwrap();
+    let (_, _, body) = admin_get_text(&url).await;
+    let line: serde_json::Value = serde_json::from_str(body.lines().next().unwrap()).unwrap();
+    assert_eq!(line["status"], "mismatch");
+
+    std::fs::remove_file(format!(".{path}")).unwrap();
+    let (_, _, body) = admin_get_text(&url).await;
+    let line: serde_json::Value = serde_json::from_str(body.lines().next().unwrap()).unwrap();
+    assert_eq!(line["status"], "missing");
+
+    let (status_code, _, _) = admin_get_text("/api/attachments/verify?cid=999999").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
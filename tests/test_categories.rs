@@ -158,3 +158,33 @@ async fn create_then_delete_category_post_success() {
     let new_count = body.get("all_count").unwrap().as_u64().unwrap();
     assert!(new_count < count);
 }
+
+#[tokio::test]
+async fn create_then_list_categories_tree_success() {
+    let data = json!({"name": "testCategoryTree", "slug": "test-category-tree"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/categories/test-category-tree").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let parent = body.unwrap().get("mid").unwrap().as_u64().unwrap();
+
+    let data = json!({"name": "testCategoryTreeChild", "slug": "test-category-tree-child", "parent": parent}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/categories/tree").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let nodes = body.get("results").unwrap().as_array().unwrap();
+    let node = nodes
+        .iter()
+        .find(|n| n.get("mid").unwrap().as_u64().unwrap() == parent)
+        .unwrap();
+    assert_eq!(node.get("depth").unwrap().as_u64().unwrap(), 0);
+
+    let children = node.get("children").unwrap().as_array().unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].get("depth").unwrap().as_u64().unwrap(), 1);
+}
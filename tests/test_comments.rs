@@ -2,7 +2,10 @@ use axum::http::StatusCode;
 use serde_json::json;
 
 mod common;
-use common::{admin_post, admin_get, admin_patch, admin_delete, get, post};
+use common::{
+    admin_delete, admin_get, admin_patch, admin_post, get, login, post, post_many,
+    stream_comment_event,
+};
 
 #[tokio::test]
 async fn create_then_list_comments_success() {
@@ -32,10 +35,38 @@ async fn create_then_list_comments_success() {
     println!("{:?}", body);
     assert_eq!(status_code, StatusCode::OK);
 
+    let body = body.unwrap();
+    let count = body.get("all_count").unwrap().as_u64().unwrap();
+    assert_eq!(count, 0);
+
+    let (status_code, body) =
+        admin_get("/api/posts/test-comment-post/comments/?status=waiting").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let comments = body.get("results").unwrap().as_array().unwrap();
+    let coid = comments[0].get("coid").unwrap().as_u64().unwrap();
+    assert_eq!(comments[0]["ip"], "1.1.1.1");
+    assert_eq!(comments[0]["agent"], "test");
+
+    let url = format!("/api/comments/{}/approve", coid);
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/posts/test-comment-post/comments/").await;
+    assert_eq!(status_code, StatusCode::OK);
+
     let body = body.unwrap();
     let count = body.get("all_count").unwrap().as_u64().unwrap();
     assert!(count > 0);
 
+    let comments = body.get("results").unwrap().as_array().unwrap();
+    assert!(comments[0].get("mail").is_none());
+    assert!(comments[0].get("ip").is_none());
+    assert!(comments[0].get("agent").is_none());
+    assert!(comments[0].get("gravatar").unwrap().as_str().unwrap().starts_with("https://"));
+    assert_eq!(comments[0].get("registered").unwrap(), false);
+
     let (status_code, body) = admin_get("/api/comments/").await;
     assert_eq!(status_code, StatusCode::OK);
 
@@ -67,7 +98,8 @@ async fn create_then_modify_comments_success() {
     let (status_code, _) = post("/api/posts/test-comment-post-modify/comments/", data).await;
     assert_eq!(status_code, StatusCode::CREATED);
 
-    let (status_code, body) = get("/api/posts/test-comment-post-modify/comments/").await;
+    let (status_code, body) =
+        admin_get("/api/posts/test-comment-post-modify/comments/?status=waiting").await;
     assert_eq!(status_code, StatusCode::OK);
 
     let body = body.unwrap();
@@ -119,14 +151,14 @@ async fn create_then_delete_comments_success() {
 
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
 
-    let (status_code, body) = get("/api/posts/test-comment-post-delete/comments/").await;
+    let (status_code, body) = admin_get("/api/posts/test-comment-post-delete/comments/?private=true").await;
     println!("{:?}", body);
     assert_eq!(status_code, StatusCode::OK);
 
     let body = body.unwrap();
     let count = body.get("all_count").unwrap().as_u64().unwrap();
 
-    let (status_code, body) = get("/api/posts/test-comment-post-delete/comments/").await;
+    let (status_code, body) = admin_get("/api/posts/test-comment-post-delete/comments/?private=true").await;
     assert_eq!(status_code, StatusCode::OK);
 
     let body = body.unwrap();
@@ -143,7 +175,7 @@ async fn create_then_delete_comments_success() {
     let (status_code, _) = admin_get(&url).await;
     assert_eq!(status_code, StatusCode::NOT_FOUND);
 
-    let (status_code, body) = get("/api/posts/test-comment-post-delete/comments/").await;
+    let (status_code, body) = admin_get("/api/posts/test-comment-post-delete/comments/?private=true").await;
     println!("{:?}", body);
     assert_eq!(status_code, StatusCode::OK);
 
@@ -151,3 +183,298 @@ async fn create_then_delete_comments_success() {
     let new_count = body.get("all_count").unwrap().as_u64().unwrap();
     assert!(count > new_count);
 }
+
+#[tokio::test]
+async fn create_then_mark_comment_spam_success() {
+    let data = json!({
+        "title": "testCommentPostSpam",
+        "slug": "test-comment-post-spam",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "test@local.host",
+        "url": "https://127.0.0.1",
+        "text": "test comment spam",
+    })
+    .to_string();
+    let (status_code, _) = post("/api/posts/test-comment-post-spam/comments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) =
+        admin_get("/api/posts/test-comment-post-spam/comments/?status=waiting").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let comments = body.get("results").unwrap().as_array().unwrap();
+    let coid = comments[0].get("coid").unwrap().as_u64().unwrap();
+
+    let url = format!("/api/comments/{}/spam", coid);
+    let (status_code, _) = post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) =
+        admin_get("/api/posts/test-comment-post-spam/comments/?status=spam").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let count = body.get("all_count").unwrap().as_u64().unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn list_comments_first_page_success() {
+    let (status_code, body) = admin_get("/api/comments/?page=1&page_size=1&order_by=-created").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let all_count = body.get("all_count").unwrap().as_u64().unwrap();
+    let count = body.get("count").unwrap().as_u64().unwrap();
+    assert_eq!(body.get("page").unwrap().as_u64().unwrap(), 1);
+    assert_eq!(count, all_count.min(1));
+}
+
+#[tokio::test]
+async fn create_spam_comment_success() {
+    let data = json!({
+        "title": "testCommentPostSpamCheck",
+        "slug": "test-comment-post-spam-check",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "test@local.host",
+        "url": "https://127.0.0.1",
+        "text": "https://a.test https://b.test https://c.test https://d.test",
+    })
+    .to_string();
+    let (status_code, _) = post("/api/posts/test-comment-post-spam-check/comments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) =
+        admin_get("/api/posts/test-comment-post-spam-check/comments/?status=spam").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let count = body.get("all_count").unwrap().as_u64().unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn create_comments_rate_limited() {
+    let data = json!({
+        "title": "testCommentPostRateLimit",
+        "slug": "test-comment-post-rate-limit",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "test@local.host",
+        "url": "https://127.0.0.1",
+        "text": "test comment rate limit",
+    })
+    .to_string();
+    let status_codes =
+        post_many("/api/posts/test-comment-post-rate-limit/comments/", data, 6).await;
+    assert_eq!(status_codes[..5], [StatusCode::CREATED; 5]);
+    assert_eq!(status_codes[5], StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn create_then_delete_comment_keeps_comments_num_success() {
+    let data = json!({
+        "title": "testCommentPostCount",
+        "slug": "test-comment-post-count",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap().get("cid").unwrap().as_u64().unwrap();
+
+    let (_, body) = admin_get("/api/posts/test-comment-post-count").await;
+    let comments_num = body.unwrap()["comments_num"].as_u64().unwrap();
+
+    let data = json!({"text": "test comment count"}).to_string();
+    let (status_code, _) = admin_post("/api/posts/test-comment-post-count/comments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (_, body) = admin_get("/api/posts/test-comment-post-count").await;
+    assert_eq!(
+        body.unwrap()["comments_num"].as_u64().unwrap(),
+        comments_num + 1
+    );
+
+    let (_, body) = admin_get("/api/posts/test-comment-post-count/comments/?private=true").await;
+    let body = body.unwrap();
+    let coid = body["results"][0]["coid"].as_u64().unwrap();
+    let (status_code, _) = admin_delete(&format!("/api/comments/{coid}")).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (_, body) = admin_get("/api/posts/test-comment-post-count").await;
+    assert_eq!(
+        body.unwrap()["comments_num"].as_u64().unwrap(),
+        comments_num
+    );
+
+    let url = format!("/api/admin/contents/{cid}/comments/recount");
+    let (status_code, body) = admin_post(&url, "{}".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(
+        body.unwrap()["comments_num"].as_u64().unwrap(),
+        comments_num
+    );
+}
+
+#[tokio::test]
+async fn create_allowlisted_comment_approved_success() {
+    let data = json!({
+        "title": "testCommentPostAllowlist",
+        "slug": "test-comment-post-allowlist",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"kind": "mail", "value": "Trusted@Local.Host"}).to_string();
+    let (status_code, _) = admin_post("/api/comments/lists/allow", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testTrustedAuthor",
+        "mail": "trusted@local.host",
+        "text": "test allowlisted comment",
+    })
+    .to_string();
+    let (status_code, _) = post("/api/posts/test-comment-post-allowlist/comments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-comment-post-allowlist/comments/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["all_count"], 1);
+
+    let (status_code, body) = admin_get("/api/comments/lists/allow").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body["results"].as_array().unwrap();
+    assert!(results.iter().any(|e| e["value"] == "Trusted@Local.Host"));
+
+    let (status_code, _) = admin_delete("/api/comments/lists/allow/mail/trusted@local.host").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (status_code, _) = admin_delete("/api/comments/lists/allow/mail/trusted@local.host").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, _) = admin_get("/api/comments/lists/unknown").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn list_pending_then_moderate_comments_success() {
+    let data = json!({
+        "title": "testPendingCommentPost",
+        "slug": "test-pending-comment-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testPendingAuthor",
+        "mail": "pending@local.host",
+        "url": "https://127.0.0.1",
+        "text": "pending comment",
+    })
+    .to_string();
+    let (status_code, _) = post("/api/posts/test-pending-comment-post/comments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, _) = get("/api/comments/pending").await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+
+    let (status_code, body) = admin_get("/api/comments/pending?page_size=1000").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let comments = body["results"].as_array().unwrap();
+    assert!(comments.iter().all(|c| c["status"] == "waiting"));
+    let comment = comments
+        .iter()
+        .find(|c| c["content"]["slug"] == "test-pending-comment-post")
+        .unwrap();
+    assert_eq!(comment["content"]["title"], "testPendingCommentPost");
+    let coid = comment["coid"].as_u64().unwrap();
+
+    let data = json!({"coids": [coid, 4294967295u32], "action": "approve"}).to_string();
+    let (status_code, body) = admin_post("/api/comments/moderate", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body["updated"], json!([coid]));
+    assert_eq!(body["missing"], json!([4294967295u32]));
+
+    let (status_code, body) = get("/api/posts/test-pending-comment-post/comments/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["all_count"], 1);
+}
+
+#[tokio::test]
+async fn stream_comments_on_create_success() {
+    let data = json!({
+        "title": "testCommentStreamPost",
+        "slug": "test-comment-stream-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "test@local.host",
+        "text": "test stream comment",
+    })
+    .to_string();
+    let (_, token) = login("admin@local.host", "admin").await;
+    let url = "/api/posts/test-comment-stream-post/comments/";
+    let (status_code, event) = stream_comment_event(&token, url, data.clone()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let event = event.unwrap();
+    assert!(event.starts_with("event: comment\n"));
+    assert!(event.contains("\"slug\":\"test-comment-stream-post\""));
+    assert!(event.contains("\"coid\":"));
+
+    let (status_code, event) = stream_comment_event("invalid", url, data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert!(event.is_none());
+}
@@ -0,0 +1,21 @@
+use axum::http::{header, StatusCode};
+
+mod common;
+use common::preflight;
+
+#[tokio::test]
+async fn preflight_allowed_origin_success() {
+    let (status_code, headers) = preflight("/api/users/1", "http://example.com").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(
+        headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "http://example.com"
+    );
+}
+
+#[tokio::test]
+async fn preflight_unknown_origin_failed() {
+    let (status_code, headers) = preflight("/api/users/1", "http://unknown.com").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
@@ -0,0 +1,29 @@
+use axum::http::StatusCode;
+
+mod common;
+use common::{broken_database_get, get};
+
+#[tokio::test]
+async fn get_missing_content_not_found() {
+    let (status_code, _) = get("/api/posts/test-database-missing-post").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, _) = get("/api/pages/test-database-missing-page").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn database_failed_not_reported_as_not_found() {
+    for url in [
+        "/api/posts/",
+        "/api/posts/test-database-post",
+        "/api/pages/test-database-page",
+        "/api/categories/",
+        "/api/tags/",
+        "/api/posts/test-database-post/comments/",
+    ] {
+        let (status_code, body) = broken_database_get(url).await;
+        assert_eq!(status_code, StatusCode::INTERNAL_SERVER_ERROR, "{url}");
+        assert_eq!(body.unwrap()["code"], "DATABASE_FAILED", "{url}");
+    }
+}
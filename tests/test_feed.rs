@@ -0,0 +1,30 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+mod common;
+use common::{admin_post, get_text};
+
+#[tokio::test]
+async fn feed_skip_feed_disabled_posts() {
+    for (slug, allow_feed) in [("test-feed-enabled", true), ("test-feed-disabled", false)] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testFeedText",
+            "status": "publish",
+            "allowFeed": allow_feed,
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let (status_code, headers, body) = get_text("/api/feed.xml").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let content_type = headers.get("content-type").unwrap().to_str().unwrap();
+    assert!(content_type.starts_with("application/rss+xml"));
+    assert!(body.starts_with("<?xml"));
+    assert!(!body.contains("test-feed-disabled"));
+}
@@ -0,0 +1,30 @@
+use axum::http::StatusCode;
+
+mod common;
+use common::{get, get_headers};
+
+#[tokio::test]
+async fn health_success() {
+    let (status_code, body) = get("/api/health").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("status").unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn ready_success() {
+    let (status_code, body) = get("/api/ready").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body.get("status").unwrap(), "ok");
+    assert!(body.get("backend").unwrap().is_string());
+}
+
+#[tokio::test]
+async fn response_has_request_id() {
+    let (status_code, headers) = get_headers("/api/health").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let request_id = headers.get("x-request-id").unwrap().to_str().unwrap();
+    assert_eq!(request_id.len(), 36);
+}
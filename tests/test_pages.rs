@@ -291,3 +291,42 @@ async fn create_then_delete_page_success() {
     let (status_code, _) = get("/api/pages/test-page-delete/fields/test_str").await;
     assert_eq!(status_code, StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn list_pages_order_by_order_success() {
+    let data = json!({
+        "title": "testPageOrderLast",
+        "slug": "test-page-order-last",
+        "created": 1666666666,
+        "text": "testText",
+        "order": 20,
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/pages/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testPageOrderFirst",
+        "slug": "test-page-order-first",
+        "created": 1666666666,
+        "text": "testText",
+        "order": 10,
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/pages/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/pages/?page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let slugs: Vec<&str> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["slug"].as_str().unwrap())
+        .collect();
+    let first = slugs.iter().position(|s| *s == "test-page-order-first");
+    let last = slugs.iter().position(|s| *s == "test-page-order-last");
+    assert!(first.unwrap() < last.unwrap());
+}
@@ -1,8 +1,13 @@
-use axum::http::StatusCode;
+use std::time::{Duration, SystemTime};
+
+use axum::http::{header, Method, StatusCode};
 use serde_json::json;
 
 mod common;
-use common::{admin_post, admin_patch, admin_delete, get};
+use common::{
+    admin_delete, admin_get, admin_patch, admin_post, compressed_admin_post, get, get_headers,
+    get_with_header, login, post, publish_posts, token_request,
+};
 
 #[tokio::test]
 async fn create_then_list_posts_success() {
@@ -73,6 +78,30 @@ async fn create_then_get_post_by_slug_success() {
     assert_eq!(status_code, StatusCode::OK);
 }
 
+#[tokio::test]
+async fn create_then_get_post_by_cid_success() {
+    let data = json!({
+        "title": "testPostByCid",
+        "slug": "test-post-by-cid",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap().get("cid").unwrap().as_u64().unwrap();
+
+    let (status_code, body) = get(&format!("/api/posts/by-cid/{cid}")).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("slug").unwrap(), "test-post-by-cid");
+
+    let (status_code, _) = get("/api/posts/by-cid/4294967295").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn create_then_modify_post_by_slug_success() {
     let data = json!({
@@ -127,6 +156,125 @@ async fn create_then_delete_post_by_slug_success() {
     assert_eq!(status_code, StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn create_then_modify_post_status_success() {
+    let created = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let data = json!({
+        "title": "testPostStatus",
+        "slug": "test-post-status",
+        "created": created,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, _) = get("/api/posts/test-post-status").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let data = json!({ "status": "unknown" }).to_string();
+    let (status_code, _) = admin_patch("/api/posts/test-post-status/status", data).await;
+    assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let data = json!({ "status": "publish" }).to_string();
+    let (status_code, body) = admin_patch("/api/posts/test-post-status/status", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("status").unwrap(), "publish");
+
+    let (status_code, body) = get("/api/posts/test-post-status").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body_created = body.unwrap().get("created").unwrap().as_u64().unwrap();
+    assert!(body_created < created);
+}
+
+#[tokio::test]
+async fn modify_post_status_for_contributor_waiting_success() {
+    let data = json!({"name": "status_posts", "mail": "status_posts@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (uid, _) = login("status_posts@test.local", "password").await;
+    let data = json!({"name": "status_posts", "screenName": "status_posts", "mail": "status_posts@test.local", "url": "http://127.0.0.1", "group": "contributor"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/users/{uid}"), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, token) = login("status_posts@test.local", "password").await;
+
+    let data = json!({
+        "title": "testContributorStatus",
+        "slug": "test-contributor-status",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = token_request(Method::POST, "/api/posts/", &token, data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let url = "/api/posts/test-contributor-status/status";
+    for status in ["hidden", "password", "private", "publish"] {
+        let data = json!({ "status": status }).to_string();
+        let (status_code, body) = token_request(Method::PATCH, url, &token, data).await;
+        assert_eq!(status_code, StatusCode::OK);
+        assert_eq!(body.unwrap()["status"], "waiting");
+    }
+
+    let (status_code, _) = get("/api/posts/test-contributor-status").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn trash_then_restore_and_purge_post_success() {
+    let data = json!({
+        "title": "testPostTrash",
+        "slug": "test-post-trash",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "hidden",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-trash").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = admin_get("/api/posts/?status=trash&page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(results.iter().any(|p| p["slug"] == "test-post-trash"));
+
+    let (status_code, body) = admin_get("/api/posts/?private=true&page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(!results.iter().any(|p| p["slug"] == "test-post-trash"));
+
+    let (status_code, _) = admin_post("/api/posts/test-post-trash/restore", "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = admin_get("/api/posts/test-post-trash?private=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("status").unwrap(), "hidden");
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-trash/purge").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-trash").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-trash/purge").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_get("/api/posts/test-post-trash?private=true").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn create_then_get_post_field_success() {
     let data = json!({
@@ -279,3 +427,1238 @@ async fn create_then_delete_post_field_success() {
     let count = body.get("fields").unwrap().as_array().unwrap().len();
     assert!(count == 1);
 }
+
+#[tokio::test]
+async fn create_post_return_created_post() {
+    let data = json!({
+        "title": "testPostCreated",
+        "slug": "test-post-created",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let body = body.unwrap();
+    assert!(body.get("cid").unwrap().as_u64().unwrap() > 0);
+    assert!(body.get("modified").unwrap().as_u64().unwrap() > 0);
+    assert_eq!(body.get("slug").unwrap(), "test-post-created");
+    assert_eq!(body.get("title").unwrap(), "testPostCreated");
+}
+
+#[tokio::test]
+async fn create_post_with_invalid_slug_or_title_failed() {
+    let data = json!({
+        "title": "testInvalidSlug",
+        "slug": "test invalid slug",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+    let body = body.unwrap();
+    assert_eq!(body.get("field").unwrap(), "slug");
+    assert!(body["errors"]["slug"].is_array());
+
+    for slug in ["Test-Upper-Slug", "test_underscore_slug", "test.dot.slug"] {
+        let data = json!({
+            "title": "testInvalidSlug",
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let data = json!({
+        "title": "testNonAsciiSlug",
+        "slug": "café-slug",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap().get("field").unwrap(), "slug");
+
+    let data = json!({
+        "title": "t".repeat(201),
+        "slug": "test-oversized-title",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body.unwrap().get("field").unwrap(), "title");
+
+    let data = json!({
+        "title": "testUnknownStatus",
+        "slug": "test-unknown-status",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "unknown",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status_code, body) = admin_post("/api/posts/", "{\"title\": ".to_string()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap().get("code").unwrap(), "INVALID_JSON");
+}
+
+#[tokio::test]
+async fn create_then_list_post_fields_success() {
+    let data = json!({
+        "title": "testPostFieldList",
+        "slug": "test-post-field-list",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    for name in ["test_str", "test_str_2"] {
+        let data = json!({
+            "name": name,
+            "type": "str",
+            "str_value": "test-str-feild",
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/test-post-field-list/fields/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let (status_code, body) = get("/api/posts/test-post-field-list/fields/").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let count = body.get("count").unwrap().as_u64().unwrap();
+    assert!(count == 2);
+    let name = body["results"][0].get("name").unwrap().as_str().unwrap();
+    assert!(name == "test_str");
+
+    let (status_code, body) = get("/api/posts/test-post-field-list?with_fields=false").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let count = body.get("fields").unwrap().as_array().unwrap().len();
+    assert!(count == 0);
+}
+
+#[tokio::test]
+async fn get_post_field_visibility_success() {
+    let data = json!({
+        "title": "testPostFieldHidden",
+        "slug": "test-post-field-hidden",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "private",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "name": "test_str",
+        "type": "str",
+        "str_value": "test-str-feild",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/test-post-field-hidden/fields/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let url = "/api/posts/test-post-field-hidden/fields/test_str";
+    let (status_code, _) = get(url).await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+    let (status_code, _) = admin_get(url).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-field-hidden").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = admin_get("/api/posts/test-post-field-hidden/fields/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body["results"].as_array().unwrap();
+    assert!(results.iter().all(|f| f["name"] != "trash_status"));
+
+    let url = "/api/posts/test-post-field-hidden/fields/trash_status";
+    let (status_code, _) = admin_get(url).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let url = "/api/posts/test-post-field-hidden?private=true";
+    let (status_code, body) = admin_get(url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let fields = body.unwrap()["fields"].as_array().unwrap().clone();
+    assert!(fields.iter().all(|f| f["name"] != "trash_status"));
+}
+
+#[tokio::test]
+async fn create_then_search_posts_success() {
+    let data = json!({
+        "title": "testSearchPost",
+        "slug": "test-search-post",
+        "created": 1666666666,
+        "text": "some text with a searchable_keyword inside",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/search?q=searchable_keyword").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let count = body.get("all_count").unwrap().as_u64().unwrap();
+    assert!(count > 0);
+    let results = body.get("results").unwrap().as_array().unwrap();
+    let snippet = results[0].get("snippet").unwrap().as_str().unwrap();
+    assert!(snippet.contains("searchable_keyword"));
+
+    let (status_code, _) = get("/api/search").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn create_compressed_then_search_posts_by_title_success() {
+    let data = json!({
+        "title": "testCompressedSearchTitle",
+        "slug": "test-compressed-search-post",
+        "created": 1666666666,
+        "text": "compressed text with a compressed_body_keyword inside",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = compressed_admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/search?q=compressed_body_keyword").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["all_count"], 0);
+
+    let (status_code, body) = get("/api/search?q=testCompressedSearchTitle").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["all_count"], 1);
+    let snippet = body["results"][0]["snippet"].as_str().unwrap();
+    assert!(snippet.contains("compressed_body_keyword"));
+}
+
+#[tokio::test]
+async fn create_then_list_posts_excerpt_only_success() {
+    let data = json!({
+        "title": "testPostExcerpt",
+        "slug": "test-post-excerpt",
+        "created": 1666666666,
+        "text": "intro text<!--more-->rest of the post",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-post-excerpt").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body.get("excerpt").unwrap().as_str().unwrap(), "intro text");
+    assert!(body.get("text").unwrap().is_string());
+
+    let (status_code, body) = get("/api/posts/?page=1&page_size=10&order_by=-cid&excerpt_only=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(!results.is_empty());
+    for post in results {
+        assert!(post.get("text").unwrap().is_null());
+        assert!(post.get("excerpt").is_some());
+    }
+}
+
+#[tokio::test]
+async fn create_then_get_password_post_by_slug() {
+    let data = json!({
+        "title": "testPostPassword",
+        "slug": "test-post-password",
+        "created": 1666666666,
+        "text": "secretText",
+        "status": "publish",
+        "password": "postPassword",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-post-password").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("protected").unwrap(), true);
+    assert_ne!(body.get("text").unwrap(), "secretText");
+    assert!(body.get("password").unwrap().is_null());
+
+    let (status_code, body) = get("/api/posts/test-post-password?password=wrongPassword").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("protected").unwrap(), true);
+    assert_ne!(body.get("text").unwrap(), "secretText");
+
+    let (status_code, body) = get("/api/posts/test-post-password?password=postPassword").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("protected").unwrap(), false);
+    assert_eq!(body.get("text").unwrap(), "secretText");
+
+    let (status_code, body) = admin_get("/api/posts/test-post-password").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("protected").unwrap(), false);
+    assert_eq!(body.get("text").unwrap(), "secretText");
+}
+
+#[tokio::test]
+async fn create_then_publish_scheduled_post_success() {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let data = json!({
+        "title": "testPostScheduled",
+        "slug": "test-post-scheduled",
+        "created": now + 2,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, _) = get("/api/posts/test-post-scheduled").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, body) = admin_get("/api/posts/test-post-scheduled?private=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("status").unwrap(), "waiting");
+
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(publish_posts().await > 0);
+
+    let (status_code, body) = get("/api/posts/test-post-scheduled").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("status").unwrap(), "publish");
+}
+
+#[tokio::test]
+async fn create_then_set_post_sticky_success() {
+    let data = json!({
+        "title": "testPostSticky",
+        "slug": "test-post-sticky",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({ "sticky": true, "order": 100 }).to_string();
+    let (status_code, _) = admin_post("/api/posts/test-post-sticky/sticky", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/posts/?page=1&page_size=10&order_by=slug").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(results[0].get("slug").unwrap(), "test-post-sticky");
+    assert_eq!(results[0].get("sticky").unwrap(), true);
+
+    let data = json!({ "sticky": false }).to_string();
+    let (status_code, _) = admin_post("/api/posts/test-post-sticky/sticky", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/posts/test-post-sticky").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("sticky").unwrap(), false);
+}
+
+#[tokio::test]
+async fn create_then_list_related_posts_success() {
+    let data = json!({"name": "testRelatedTag", "slug": "test-related-tag"}).to_string();
+    let (status_code, _) = admin_post("/api/tags/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    for slug in ["test-post-related-1", "test-post-related-2", "test-post-related-3"] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    for slug in ["test-post-related-1", "test-post-related-2"] {
+        let data = json!({ "slug": slug }).to_string();
+        let (status_code, _) = admin_post("/api/tags/test-related-tag/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let (status_code, body) = get("/api/posts/test-post-related-1/related?limit=5").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    let slugs: Vec<&str> = results
+        .iter()
+        .map(|p| p.get("slug").unwrap().as_str().unwrap())
+        .collect();
+    assert!(slugs.contains(&"test-post-related-2"));
+    assert!(!slugs.contains(&"test-post-related-1"));
+    assert!(!slugs.contains(&"test-post-related-3"));
+
+    let (status_code, _) = get("/api/posts/test-post-related-1/related?limit=100").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn create_then_set_post_tags_success() {
+    let data = json!({
+        "title": "testPostTags",
+        "slug": "test-post-tags",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({ "tags": ["test-post-tags-1", "test-post-tags-2"] }).to_string();
+    let (status_code, body) = admin_post("/api/posts/test-post-tags/tags", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("count").unwrap(), 2);
+
+    let data = json!({ "tags": ["test-post-tags-2", "test-post-tags-3"] }).to_string();
+    let (status_code, body) = admin_post("/api/posts/test-post-tags/tags", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("count").unwrap(), 2);
+
+    let (status_code, body) = get("/api/tags/test-post-tags-1").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("count").unwrap(), 0);
+
+    let (status_code, body) = get("/api/tags/test-post-tags-2").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("count").unwrap(), 1);
+
+    let (status_code, body) = get("/api/posts/test-post-tags").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let slugs: Vec<&str> = body
+        .get("tags")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t.get("slug").unwrap().as_str().unwrap())
+        .collect();
+    assert!(slugs.contains(&"test-post-tags-2"));
+    assert!(slugs.contains(&"test-post-tags-3"));
+    assert!(!slugs.contains(&"test-post-tags-1"));
+}
+
+#[tokio::test]
+async fn list_posts_order_by_created_success() {
+    for (slug, created) in [
+        ("test-post-order-old", 1666666666),
+        ("test-post-order-new", 1666666777),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": created,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+
+        let data = json!({ "tags": ["test-post-order-tag"] }).to_string();
+        let url = format!("/api/posts/{slug}/tags");
+        let (status_code, _) = admin_post(&url, data).await;
+        assert_eq!(status_code, StatusCode::OK);
+    }
+
+    let (status_code, body) = get("/api/tags/test-post-order-tag/posts/?order_by=-created").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["results"][0]["slug"], "test-post-order-new");
+
+    let (status_code, body) = get("/api/tags/test-post-order-tag/posts/?order_by=created").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["results"][0]["slug"], "test-post-order-old");
+
+    let (status_code, _) = get("/api/posts/?order_by=-title").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn list_posts_with_cursor_success() {
+    for slug in ["test-post-cursor-1", "test-post-cursor-2", "test-post-cursor-3"] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let (status_code, body) = get("/api/posts/?page_size=2&order_by=cid&after=0").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let cursor = body.get("next_cursor").unwrap().as_u64().unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].get("cid").unwrap().as_u64().unwrap(), cursor);
+
+    let url = format!("/api/posts/?page_size=2&order_by=cid&after={}", cursor);
+    let (status_code, body) = get(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(results[0].get("cid").unwrap().as_u64().unwrap() > cursor);
+
+    let (status_code, _) = get("/api/posts/?order_by=slug&after=1").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn list_own_posts_success() {
+    let data = json!({"name": "own_posts", "mail": "own_posts@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (uid, _) = login("own_posts@test.local", "password").await;
+    let data = json!({"name": "own_posts", "screenName": "own_posts", "mail": "own_posts@test.local", "url": "http://127.0.0.1", "group": "contributor"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/users/{uid}"), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, token) = login("own_posts@test.local", "password").await;
+
+    let data = json!({
+        "title": "testOwnPost",
+        "slug": "test-own-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = token_request(Method::POST, "/api/posts/", &token, data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testOwnPostOther",
+        "slug": "test-own-post-other",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let url = "/api/me/posts?status=waiting";
+    let (status_code, body) = token_request(Method::GET, url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(body.get("all_count").unwrap().as_u64().unwrap(), 1);
+    assert_eq!(results[0]["slug"], "test-own-post");
+    assert!(results.iter().all(|p| p["author_id"] == uid));
+    assert_eq!(body["links"]["total_pages"], 1);
+
+    let data = json!({
+        "title": "testOwnPostDraft",
+        "slug": "test-own-post-draft",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "draft",
+    })
+    .to_string();
+    let (status_code, _) = token_request(Method::POST, "/api/posts/", &token, data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let url = "/api/me/posts?status=draft";
+    let (status_code, body) = token_request(Method::GET, url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["all_count"], 1);
+    assert_eq!(body["results"][0]["slug"], "test-own-post-draft");
+    assert_eq!(body["results"][0]["status"], "draft");
+
+    let (status_code, _) = get("/api/posts/test-own-post-draft").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, _) = get("/api/me/posts").await;
+    assert_ne!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn check_slug_available_success() {
+    let (status_code, body) =
+        admin_get("/api/posts/slug-available?slug=Test%20Slug%20Available").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["slug"], "test-slug-available");
+    assert_eq!(body["available"], true);
+
+    let data = json!({
+        "title": "testSlugAvailable",
+        "slug": "test-slug-available",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = admin_get("/api/posts/slug-available?slug=test-slug-available").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["available"], false);
+
+    let (status_code, _) = admin_get("/api/posts/slug-available?slug=%21%21").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn check_slug_available_transliterate_success() {
+    let (status_code, body) =
+        admin_get("/api/posts/slug-available?slug=Caf%C3%A9%20d%C3%A9j%C3%A0%20vu").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["slug"], "cafe-deja-vu");
+
+    let (status_code, body) =
+        admin_get("/api/posts/slug-available?slug=%E4%BD%A0%E5%A5%BD%E4%B8%96%E7%95%8C").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["slug"], "ni-hao-shi-jie");
+}
+
+#[tokio::test]
+async fn create_post_with_categories_and_tags_success() {
+    let data = json!({"name": "testAtomicCategory", "slug": "test-atomic-category"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testAtomicPost",
+        "slug": "test-atomic-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "categories": ["test-atomic-category"],
+        "tags": ["test-atomic-tag", "test-atomic-tag"],
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let body = body.unwrap();
+    assert_eq!(body["categories"][0]["slug"], "test-atomic-category");
+    assert_eq!(body["tags"].as_array().unwrap().len(), 1);
+    assert_eq!(body["tags"][0]["slug"], "test-atomic-tag");
+
+    let data = json!({
+        "title": "testAtomicPostRollback",
+        "slug": "test-atomic-post-rollback",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "categories": ["test-atomic-missing-category"],
+        "tags": ["test-atomic-rollback-tag"],
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = admin_get("/api/posts/test-atomic-post-rollback").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+    let (status_code, _) = get("/api/tags/test-atomic-rollback-tag").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn get_post_siblings_success() {
+    let data =
+        json!({"name": "testSiblingsCategory", "slug": "test-siblings-category"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    for (slug, status) in [
+        ("test-siblings-1", "publish"),
+        ("test-siblings-2", "publish"),
+        ("test-siblings-3", "publish"),
+        ("test-siblings-4", "hidden"),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": status,
+            "categories": ["test-siblings-category"],
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let url = "/api/posts/test-siblings-2/siblings?category=test-siblings-category";
+    let (status_code, body) = get(url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["prev"]["slug"], "test-siblings-1");
+    assert_eq!(body["next"]["slug"], "test-siblings-3");
+
+    let url = "/api/posts/test-siblings-3/siblings?category=test-siblings-category";
+    let (status_code, body) = get(url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.unwrap()["next"].is_null());
+
+    let url = "/api/posts/test-siblings-1/siblings?category=test-siblings-missing";
+    let (status_code, _) = get(url).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn get_post_not_modified_success() {
+    let data = json!({
+        "title": "testPostEtag",
+        "slug": "test-post-etag",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, headers) = get_headers("/api/posts/test-post-etag").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let etag = headers.get(header::ETAG).unwrap().to_str().unwrap();
+    let last_modified = headers
+        .get(header::LAST_MODIFIED)
+        .unwrap()
+        .to_str()
+        .unwrap();
+
+    let (status_code, headers) =
+        get_with_header("/api/posts/test-post-etag", header::IF_NONE_MATCH, etag).await;
+    assert_eq!(status_code, StatusCode::NOT_MODIFIED);
+    assert_eq!(headers.get(header::ETAG).unwrap(), etag);
+
+    let (status_code, _) = get_with_header(
+        "/api/posts/test-post-etag",
+        header::IF_MODIFIED_SINCE,
+        last_modified,
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::NOT_MODIFIED);
+
+    let (status_code, _) = get_with_header(
+        "/api/posts/test-post-etag",
+        header::IF_NONE_MATCH,
+        "\"stale\"",
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn list_posts_with_links_success() {
+    let data = json!({
+        "title": "testPostLinks",
+        "slug": "test-post-links",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/?page=1&page_size=1").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let all_count = body["all_count"].as_u64().unwrap();
+    let links = &body["links"];
+    assert_eq!(links["total_pages"].as_u64().unwrap(), all_count);
+    assert_eq!(links["last"].as_u64().unwrap(), all_count);
+    assert!(links["prev"].is_null());
+    if all_count > 1 {
+        assert_eq!(links["next"], 2);
+    }
+}
+
+#[tokio::test]
+async fn list_posts_json_api_format_success() {
+    let data = json!({
+        "title": "testPostJsonApi",
+        "slug": "test-post-json-api",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/?page=1&page_size=1&format=jsonapi").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert!(body["data"].is_array());
+    assert!(body["meta"]["all_count"].as_u64().unwrap() >= 1);
+    assert!(body["meta"].get("results").is_none());
+    assert!(body["links"]["total_pages"].is_u64());
+
+    let (status_code, body) = get("/api/posts/test-post-json-api?format=jsonapi").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["data"]["slug"], "test-post-json-api");
+}
+
+#[tokio::test]
+async fn create_post_with_invalid_created_failed() {
+    for (slug, created) in [
+        ("test-post-created-zero", 0u64),
+        ("test-post-created-far", 32503680000),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": created,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, body) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(body.unwrap().get("field").unwrap(), "created");
+    }
+
+    let data = json!({
+        "title": "testPostCreatedOmitted",
+        "slug": "test-post-created-omitted",
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn import_posts_success() {
+    let post = |slug: &str| {
+        json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testImportText",
+            "status": "publish",
+            "tags": ["test-import-tag"],
+        })
+    };
+    let data = json!({
+        "posts": [post("test-import-1"), post("test-import-1"), post("Bad Slug")],
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/import", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["created_count"], 1);
+    assert_eq!(body["skipped"][0]["index"], 1);
+    assert_eq!(body["failed"][0]["index"], 2);
+
+    let data = json!({
+        "posts": [post("test-import-1")],
+        "on_conflict": "suffix",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/import", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["created"][0]["slug"], "test-import-1-1");
+
+    let (status_code, body) = get("/api/posts/test-import-1-1").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["created"], 1666666666);
+}
+
+#[tokio::test]
+async fn export_posts_success() {
+    let data = json!({
+        "title": "testPostExport",
+        "slug": "test-post-export",
+        "created": 1666666666,
+        "text": "testExportText",
+        "status": "publish",
+        "tags": ["test-export-tag"],
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) =
+        admin_get("/api/posts/export?status=publish&since=1666666666&until=1666666666").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["version"], 1);
+    let posts = body["posts"].as_array().unwrap();
+    let post = posts
+        .iter()
+        .find(|p| p["slug"] == "test-post-export")
+        .unwrap();
+    assert_eq!(post["tags"][0], "test-export-tag");
+    assert_eq!(post["text"], "testExportText");
+
+    let (status_code, _) = get("/api/posts/export").await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn create_post_with_duplicate_slug_conflict() {
+    let data = json!({
+        "title": "testPostConflict",
+        "slug": "test-post-conflict",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data.clone()).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CONFLICT);
+    let body = body.unwrap();
+    assert_eq!(body["code"], "ALREADY_EXISTS");
+    assert_eq!(body["field"], "slug");
+}
+
+#[tokio::test]
+async fn get_post_render_html_success() {
+    let data = json!({
+        "title": "testPostRender",
+        "slug": "test-post-render",
+        "created": 1666666666,
+        "text": "<!--markdown-->intro **text**\n\n<!--more-->\n\n<script>alert(1)</script>full text",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-post-render?render=html").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let text = body["text"].as_str().unwrap();
+    assert!(text.starts_with("<!--markdown-->"));
+    assert_eq!(body["excerpt_html"], "<p>intro <strong>text</strong></p>\n");
+    let content_html = body["content_html"].as_str().unwrap();
+    assert!(content_html.contains("full text"));
+    assert!(!content_html.contains("<script>"));
+
+    let (status_code, body) = get("/api/posts/test-post-render").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.unwrap().get("content_html").is_none());
+
+    let (status_code, _) = get("/api/posts/test-post-render?render=pdf").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn create_post_sanitized_for_contributor_success() {
+    let data = json!({"name": "sanitize_posts", "mail": "sanitize_posts@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (uid, _) = login("sanitize_posts@test.local", "password").await;
+    let data = json!({"name": "sanitize_posts", "screenName": "sanitize_posts", "mail": "sanitize_posts@test.local", "url": "http://127.0.0.1", "group": "contributor"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/users/{uid}"), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, token) = login("sanitize_posts@test.local", "password").await;
+
+    let text = "<p>hello</p><script>alert(1)</script>";
+    let data = json!({
+        "title": "testContributorScript",
+        "slug": "test-contributor-script",
+        "created": 1666666666,
+        "text": text,
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = token_request(Method::POST, "/api/posts/", &token, data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testAdminScript",
+        "slug": "test-admin-script",
+        "created": 1666666666,
+        "text": text,
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = admin_get("/api/posts/test-contributor-script?private=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["text"], "<p>hello</p>");
+
+    let (status_code, body) = admin_get("/api/posts/test-admin-script").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["text"], text);
+}
+
+#[tokio::test]
+async fn list_posts_with_tied_created_stable_success() {
+    let mut cids = vec![];
+    for slug in ["test-post-tied-first", "test-post-tied-second"] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1333333333,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, body) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+        cids.push(body.unwrap()["cid"].as_u64().unwrap());
+    }
+
+    for (order_by, expected) in [
+        ("created", [cids[0], cids[1]]),
+        ("-created", [cids[1], cids[0]]),
+    ] {
+        let url = format!("/api/posts/?page=1&page_size=1000&order_by={}", order_by);
+        for _ in 0..2 {
+            let (status_code, body) = get(&url).await;
+            assert_eq!(status_code, StatusCode::OK);
+
+            let tied: Vec<u64> = body.unwrap()["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|post| post["created"] == 1333333333)
+                .map(|post| post["cid"].as_u64().unwrap())
+                .collect();
+            assert_eq!(tied, expected);
+        }
+    }
+}
+
+#[tokio::test]
+async fn list_posts_with_oversized_page_size_clamped_success() {
+    let (status_code, body) = get("/api/posts/?page=1&page_size=1000000").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body["page_size"], 1000);
+    assert!(body["results"].as_array().unwrap().len() <= 1000);
+}
+
+#[tokio::test]
+async fn modify_post_with_stale_modified_conflict() {
+    let data = json!({
+        "title": "testPostStale",
+        "slug": "test-post-stale",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-post-stale").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let modified = body.unwrap()["modified"].as_u64().unwrap();
+
+    // both edits land within the same second as the create
+    let data = |text: &str| {
+        json!({
+            "title": "testPostStale",
+            "slug": "test-post-stale",
+            "created": 1666666666,
+            "text": text,
+            "status": "publish",
+            "modified": modified,
+        })
+        .to_string()
+    };
+    let (status_code, _) = admin_patch("/api/posts/test-post-stale", data("first edit")).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = admin_patch("/api/posts/test-post-stale", data("second edit")).await;
+    assert_eq!(status_code, StatusCode::CONFLICT);
+    assert_eq!(body.unwrap()["code"], "EDIT_CONFLICT");
+
+    let (status_code, body) = get("/api/posts/test-post-stale").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["text"], "first edit");
+}
+
+#[tokio::test]
+async fn list_posts_with_created_range_success() {
+    for (slug, created) in [
+        ("test-post-range-before", 1555555500),
+        ("test-post-range-inside", 1555555550),
+        ("test-post-range-after", 1555555600),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": created,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let (status_code, body) =
+        get("/api/posts/?created_after=1555555520&created_before=1555555580").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body["all_count"], 1);
+    assert_eq!(body["results"][0]["slug"], "test-post-range-inside");
+
+    let (status_code, _) =
+        get("/api/posts/?created_after=1555555580&created_before=1555555520").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_post_include_author_success() {
+    let data = json!({
+        "title": "testPostIncludeAuthor",
+        "slug": "test-post-include-author",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = admin_get("/api/me").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let me = body.unwrap();
+
+    let (status_code, body) = get("/api/posts/test-post-include-author?include=author").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let author = &body.unwrap()["author"];
+    assert_eq!(author["uid"], me["uid"]);
+    assert_eq!(author["screen_name"], me["screen_name"]);
+    assert!(author.get("mail").is_none());
+    assert!(author.get("password").is_none());
+
+    let (status_code, body) = get("/api/posts/?page_size=1000&include=author").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let post = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["slug"] == "test-post-include-author")
+        .unwrap();
+    assert_eq!(post["author"]["screen_name"], me["screen_name"]);
+
+    let (status_code, body) = get("/api/posts/test-post-include-author").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.unwrap().get("author").is_none());
+
+    let (status_code, _) = get("/api/posts/test-post-include-author?include=mail").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_posts_by_slugs_batch_success() {
+    for (slug, status) in [
+        ("test-post-batch-1", "publish"),
+        ("test-post-batch-2", "publish"),
+        ("test-post-batch-draft", "draft"),
+    ] {
+        let data = json!({
+            "title": "testPostBatch",
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": status,
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let slugs = [
+        "test-post-batch-2",
+        "test-post-batch-missing",
+        "test-post-batch-draft",
+        "test-post-batch-1",
+    ];
+    let data = json!({ "slugs": slugs }).to_string();
+    let (status_code, body) = post("/api/posts/batch", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["count"], 4);
+    assert_eq!(body["results"][0]["slug"], "test-post-batch-2");
+    assert!(body["results"][1].is_null());
+    assert!(body["results"][2].is_null());
+    assert_eq!(body["results"][3]["slug"], "test-post-batch-1");
+
+    let data = json!({ "slugs": slugs, "omit_missing": true }).to_string();
+    let (status_code, body) = post("/api/posts/batch", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["count"], 2);
+    assert_eq!(body["results"][0]["slug"], "test-post-batch-2");
+
+    let data = json!({ "slugs": slugs, "private": true }).to_string();
+    let (status_code, body) = admin_post("/api/posts/batch", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["results"][2]["slug"], "test-post-batch-draft");
+
+    let slugs: Vec<String> = (0..51).map(|i| format!("test-post-batch-{i}")).collect();
+    let data = json!({ "slugs": slugs }).to_string();
+    let (status_code, _) = post("/api/posts/batch", data).await;
+    assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,70 @@
+use axum::http::{header, Method, StatusCode};
+use serde_json::json;
+
+mod common;
+use common::{allowed_methods, read_only_request, trailing_slash_request};
+
+#[tokio::test]
+async fn read_only_write_routes_not_mounted() {
+    let data = json!({
+        "title": "testPostReadOnly",
+        "slug": "test-post-read-only",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let status_code = read_only_request(Method::POST, "/api/posts/", data).await;
+    assert!(status_code == StatusCode::NOT_FOUND || status_code == StatusCode::METHOD_NOT_ALLOWED);
+
+    let data = json!({"mail": "admin@local.host", "password": "admin"}).to_string();
+    let status_code = read_only_request(Method::POST, "/api/users/token", data).await;
+    assert!(status_code == StatusCode::NOT_FOUND || status_code == StatusCode::METHOD_NOT_ALLOWED);
+
+    let status_code =
+        read_only_request(Method::DELETE, "/api/categories/default", String::new()).await;
+    assert!(status_code == StatusCode::NOT_FOUND || status_code == StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn read_only_read_routes_mounted() {
+    let status_code = read_only_request(Method::GET, "/api/posts/", String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let status_code = read_only_request(Method::GET, "/api/categories/", String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn options_lists_allowed_methods() {
+    let (status_code, allow) = allowed_methods("/api/attachments/", true).await;
+    assert_eq!(status_code, StatusCode::NO_CONTENT);
+    let allow = allow.unwrap();
+    assert!(allow.contains("GET"));
+    assert!(!allow.contains("POST"));
+
+    let (status_code, allow) = allowed_methods("/api/attachments/", false).await;
+    assert_eq!(status_code, StatusCode::NO_CONTENT);
+    let allow = allow.unwrap();
+    assert!(allow.contains("GET"));
+    assert!(allow.contains("POST"));
+
+    let (status_code, _) = allowed_methods("/api/not-exist", false).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn unsupported_method_not_allowed() {
+    let (status_code, headers) =
+        trailing_slash_request(Method::PUT, "/api/posts/test-post-405", true).await;
+    assert_eq!(status_code, StatusCode::METHOD_NOT_ALLOWED);
+    let allow = headers.get(header::ALLOW).unwrap().to_str().unwrap();
+    assert!(allow.contains("GET"));
+    assert!(allow.contains("PATCH"));
+    assert!(allow.contains("DELETE"));
+    assert!(allow.contains("OPTIONS"));
+
+    let status_code =
+        read_only_request(Method::DELETE, "/api/posts/test-post-405", String::new()).await;
+    assert_eq!(status_code, StatusCode::METHOD_NOT_ALLOWED);
+}
@@ -0,0 +1,36 @@
+use sea_orm::{ConnectionTrait, Database, DatabaseBackend, Statement};
+
+use rumo::check_schema;
+
+#[tokio::test]
+async fn check_incomplete_schema_failed() {
+    let conn = Database::connect("sqlite::memory:").await.unwrap();
+    let stmt = r#"
+        CREATE TABLE typecho_contents (
+            "cid" INTEGER NOT NULL PRIMARY KEY,
+            "title" varchar(150) default NULL,
+            "slug" varchar(150) default NULL,
+            "created" int(10) default '0',
+            "modified" int(10) default '0',
+            "text" text,
+            "order" int(10) default '0',
+            "authorId" int(10) default '0',
+            "template" varchar(32) default NULL,
+            "type" varchar(16) default 'post',
+            "status" varchar(16) default 'publish',
+            "password" varchar(32) default NULL,
+            "commentsNum" int(10) default '0',
+            "allowComment" char(1) default '0',
+            "allowPing" char(1) default '0',
+            "parent" int(10) default '0'
+        );
+    "#;
+    conn.execute(Statement::from_string(DatabaseBackend::Sqlite, stmt))
+        .await
+        .unwrap();
+
+    let err = check_schema(&conn).await.unwrap_err();
+    assert!(err.contains("typecho_contents.allowFeed"));
+    assert!(!err.contains("typecho_contents.allowPing"));
+    assert!(err.contains("typecho_users (table)"));
+}
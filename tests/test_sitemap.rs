@@ -0,0 +1,68 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+mod common;
+use common::{admin_post, get, get_text};
+
+#[tokio::test]
+async fn sitemap_skip_protected_posts() {
+    for (slug, password) in [
+        ("test-sitemap-public", None),
+        ("test-sitemap-protected", Some("pw")),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testSitemapText",
+            "status": "publish",
+            "password": password,
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let (status_code, headers, body) = get_text("/api/sitemap.xml").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let content_type = headers.get("content-type").unwrap().to_str().unwrap();
+    assert!(content_type.starts_with("application/xml"));
+    assert!(body.contains("<loc>http://example.com/posts/test-sitemap-public</loc>"));
+    assert!(!body.contains("test-sitemap-protected"));
+
+    let (status_code, _, _) = get_text("/api/sitemaps/2").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn list_slugs_success() {
+    let data = json!({
+        "title": "testSlugsPost",
+        "slug": "test-slugs-post",
+        "created": 1666666666,
+        "text": "testSlugsText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let modified = body.unwrap().get("modified").unwrap().as_u64().unwrap();
+
+    let (status_code, body) = get("/api/slugs").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let posts = body.get("posts").unwrap().as_array().unwrap();
+    assert!(posts
+        .iter()
+        .any(|p| p.get("slug").unwrap() == "test-slugs-post"));
+    assert!(body.get("pages").unwrap().is_array());
+
+    let (status_code, body) = get(&format!("/api/slugs?since={modified}")).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let posts = body.get("posts").unwrap().as_array().unwrap();
+    assert!(!posts
+        .iter()
+        .any(|p| p.get("slug").unwrap() == "test-slugs-post"));
+}
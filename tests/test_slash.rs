@@ -0,0 +1,31 @@
+use axum::http::{header, Method, StatusCode};
+
+mod common;
+use common::{get, trailing_slash_request};
+
+#[tokio::test]
+async fn trailing_slash_redirect_success() {
+    let (status_code, _) = get("/api/posts/").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, headers) = trailing_slash_request(Method::GET, "/api/posts", true).await;
+    assert_eq!(status_code, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(headers.get(header::LOCATION).unwrap(), "/api/posts/");
+
+    let (status_code, headers) =
+        trailing_slash_request(Method::POST, "/api/posts?page=2", true).await;
+    assert_eq!(status_code, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(headers.get(header::LOCATION).unwrap(), "/api/posts/?page=2");
+
+    let (status_code, _) = trailing_slash_request(Method::GET, "/api/posts/", true).await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn trailing_slash_redirect_disabled() {
+    let (status_code, _) = trailing_slash_request(Method::GET, "/api/posts", false).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, _) = trailing_slash_request(Method::GET, "/api/unknown/", true).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,37 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+mod common;
+use common::{admin_get, admin_post, admin_post_file, get, get_multipart};
+
+#[tokio::test]
+async fn get_stats_success() {
+    let data = json!({
+        "title": "testStatsPost",
+        "slug": "test-stats-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = get_multipart("testFileStats.png", "image/png");
+    let (status_code, _) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = admin_get("/api/stats").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert!(body["posts"]["publish"].as_u64().unwrap() > 0);
+    assert!(body["users"]["administrator"].as_u64().unwrap() > 0);
+    assert!(body["attachments"]["count"].as_u64().unwrap() > 0);
+    assert!(body["attachments"]["size"].as_u64().unwrap() > 0);
+    assert!(body["comments"].is_object());
+    assert!(body["password_rehashes"].is_u64());
+
+    let (status_code, _) = get("/api/stats").await;
+    assert_ne!(status_code, StatusCode::OK);
+}
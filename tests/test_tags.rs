@@ -2,7 +2,7 @@ use axum::http::StatusCode;
 use serde_json::json;
 
 mod common;
-use common::{admin_post, admin_delete, admin_patch, get};
+use common::{admin_post, admin_delete, admin_patch, get, post, set_meta_count};
 
 #[tokio::test]
 async fn create_then_list_tags_success() {
@@ -150,3 +150,34 @@ async fn create_then_delete_tag_posts_success() {
 
     assert!(new_count < count);
 }
+
+#[tokio::test]
+async fn recount_metas_success() {
+    let data = json!({
+        "title": "testRecountMetas",
+        "slug": "test-recount-metas",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "tags": ["test-recount-tag"],
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/tags/test-recount-tag").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["count"], 1);
+    set_meta_count(body["mid"].as_u64().unwrap(), 99).await;
+
+    let (status_code, _) = post("/api/admin/metas/recount", String::new()).await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+    let (status_code, body) = admin_post("/api/admin/metas/recount", String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.unwrap()["corrected"].as_u64().unwrap() >= 1);
+
+    let (status_code, body) = get("/api/tags/test-recount-tag").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["count"], 1);
+}
@@ -1,8 +1,14 @@
-use axum::http::StatusCode;
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
 use serde_json::json;
 
 mod common;
-use common::{admin_delete, admin_get, admin_patch, get, post, admin_post};
+use common::{
+    admin_delete, admin_get, admin_logout_then_get, admin_patch, admin_post, admin_post_file, get,
+    get_multipart, login, post, post_concurrently, post_many, request_password_reset_token,
+    token_request,
+};
 
 #[tokio::test]
 async fn index() {
@@ -24,8 +30,14 @@ async fn login_success() {
     assert_eq!(status_code, StatusCode::CREATED);
 
     let data = json!({"mail": "login_success@test.local", "password": "password"}).to_string();
-    let (status_code, _) = post("/api/users/token", data).await;
+    let (status_code, body) = post("/api/users/token", data).await;
     assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert!(body.get("access_token").is_some());
+    assert!(body.get("uid").is_some());
+    assert_eq!(body.get("screenName").unwrap(), "login_test");
+    assert_eq!(body.get("group").unwrap(), "subscriber");
 }
 
 #[tokio::test]
@@ -159,3 +171,401 @@ async fn create_then_delete_user_option_success() {
     let (status_code, _) = admin_get("/api/users/1/options/delete_option").await;
     assert_eq!(status_code, StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn user_option_reserved_name_failed() {
+    for name in [
+        "apiToken:forged",
+        "refreshToken:forged",
+        "passwordReset:forged",
+        "passwordChangedAt",
+        "avatar",
+    ] {
+        let data = json!({"name": name, "value": "1"}).to_string();
+        let (status_code, _) = admin_post("/api/users/1/options/", data).await;
+        assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    }
+
+    let (status_code, _) = admin_delete("/api/users/1/options/passwordChangedAt").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    let data = json!({"value": "0"}).to_string();
+    let (status_code, _) = admin_patch("/api/users/1/options/passwordChangedAt", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, body) = admin_get("/api/users/1/options/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    for option in body["results"].as_array().unwrap() {
+        let name = option["name"].as_str().unwrap();
+        assert!(!name.starts_with("refreshToken:"));
+        assert!(!name.starts_with("apiToken:"));
+    }
+}
+
+#[tokio::test]
+async fn login_then_refresh_token_success() {
+    let data = json!({"name": "refresh_test","mail": "refresh_test@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"mail": "refresh_test@test.local", "password": "password"}).to_string();
+    let (status_code, body) = post("/api/users/token", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let refresh_token = body.get("refresh_token").unwrap().as_str().unwrap();
+
+    let data = json!({ "refresh_token": refresh_token }).to_string();
+    let (status_code, body) = post("/api/refresh", data.clone()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert!(body.get("access_token").is_some());
+    let new_refresh_token = body.get("refresh_token").unwrap().as_str().unwrap();
+    assert_ne!(new_refresh_token, refresh_token);
+
+    let (status_code, _) = post("/api/refresh", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn refresh_token_concurrently_once_success() {
+    let data = json!({"name": "refresh_race","mail": "refresh_race@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"mail": "refresh_race@test.local", "password": "password"}).to_string();
+    let (status_code, body) = post("/api/users/token", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let refresh_token = body.unwrap()["refresh_token"].as_str().unwrap().to_string();
+
+    let data = json!({ "refresh_token": refresh_token }).to_string();
+    let status_codes = post_concurrently("/api/refresh", data, 4).await;
+    let accepted = status_codes
+        .iter()
+        .filter(|s| **s == StatusCode::OK)
+        .count();
+    assert_eq!(accepted, 1);
+}
+
+#[tokio::test]
+async fn logout_then_get_user_failed() {
+    let (logout_status_code, status_code) = admin_logout_then_get("/api/users/1").await;
+    assert_eq!(logout_status_code, StatusCode::OK);
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn login_with_wrong_password_throttled() {
+    let data = json!({"mail": "admin@local.host", "password": "wrong_password"}).to_string();
+    let status_codes = post_many("/api/users/token", data, 6).await;
+    assert_eq!(status_codes[..5], [StatusCode::UNAUTHORIZED; 5]);
+    assert_eq!(status_codes[5], StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn request_password_reset_success() {
+    let data = json!({"mail": "admin@local.host"}).to_string();
+    let (status_code, body) = post("/api/password-reset/request", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let exist_body = body.unwrap();
+
+    let data = json!({"mail": "not_exist@local.host"}).to_string();
+    let (status_code, body) = post("/api/password-reset/request", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap(), exist_body);
+}
+
+#[tokio::test]
+async fn confirm_password_reset_with_invalid_token_failed() {
+    let data = json!({"token": "1.invalid.token", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/password-reset/confirm", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn confirm_password_reset_concurrently_once_success() {
+    let data = json!({"name": "reset_race","mail": "reset_race@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let token = request_password_reset_token("reset_race@test.local").await;
+    let data = json!({"token": token, "password": "new_password"}).to_string();
+    let status_codes = post_concurrently("/api/password-reset/confirm", data, 4).await;
+    let accepted = status_codes
+        .iter()
+        .filter(|s| **s == StatusCode::OK)
+        .count();
+    assert_eq!(accepted, 1);
+
+    let data = json!({"mail": "reset_race@test.local", "password": "new_password"}).to_string();
+    let (status_code, _) = post("/api/users/token", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn register_return_created_user() {
+    let data = json!({"name": "register_created","mail": "register_created@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, body) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let body = body.unwrap();
+    assert!(body.get("uid").unwrap().as_u64().unwrap() > 0);
+    assert_eq!(body.get("name").unwrap(), "register_created");
+    assert!(body.get("password").is_none());
+}
+
+#[tokio::test]
+async fn register_with_exist_name_or_mail_failed() {
+    let data = json!({"name": "register_exist","mail": "register_exist@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"name": "register_exist","mail": "register_exist_2@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, body) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap().get("msg").unwrap(), "name already exist");
+
+    let data = json!({"name": "register_exist_2","mail": "register_exist@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, body) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap().get("msg").unwrap(), "mail already exist");
+
+    let data = json!({"name": "register_bad_mail","mail": "register_bad_mail", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn delete_last_administrator_failed() {
+    let (status_code, body) = admin_delete("/api/users/1").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap().get("msg").unwrap(), "Invalid last administrator");
+}
+
+#[tokio::test]
+async fn delete_user_with_invalid_reassign_failed() {
+    let data = json!({"name": "reassign_test","mail": "reassign_test@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"mail": "reassign_test@test.local", "password": "password"}).to_string();
+    let (status_code, body) = post("/api/users/token", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let uid = body.unwrap().get("uid").unwrap().as_u64().unwrap();
+
+    let url = format!("/api/users/{}?reassign_to={}", uid, uid);
+    let (status_code, _) = admin_delete(&url).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let url = format!("/api/users/{}?reassign_to=1", uid);
+    let (status_code, _) = admin_delete(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn demote_last_administrator_failed() {
+    let data = json!({"name": "admin", "screenName": "admin", "mail": "admin@local.host", "url": "http://127.0.0.1", "group": "subscriber"}).to_string();
+    let (status_code, body) = admin_patch("/api/users/1", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(body.unwrap().get("msg").unwrap(), "Invalid last administrator");
+}
+
+#[tokio::test]
+async fn change_password_then_old_token_failed() {
+    let data = json!({"name": "token_test", "mail": "token_test@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (uid, token) = login("token_test@test.local", "password").await;
+    let url = format!("/api/users/{}", uid);
+    let (status_code, _) = token_request(Method::GET, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    let data = json!({"name": "token_test", "screenName": "token_test", "mail": "token_test@test.local", "url": "http://127.0.0.1", "password": "new_password", "group": "subscriber"}).to_string();
+    let (status_code, _) = token_request(Method::PATCH, &url, &token, data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = token_request(Method::GET, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (_, token) = login("token_test@test.local", "new_password").await;
+    let (status_code, _) = token_request(Method::GET, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn upload_then_get_avatar_success() {
+    let data = get_multipart("avatar.png", "image/png");
+    let (status_code, body) = admin_post_file("/api/users/1/avatar", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let avatar = body.unwrap().get("avatar").unwrap().as_str().unwrap().to_string();
+    assert!(avatar.starts_with("/usr/avatars/1-"));
+
+    let (status_code, _) = get("/api/users/1/avatar").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = get_multipart("avatar.html", "text/html");
+    let (status_code, _) = admin_post_file("/api/users/1/avatar", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_users_with_fields_success() {
+    let (status_code, body) = admin_get("/api/users/1?fields=uid,screen_name,url").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let keys: Vec<&String> = body.as_object().unwrap().keys().collect();
+    assert_eq!(keys.len(), 3);
+    assert!(body.get("mail").is_none());
+
+    let (status_code, body) = admin_get("/api/users/?fields=uid").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let user = &body["results"].as_array().unwrap()[0];
+    assert_eq!(user.as_object().unwrap().len(), 1);
+    assert!(user.get("uid").is_some());
+
+    let (status_code, _) = admin_get("/api/users/1?fields=uid,password").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn list_users_without_password_success() {
+    let (status_code, body) = admin_get("/api/users/?page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let users = body["results"].as_array().unwrap();
+    assert!(!users.is_empty());
+    for user in users {
+        assert!(user.get("password").is_none());
+        assert!(user.get("auth_code").is_none());
+    }
+
+    let (status_code, body) = admin_get("/api/users/1").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.unwrap().get("password").is_none());
+}
+
+#[tokio::test]
+async fn admin_create_user_success() {
+    let data = json!({"name": "created_editor", "mail": "created_editor@test.local", "url": "http://127.0.0.1", "password": "password", "group": "editor"}).to_string();
+    let (status_code, body) = admin_post("/api/admin/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let body = body.unwrap();
+    assert_eq!(body.get("group").unwrap(), "editor");
+    assert_eq!(body.get("screen_name").unwrap(), "created_editor");
+    assert!(body.get("password").is_none());
+
+    let (uid, _) = login("created_editor@test.local", "password").await;
+    assert_eq!(uid, body.get("uid").unwrap().as_u64().unwrap());
+
+    let data = json!({"name": "created_editor", "mail": "created_editor_2@test.local", "url": "http://127.0.0.1", "password": "password", "group": "editor"}).to_string();
+    let (status_code, _) = admin_post("/api/admin/users/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let data = json!({"name": "created_unknown", "mail": "created_unknown@test.local", "url": "http://127.0.0.1", "password": "password", "group": "visitor"}).to_string();
+    let (status_code, _) = admin_post("/api/admin/users/", data).await;
+    assert_eq!(status_code, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn get_me_success() {
+    let data = json!({"name": "me_test", "mail": "me_test@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (uid, token) = login("me_test@test.local", "password").await;
+    let (status_code, body) = token_request(Method::GET, "/api/me", &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["uid"].as_u64().unwrap(), uid);
+    assert_eq!(body["group"], "subscriber");
+    assert!(body.get("screen_name").is_some());
+    assert!(body.get("password").is_none());
+
+    let (status_code, _) =
+        token_request(Method::GET, "/api/me", "invalid.token", String::new()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = get("/api/me").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn create_use_then_revoke_api_token_success() {
+    let data = json!({"name": "pat_test", "mail": "pat_test@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (uid, token) = login("pat_test@test.local", "password").await;
+
+    let data = json!({"label": "ci", "scope": "read"}).to_string();
+    let (status_code, body) = token_request(Method::POST, "/api/me/tokens", &token, data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let body = body.unwrap();
+    let id = body["id"].as_str().unwrap().to_string();
+    let pat = body["token"].as_str().unwrap().to_string();
+    assert!(body.get("hash").is_none());
+
+    let (status_code, body) = token_request(Method::GET, "/api/me", &pat, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["uid"].as_u64().unwrap(), uid);
+
+    let data = json!({"label": "nested"}).to_string();
+    let (status_code, _) = token_request(Method::POST, "/api/me/tokens", &pat, data).await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+
+    let (status_code, body) =
+        token_request(Method::GET, "/api/me/tokens", &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["results"][0]["id"], id.as_str());
+
+    let url = format!("/api/me/tokens/{id}");
+    let (status_code, _) = token_request(Method::DELETE, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = token_request(Method::GET, "/api/me", &pat, String::new()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn admin_reset_password_then_old_token_failed() {
+    let data = json!({"name": "reset_test", "mail": "reset_test@test.local", "url": "http://127.0.0.1", "password": "password"}).to_string();
+    let (status_code, _) = post("/api/users/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (uid, token) = login("reset_test@test.local", "password").await;
+    let url = format!("/api/users/{}", uid);
+    let (status_code, _) = token_request(Method::GET, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = json!({"label": "reset"}).to_string();
+    let (status_code, body) = token_request(Method::POST, "/api/me/tokens", &token, data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let pat = body.unwrap()["token"].as_str().unwrap().to_string();
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    let reset_url = format!("/api/admin/users/{}/password-reset", uid);
+    let (status_code, body) = admin_post(&reset_url, json!({}).to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let password = body.unwrap()["password"].as_str().unwrap().to_string();
+    assert_eq!(password.len(), 16);
+
+    let (status_code, _) = token_request(Method::GET, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    let (status_code, _) = token_request(Method::GET, &url, &pat, String::new()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (_, token) = login("reset_test@test.local", &password).await;
+    let (status_code, _) = token_request(Method::GET, &url, &token, String::new()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = json!({"password": "chosen_password"}).to_string();
+    let (status_code, body) = admin_post(&reset_url, data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.unwrap().get("password").is_none());
+}